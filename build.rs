@@ -86,7 +86,7 @@ fn get_output_path() -> PathBuf {
     let build_type = std::env::var("PROFILE").unwrap();
     let path = Path::new(&manifest_dir_string).join("target").join(build_type);
 
-    PathBuf::from(path)
+    path
 }
 
 fn get_shader_output_path(build_path: PathBuf) -> PathBuf {
@@ -166,12 +166,14 @@ fn compile_source(stage: naga::ShaderStage, source: &str) {
         &pipeline_options_owned
     };
 
-    let mut options = naga::back::spv::Options::default();
+    let mut options = naga::back::spv::Options {
+        bounds_check_policies: naga::proc::BoundsCheckPolicies::default(),
+        ..Default::default()
+    };
 
-    options.bounds_check_policies = naga::proc::BoundsCheckPolicies::default();
     options.flags.set(
         naga::back::spv::WriterFlags::ADJUST_COORDINATE_SPACE,
-        !false,
+        true,
     );
 
     let output = naga::back::spv::write_vec(&module, &info, &options, Some(pipeline_options)).unwrap();
@@ -187,8 +189,10 @@ fn compile_source(stage: naga::ShaderStage, source: &str) {
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=./Cargo.lock");
+    // Watching the directory itself picks up newly added shaders, not just edits to known ones.
+    println!("cargo:rerun-if-changed=./res/shaders");
 
-    let sane_shader_extensions = vec!["vert", "frag"];
+    let sane_shader_extensions = ["vert", "frag"];
     for element in std::path::Path::new(r"./res/shaders/").read_dir().unwrap() {
         let path = &element.unwrap().path();
         if let Some(extension) = path.extension() {
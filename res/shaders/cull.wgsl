@@ -0,0 +1,47 @@
+// Frustum culls instances on the GPU. Every instance that survives gets copied into the output
+// instance buffer and counted into the indirect draw's instance count.
+//
+// This one's WGSL rather than GLSL like the rest because naga's GLSL frontend can't do atomics.
+
+struct CullUniform {
+    planes: array<vec4<f32>, 6>,
+    count: u32,
+    // Words per `InstanceRaw`. They're copied as `u32`s so the non-float fields (and any NaNs)
+    // come through bit for bit.
+    stride: u32,
+};
+
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0) var<uniform> cull: CullUniform;
+// World space center and radius of every instance's bounding sphere.
+@group(0) @binding(1) var<storage, read> spheres: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read> source: array<u32>;
+@group(0) @binding(3) var<storage, read_write> destination: array<u32>;
+@group(0) @binding(4) var<storage, read_write> draw_args: DrawIndexedIndirect;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= cull.count) {
+        return;
+    }
+
+    let sphere = spheres[index];
+    for (var plane = 0; plane < 6; plane = plane + 1) {
+        if (dot(cull.planes[plane].xyz, sphere.xyz) + cull.planes[plane].w < -sphere.w) {
+            return;
+        }
+    }
+
+    let slot = atomicAdd(&draw_args.instance_count, 1u);
+    for (var i = 0u; i < cull.stride; i = i + 1u) {
+        destination[slot * cull.stride + i] = source[index * cull.stride + i];
+    }
+}
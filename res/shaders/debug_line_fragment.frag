@@ -0,0 +1,13 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+layout(location = 0) smooth in vec3 line_color;
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+    frag_color = vec4(line_color, 1.0);
+
+    return;
+}
@@ -0,0 +1,25 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+struct Camera {
+    vec4 view_pos;
+    mat4x4 view_proj;
+};
+
+layout(binding = 0) uniform CameraData { Camera camera; };
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 color;
+layout(location = 0) smooth out vec3 line_color;
+
+void main() {
+    line_color = color;
+
+    // Same remapping as the scene's vertex shader, so the lines depth test against it properly.
+    gl_Position = camera.view_proj * vec4(position, 1.0);
+    gl_Position.yz = vec2(-gl_Position.y, gl_Position.z * 2.0 - gl_Position.w);
+
+    return;
+}
@@ -1,14 +1,21 @@
 #version 460
 
 %include res/shaders/h_vertex.vert
+%include res/shaders/h_shadow.frag
+%include res/shaders/h_material.frag
 
 layout(location = 0) smooth in vec3 vertex_color;
+layout(location = 1) smooth in vec4 vertex_tint;
+layout(location = 2) smooth in vec4 light_position;
+layout(location = 3) flat in uint tex_layer;
+layout(location = 4) smooth in vec3 model_position;
 layout(location = 0) out vec4 vertex_clip_position;
 
 void main() {
     VertexOutput vertex_out = VertexOutput(gl_FragCoord, vertex_color);
 
-    vertex_clip_position = vec4(vertex_out.color, 1.0);
+    vertex_clip_position = vec4(vertex_out.color * shadow_factor(light_position), 1.0) * vertex_tint
+        * material_color(model_position, tex_layer);
 
     return;
 }
@@ -0,0 +1,31 @@
+#version 460
+
+%include res/shaders/h_vertex.vert
+%include res/shaders/h_shadow.frag
+%include res/shaders/h_material.frag
+
+layout(location = 0) smooth in vec3 vertex_color;
+layout(location = 1) smooth in vec4 vertex_tint;
+layout(location = 2) smooth in vec4 light_position;
+layout(location = 3) flat in uint tex_layer;
+layout(location = 4) smooth in vec3 model_position;
+layout(location = 0) out vec4 vertex_clip_position;
+
+// `fragment.frag' for swapchains that aren't sRGB, which store whatever they're given as is.
+// Colors are worked out in linear space, so they have to be encoded by hand before going out.
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
+
+void main() {
+    VertexOutput vertex_out = VertexOutput(gl_FragCoord, vertex_color);
+    vec4 color = vec4(vertex_out.color * shadow_factor(light_position), 1.0) * vertex_tint
+        * material_color(model_position, tex_layer);
+
+    vertex_clip_position = vec4(linear_to_srgb(clamp(color.rgb, 0.0, 1.0)), color.a);
+
+    return;
+}
@@ -0,0 +1,66 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+layout(set = 0, binding = 0) uniform texture2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler scene_sampler;
+
+layout(location = 0) smooth in vec2 uv;
+layout(location = 0) out vec4 color_out;
+
+// The usual FXAA tuning knobs: how far along an edge to blur, and how much contrast an edge needs
+// before it gets touched at all.
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    // Edges are found by perceived brightness, which the (linear) colors have to be brought
+    // back to first. A square root is close enough to the sRGB curve for this.
+    return dot(sqrt(max(color, vec3(0.0))), vec3(0.299, 0.587, 0.114));
+}
+
+vec3 scene(vec2 position) {
+    return textureLod(sampler2D(scene_texture, scene_sampler), position, 0.0).rgb;
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(scene_texture, scene_sampler), 0));
+
+    vec4 center_color = textureLod(sampler2D(scene_texture, scene_sampler), uv, 0.0);
+    float center = luma(center_color.rgb);
+    float north_west = luma(scene(uv + vec2(-1.0, -1.0) * texel));
+    float north_east = luma(scene(uv + vec2(1.0, -1.0) * texel));
+    float south_west = luma(scene(uv + vec2(-1.0, 1.0) * texel));
+    float south_east = luma(scene(uv + vec2(1.0, 1.0) * texel));
+
+    float luma_min = min(center, min(min(north_west, north_east), min(south_west, south_east)));
+    float luma_max = max(center, max(max(north_west, north_east), max(south_west, south_east)));
+
+    // Across the edge is the way the brightness changes fastest, so along it is perpendicular.
+    vec2 direction = vec2(
+        -((north_west + north_east) - (south_west + south_east)),
+        (north_west + south_west) - (north_east + south_east)
+    );
+
+    float reduce = max((north_west + north_east + south_west + south_east) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 near = 0.5 * (
+        scene(uv + direction * (1.0 / 3.0 - 0.5)) +
+        scene(uv + direction * (2.0 / 3.0 - 0.5)));
+    vec3 far = near * 0.5 + 0.25 * (
+        scene(uv + direction * -0.5) +
+        scene(uv + direction * 0.5));
+
+    // Reaching further can run off the edge into something else entirely, in which case the
+    // shorter blur is the safer bet.
+    float far_luma = luma(far);
+    vec3 color = (far_luma < luma_min || far_luma > luma_max) ? near : far;
+
+    color_out = vec4(color, center_color.a);
+
+    return;
+}
@@ -0,0 +1,57 @@
+const float PI = 3.14159265359;
+
+layout(set = 0, binding = 0) uniform textureCube environment_texture;
+layout(set = 0, binding = 1) uniform sampler environment_sampler;
+layout(set = 0, binding = 2) uniform BakeData { uint face; float roughness; uint _padding0; uint _padding1; };
+
+// The direction through `uv' (0..1 from the top left) on cube face `face', in the usual
+// +X, -X, +Y, -Y, +Z, -Z order.
+vec3 face_direction(uint face, vec2 uv) {
+    vec2 st = uv * 2.0 - 1.0;
+
+    switch (face) {
+        case 0u: return normalize(vec3(1.0, -st.y, -st.x));
+        case 1u: return normalize(vec3(-1.0, -st.y, st.x));
+        case 2u: return normalize(vec3(st.x, 1.0, st.y));
+        case 3u: return normalize(vec3(st.x, -1.0, -st.y));
+        case 4u: return normalize(vec3(st.x, -st.y, 1.0));
+        default: return normalize(vec3(-st.x, -st.y, -1.0));
+    }
+}
+
+// Any two axes perpendicular to `normal' (and each other), for turning tangent space samples
+// around it.
+void tangent_frame(vec3 normal, out vec3 tangent, out vec3 bitangent) {
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+
+    tangent = normalize(cross(up, normal));
+    bitangent = cross(normal, tangent);
+}
+
+// The `i'th of `count' points of the Hammersley set, which covers the unit square far more
+// evenly than random points would.
+vec2 hammersley(uint i, uint count) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// A half vector around `normal', picked so that more land where a GGX lobe of `roughness' is
+// strongest.
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+    vec3 tangent;
+    vec3 bitangent;
+    tangent_frame(normal, tangent, bitangent);
+
+    return normalize(tangent * (cos(phi) * sin_theta) + bitangent * (sin(phi) * sin_theta) + normal * cos_theta);
+}
@@ -0,0 +1,10 @@
+layout(set = 2, binding = 0) uniform texture2DArray material_texture;
+layout(set = 2, binding = 1) uniform sampler material_sampler;
+
+// The material's color at `position' (in the mesh's own space), from layer `layer' of its
+// texture array (single textures are arrays of one, and any layer past the end reads the last).
+// Meshes don't carry texture coordinates, so the texture is projected along Z onto the XY plane,
+// repeating every unit.
+vec4 material_color(vec3 position, uint layer) {
+    return texture(sampler2DArray(material_texture, material_sampler), vec3(fract(position.xy), float(layer)));
+}
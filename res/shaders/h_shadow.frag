@@ -0,0 +1,27 @@
+layout(set = 1, binding = 0) uniform ShadowData { mat4x4 light_view_proj; vec4 shadow_params; };
+layout(set = 1, binding = 1) uniform texture2D shadow_map;
+layout(set = 1, binding = 2) uniform samplerShadow shadow_sampler;
+
+// How much light is left in full shadow.
+const float SHADOW_AMBIENT = 0.4;
+
+// 1.0 when `light_position' (the fragment in the light's clip space) can see the light, down to
+// `SHADOW_AMBIENT' when something nearer the light covers it. Anything outside the shadow map
+// counts as lit.
+float shadow_factor(vec4 light_position) {
+    vec3 ndc = light_position.xyz / light_position.w;
+    vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    // The shadow pass stretches its depth the same way every vertex shader here does, so the map
+    // holds `z * 2 - w' rather than `z'.
+    float depth = ndc.z * 2.0 - 1.0;
+
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) || depth > 1.0) {
+        return 1.0;
+    }
+
+    // The bias pushes the comparison a little towards the light, so surfaces don't shadow
+    // themselves from the map's limited precision.
+    float lit = texture(sampler2DShadow(shadow_map, shadow_sampler), vec3(uv, depth - shadow_params.x));
+
+    return mix(SHADOW_AMBIENT, 1.0, lit);
+}
@@ -0,0 +1,53 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+%include res/shaders/h_ibl.frag
+
+layout(location = 0) smooth in vec2 uv;
+layout(location = 0) out vec4 brdf_out;
+
+const uint SAMPLE_COUNT = 1024u;
+
+float geometry_schlick_ggx(float n_dot_v, float roughness) {
+    // The image based lighting flavour of `k', not the analytic lights' one.
+    float k = roughness * roughness / 2.0;
+
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// The split sum's second half: the scale (red) and bias (green) to apply to F0 for a given
+// `n_dot_v' (across) and roughness (down).
+void main() {
+    float n_dot_v = max(uv.x, 0.001);
+    float roughness = uv.y;
+
+    vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+
+    float scale = 0.0;
+    float bias = 0.0;
+
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 half_vector = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, half_vector) * half_vector - view);
+
+        float n_dot_l = max(light.z, 0.0);
+        float n_dot_h = max(half_vector.z, 0.0);
+        float v_dot_h = max(dot(view, half_vector), 0.0);
+
+        if (n_dot_l > 0.0) {
+            float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            float visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+
+    brdf_out = vec4(scale / float(SAMPLE_COUNT), bias / float(SAMPLE_COUNT), 0.0, 1.0);
+
+    return;
+}
@@ -0,0 +1,40 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+%include res/shaders/h_ibl.frag
+
+layout(location = 0) smooth in vec2 uv;
+layout(location = 0) out vec4 irradiance_out;
+
+// Radians between samples, in both directions over the hemisphere.
+const float SAMPLE_DELTA = 0.05;
+
+// The cosine weighted average of the environment over the hemisphere around each direction,
+// i.e. the diffuse light arriving at a surface facing that way.
+void main() {
+    vec3 normal = face_direction(face, uv);
+
+    vec3 tangent;
+    vec3 bitangent;
+    tangent_frame(normal, tangent, bitangent);
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+
+    for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+            vec3 local = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = tangent * local.x + bitangent * local.y + normal * local.z;
+
+            irradiance += textureLod(samplerCube(environment_texture, environment_sampler), direction, 0.0).rgb
+                * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+
+    irradiance_out = vec4(PI * irradiance / samples, 1.0);
+
+    return;
+}
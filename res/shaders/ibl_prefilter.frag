@@ -0,0 +1,36 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+%include res/shaders/h_ibl.frag
+
+layout(location = 0) smooth in vec2 uv;
+layout(location = 0) out vec4 prefiltered_out;
+
+const uint SAMPLE_COUNT = 512u;
+
+// The environment blurred by a GGX lobe of `roughness', as seen straight down the reflection
+// (the usual split sum assumption that the view and normal line up).
+void main() {
+    vec3 normal = face_direction(face, uv);
+    vec3 view = normal;
+
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 half_vector = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, half_vector) * half_vector - view);
+        float n_dot_l = max(dot(normal, light), 0.0);
+
+        if (n_dot_l > 0.0) {
+            color += textureLod(samplerCube(environment_texture, environment_sampler), light, 0.0).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+
+    prefiltered_out = vec4(color / max(total_weight, 0.0001), 1.0);
+
+    return;
+}
@@ -0,0 +1,17 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+layout(location = 0) smooth out vec2 uv;
+
+void main() {
+    // A single triangle big enough to cover the whole face.
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2)) * 2.0 - 1.0;
+
+    // Texture coordinates run top to bottom, clip space bottom to top.
+    uv = vec2(position.x, -position.y) * 0.5 + 0.5;
+    gl_Position = vec4(position.x, -position.y, 0.0, 1.0);
+
+    return;
+}
@@ -0,0 +1,10 @@
+#version 460
+
+layout(location = 0) flat in uint instance_id;
+layout(location = 0) out uint picked_id;
+
+void main() {
+    picked_id = instance_id;
+
+    return;
+}
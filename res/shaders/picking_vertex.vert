@@ -0,0 +1,24 @@
+#version 460
+
+%include res/shaders/h_vertex.vert
+
+layout(binding = 0) uniform CameraData { Camera camera; };
+
+layout(location = 0) in vec3 model_matrix_0;
+layout(location = 5) in vec4 model_matrix_5;
+layout(location = 6) in vec4 model_matrix_6;
+layout(location = 7) in vec4 model_matrix_7;
+layout(location = 8) in vec4 model_matrix_8;
+layout(location = 0) flat out uint instance_id;
+
+void main() {
+    mat4x4 model_matrix = mat4x4(model_matrix_5, model_matrix_6, model_matrix_7, model_matrix_8);
+
+    // Zero is left for the background.
+    instance_id = uint(gl_InstanceIndex) + 1u;
+
+    gl_Position = (camera.view_proj * model_matrix) * vec4(model_matrix_0, 1.0);
+    gl_Position.yz = vec2(-gl_Position.y, gl_Position.z * 2.0 - gl_Position.w);
+
+    return;
+}
@@ -0,0 +1,16 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+layout(binding = 0) uniform textureCube sky_texture;
+layout(binding = 1) uniform sampler sky_sampler;
+
+layout(location = 0) smooth in vec3 sky_direction;
+layout(location = 0) out vec4 sky_color;
+
+void main() {
+    sky_color = texture(samplerCube(sky_texture, sky_sampler), sky_direction);
+
+    return;
+}
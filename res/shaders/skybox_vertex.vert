@@ -0,0 +1,21 @@
+#version 460
+
+precision highp float;
+precision highp int;
+
+layout(binding = 2) uniform SkyboxData { mat4x4 inverse_view_proj; };
+
+layout(location = 0) smooth out vec3 sky_direction;
+
+void main() {
+    // A single triangle big enough to cover the whole screen.
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2)) * 2.0 - 1.0;
+    vec4 world = inverse_view_proj * vec4(position, 1.0, 1.0);
+
+    sky_direction = world.xyz / world.w;
+
+    // Sits right on the far plane, so anything drawn afterwards ends up in front of it.
+    gl_Position = vec4(position.x, -position.y, 1.0, 1.0);
+
+    return;
+}
@@ -3,6 +3,7 @@
 %include res/shaders/h_vertex.vert
 
 layout(binding = 0) uniform CameraData { Camera camera; };
+layout(set = 1, binding = 0) uniform ShadowData { mat4x4 light_view_proj; vec4 shadow_params; };
 
 layout(location = 0) in vec3 model_matrix_0;
 layout(location = 1) in vec3 model_matrix_1;
@@ -10,7 +11,13 @@ layout(location = 5) in vec4 model_matrix_5;
 layout(location = 6) in vec4 model_matrix_6;
 layout(location = 7) in vec4 model_matrix_7;
 layout(location = 8) in vec4 model_matrix_8;
+layout(location = 12) in vec4 instance_color;
+layout(location = 13) in uint instance_tex_layer;
 layout(location = 0) smooth out vec3 vertex_color;
+layout(location = 1) smooth out vec4 vertex_tint;
+layout(location = 2) smooth out vec4 light_position;
+layout(location = 3) flat out uint tex_layer;
+layout(location = 4) smooth out vec3 model_position;
 
 void main() {
     VertexOutput vertex_out = VertexOutput(vec4(0.0), vec3(0.0));
@@ -21,6 +28,10 @@ void main() {
     vertex_out.color = model.color;
     vertex_out.clip_position = ((camera.view_proj * model_matrix) * vec4(model.position, 1.0));
     vertex_color = vertex_out.color;
+    vertex_tint = instance_color;
+    light_position = (light_view_proj * model_matrix) * vec4(model.position, 1.0);
+    tex_layer = instance_tex_layer;
+    model_position = model.position;
 
     gl_Position = vertex_out.clip_position;
     gl_Position.yz = vec2(-gl_Position.y, gl_Position.z * 2.0 - gl_Position.w);
@@ -0,0 +1,228 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc::{channel, Receiver, Sender}};
+use std::thread::JoinHandle;
+use anyhow::{Context, Result};
+use log::warn;
+
+use crate::texture::Texture;
+use crate::ecs::world::{EntityId, World};
+use crate::ecs::component::material::MaterialComponent;
+
+pub const DEFAULT_MAX_WORKERS: usize = 4;
+
+/// Stands in for textures that haven't loaded (or failed to). Magenta, so it's obvious.
+pub const PLACEHOLDER_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Refers to a texture queued with `AssetLoader::load_texture_async`, whether it's done or not.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AssetHandle(u64);
+
+type Job = (AssetHandle, PathBuf);
+type Decoded = (AssetHandle, Result<(Vec<u8>, (u32, u32))>);
+
+/// Decodes textures on a pool of worker threads, so loading a pile of them doesn't stall the
+/// frame. Only decoding happens off the main thread: the GPU upload waits for `poll_ready`.
+pub struct AssetLoader {
+    // Only an `Option` so `drop` can hang up on the workers before joining them.
+    jobs: Option<Sender<Job>>,
+    results: Receiver<Decoded>,
+    workers: Vec<JoinHandle<()>>,
+    next_handle: u64,
+    pending: HashSet<AssetHandle>,
+    ready: HashSet<AssetHandle>,
+    // Loaded textures that no material has claimed yet (see `take_texture').
+    unclaimed: HashMap<AssetHandle, Texture>,
+    // Entities whose material gets swapped in once the handle is ready.
+    materials: HashMap<AssetHandle, Vec<EntityId>>,
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetLoader {
+    /// One worker per core, up to `DEFAULT_MAX_WORKERS`.
+    pub fn new() -> Self {
+        let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+        Self::with_workers(workers.min(DEFAULT_MAX_WORKERS))
+    }
+
+    pub fn with_workers(workers: usize) -> Self {
+        let (jobs, job_receiver) = channel::<Job>();
+        let (result_sender, results) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..workers.max(1)).map(|_| {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+
+            std::thread::spawn(move || loop {
+                // The lock is only held while waiting for a job, not while decoding it.
+                let job = match job_receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+
+                let (handle, path) = match job {
+                    Ok(job) => job,
+                    // The loader is gone.
+                    Err(_) => return,
+                };
+
+                if result_sender.send((handle, decode(&path))).is_err() {
+                    return;
+                }
+            })
+        }).collect();
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+            next_handle: 0,
+            pending: HashSet::new(),
+            ready: HashSet::new(),
+            unclaimed: HashMap::new(),
+            materials: HashMap::new(),
+        }
+    }
+
+    pub fn load_texture_async(&mut self, path: impl AsRef<Path>) -> AssetHandle {
+        let handle = AssetHandle(self.next_handle);
+        self.next_handle += 1;
+
+        if let Some(jobs) = &self.jobs {
+            if jobs.send((handle, path.as_ref().to_path_buf())).is_ok() {
+                self.pending.insert(handle);
+            }
+        }
+
+        handle
+    }
+
+    /// Gives `entity` a placeholder material right away, and swaps in the real one once `handle`
+    /// is ready (in `poll_ready`). If it's ready already, the entity gets it straight away, as
+    /// long as nothing else has claimed the texture yet.
+    pub fn bind_material(&mut self, world: &mut World, device: &wgpu::Device, queue: &wgpu::Queue, entity: EntityId, handle: AssetHandle) {
+        if let Some(texture) = self.unclaimed.remove(&handle) {
+            world.insert(entity, MaterialComponent::from_texture(device, texture, "Loaded Material"));
+            return;
+        }
+
+        world.insert(entity, MaterialComponent::from_texture(device, placeholder(device, queue), "Placeholder Material"));
+
+        if self.ready.contains(&handle) {
+            warn!("Texture {:?} was already claimed by another material. Keeping the placeholder.", handle);
+        } else {
+            self.materials.entry(handle).or_default().push(entity);
+        }
+    }
+
+    /// Uploads whatever the workers have finished decoding, and swaps it into the materials bound
+    /// to it (each gets its own copy). Returns the handles that became ready.
+    pub fn poll_ready(&mut self, world: &mut World, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<AssetHandle> {
+        let mut newly_ready = vec![];
+
+        for (handle, decoded) in self.results.try_iter().collect::<Vec<_>>() {
+            self.pending.remove(&handle);
+            let entities = self.materials.remove(&handle).unwrap_or_default();
+
+            let (rgba, dimensions) = match decoded {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    warn!("Unable to load texture {:?}: {:#}. Keeping the placeholder.", handle, e);
+                    continue;
+                },
+            };
+
+            if entities.is_empty() {
+                self.unclaimed.insert(handle, Texture::from_rgba(device, queue, &rgba, dimensions, Some("Loaded Texture")));
+            }
+
+            for entity in entities {
+                if world.is_alive(entity) {
+                    world.insert(entity, MaterialComponent::from_bytes(device, queue, &rgba, dimensions, "Loaded Material"));
+                }
+            }
+
+            self.ready.insert(handle);
+            newly_ready.push(handle);
+        }
+
+        newly_ready
+    }
+
+    pub fn is_ready(&self, handle: AssetHandle) -> bool {
+        self.ready.contains(&handle)
+    }
+
+    /// Still being decoded, or decoded but not yet picked up by `poll_ready`.
+    pub fn is_pending(&self, handle: AssetHandle) -> bool {
+        self.pending.contains(&handle)
+    }
+
+    /// Hands over a loaded texture no material was bound to.
+    pub fn take_texture(&mut self, handle: AssetHandle) -> Option<Texture> {
+        self.unclaimed.remove(&handle)
+    }
+}
+
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        // Hanging up makes every idle worker's `recv` fail, so they all wind down.
+        self.jobs = None;
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A 1x1 `PLACEHOLDER_COLOR` texture.
+pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+    Texture::from_rgba(device, queue, &PLACEHOLDER_COLOR, (1, 1), Some("Placeholder Texture"))
+}
+
+fn decode(path: &Path) -> Result<(Vec<u8>, (u32, u32))> {
+    let img = image::open(path).with_context(|| format!("Unable to decode {}", path.display()))?;
+    let rgba = img.to_rgba8();
+    let dimensions = rgba.dimensions();
+
+    Ok((rgba.into_raw(), dimensions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn queued_load_is_ready_only_after_the_worker_finishes() {
+        let gpu = test_renderer();
+        let path = std::env::temp_dir().join(format!("sit-asset-{}.png", std::process::id()));
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 255, 0, 255])).save(&path).unwrap();
+
+        let mut world = World::new();
+        let mut loader = AssetLoader::with_workers(1);
+        let handle = loader.load_texture_async(&path);
+        assert!(!loader.is_ready(handle));
+        assert!(loader.is_pending(handle));
+
+        // The worker's on another thread, so give it a moment.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !loader.is_ready(handle) && std::time::Instant::now() < deadline {
+            loader.poll_ready(&mut world, &gpu.device, &gpu.queue);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loader.is_ready(handle));
+        assert!(!loader.is_pending(handle));
+        assert!(loader.take_texture(handle).is_some());
+    }
+}
@@ -6,6 +6,8 @@ use winit::{
 };
 use cgmath::{perspective, Vector3, Point3, Rad, Matrix4, SquareMatrix, InnerSpace};
 
+use crate::math::convert::{mat4_to_array, vec4_to_array};
+
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
@@ -14,7 +16,7 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
-const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+pub const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -23,17 +25,27 @@ pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
 }
 
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
             view_position: [0.0; 4],
-            view_proj: cgmath::Matrix4::identity().into(),
+            view_proj: mat4_to_array(cgmath::Matrix4::identity()),
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
-        self.view_position = camera.position.to_homogeneous().into();
-        self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into()
+        self.update_raw(camera.position, projection.calc_matrix() * camera.calc_matrix())
+    }
+
+    pub fn update_raw(&mut self, view_position: Point3<f32>, view_proj: Matrix4<f32>) {
+        self.view_position = vec4_to_array(view_position.to_homogeneous());
+        self.view_proj = mat4_to_array(view_proj)
     }
 }
 
@@ -0,0 +1,220 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Weak};
+
+use crate::ecs::component::Component;
+
+/// A shared reference to an asset in an `Assets<T>`. Clones point at the same asset, and once
+/// every one of them is gone the asset can be reclaimed (see `Assets::remove_unused`).
+///
+/// Handles are components too, so entities can carry a `Handle<MeshComponent>` (say) instead of
+/// owning a copy of the mesh.
+pub struct Handle<T> {
+    id: Arc<u64>,
+    // `fn() -> T` so the handle is `Send` and `Sync` whatever `T` is: it doesn't hold one.
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    /// Stable for as long as the asset is stored, and never reused by the same `Assets`.
+    pub fn id(&self) -> u64 {
+        *self.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: Arc::clone(&self.id),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Handle({})", self.id())
+    }
+}
+
+impl<T: 'static> Component for Handle<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "Handle"
+    }
+}
+
+struct Entry<T> {
+    asset: T,
+    handles: Weak<u64>,
+    key: Option<String>,
+}
+
+/// Stores assets (meshes, materials, ...) once, however many things use them. `load` goes by a
+/// key (usually the path it came from), so loading the same thing twice hands back the same
+/// handle instead of a second copy of its buffers.
+///
+/// Usually kept as a world resource, one per asset type. Assets aren't freed the moment their
+/// last handle drops, since that would need every handle to reach back into the store. Instead
+/// `remove_unused` (which `add` and `load` also run) drops the ones nothing refers to any more,
+/// GPU buffers and all.
+pub struct Assets<T> {
+    entries: HashMap<u64, Entry<T>>,
+    keys: HashMap<String, u64>,
+    next_id: u64,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Assets<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            keys: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Stores `asset` without a key, so it's never deduplicated.
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        self.insert(asset, None)
+    }
+
+    /// The handle already stored under `key`, or else a new one for whatever `load` returns.
+    pub fn load(&mut self, key: &str, load: impl FnOnce() -> T) -> Handle<T> {
+        match self.try_load(key, || Ok::<_, std::convert::Infallible>(load())) {
+            Ok(handle) => handle,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `load`, for loaders that can fail. Nothing is stored if they do.
+    pub fn try_load<E>(&mut self, key: &str, load: impl FnOnce() -> Result<T, E>) -> Result<Handle<T>, E> {
+        if let Some(handle) = self.keys.get(key).and_then(|id| self.handle(*id)) {
+            return Ok(handle);
+        }
+
+        Ok(self.insert(load()?, Some(key.to_owned())))
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries.get(&handle.id()).map(|entry| &entry.asset)
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entries.get_mut(&handle.id()).map(|entry| &mut entry.asset)
+    }
+
+    /// How many assets are stored, including any unused ones `remove_unused` hasn't got to yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops every asset without a handle left, and returns how many there were.
+    pub fn remove_unused(&mut self) -> usize {
+        let unused = self.entries.iter()
+            .filter(|(_, entry)| entry.handles.strong_count() == 0)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for id in &unused {
+            if let Some(key) = self.entries.remove(id).and_then(|entry| entry.key) {
+                self.keys.remove(&key);
+            }
+        }
+
+        unused.len()
+    }
+
+    fn insert(&mut self, asset: T, key: Option<String>) -> Handle<T> {
+        self.remove_unused();
+
+        let id = Arc::new(self.next_id);
+        self.next_id += 1;
+
+        if let Some(key) = &key {
+            self.keys.insert(key.clone(), *id);
+        }
+
+        self.entries.insert(*id, Entry {
+            asset,
+            handles: Arc::downgrade(&id),
+            key,
+        });
+
+        Handle {
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    // A new handle to an asset that's still stored, if anything still refers to it.
+    fn handle(&self, id: u64) -> Option<Handle<T>> {
+        let id = self.entries.get(&id)?.handles.upgrade()?;
+
+        Some(Handle {
+            id,
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_the_same_path_twice_shares_one_copy() {
+        let mut names = Assets::<String>::new();
+        let mut loads = 0;
+
+        let first = names.load("names/default", || { loads += 1; "default".to_owned() });
+        let second = names.load("names/default", || { loads += 1; "default".to_owned() });
+
+        assert_eq!(first, second);
+        assert_eq!(loads, 1);
+        assert_eq!(names.len(), 1);
+        assert!(std::ptr::eq(names.get(&first).unwrap(), names.get(&second).unwrap()));
+    }
+
+    #[test]
+    fn assets_without_handles_are_removed() {
+        let mut names = Assets::<String>::new();
+        let kept = names.load("kept", || "kept".to_owned());
+        drop(names.load("dropped", || "dropped".to_owned()));
+
+        assert_eq!(names.remove_unused(), 1);
+        assert_eq!(names.get(&kept).map(String::as_str), Some("kept"));
+    }
+}
@@ -0,0 +1,104 @@
+use cgmath::prelude::*;
+
+use crate::ecs::{
+    world::World,
+    system::System,
+    component::camera::CameraComponent,
+    component::instance::{Instance, InstanceComponent},
+    component::billboard::{BillboardComponent, BillboardMode},
+};
+
+/// Turns every instance of every entity with a `BillboardComponent` so its front (+Z, the side a
+/// `MeshComponent::quad` shows) faces the active camera: the `CameraComponent` resource if
+/// there is one, otherwise the first camera entity. The instances are only marked dirty, so
+/// they go up with the next `update_if_dirty`.
+///
+/// Run it after `TransformPropagationSystem`, since the camera is brought into each entity's
+/// space through its `transform`.
+pub struct BillboardSystem;
+
+impl System for BillboardSystem {
+    fn run(&mut self, world: &mut World, _dt: f32) {
+        let camera = world.resource::<CameraComponent>()
+            .or_else(|| world.query::<CameraComponent>().map(|(_, camera)| camera).next());
+        let eye = match camera {
+            Some(camera) => camera.eye,
+            None => return,
+        };
+
+        for entity in world.entities_with::<BillboardComponent>() {
+            let mode = match world.get::<BillboardComponent>(entity) {
+                Some(billboard) => billboard.mode,
+                None => continue,
+            };
+            let instances = match world.get_mut::<InstanceComponent>(entity) {
+                Some(instances) => instances,
+                None => continue,
+            };
+
+            // A transform that can't be inverted has squashed everything flat; nothing to face.
+            let eye = match instances.transform.invert() {
+                Some(inverse) => inverse.transform_point(eye).to_vec(),
+                None => continue,
+            };
+
+            for instance in instances.iter_mut() {
+                face(instance, eye, mode);
+            }
+        }
+    }
+}
+
+// Turns `instance` so its front faces `eye`, both in the same space.
+fn face(instance: &mut Instance, eye: cgmath::Vector3<f32>, mode: BillboardMode) {
+    let mut to_eye = eye - instance.position;
+    if mode == BillboardMode::Cylindrical {
+        to_eye.y = 0.0;
+    }
+
+    // `look_at` points -Z at its target, so aim it directly away from the camera.
+    let target = instance.position - to_eye;
+    instance.look_at(target, cgmath::Vector3::unit_y());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::instance::InstanceComponentBuilder;
+    use crate::math::approx::{approx_eq_vec3, DEFAULT_EPSILON};
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn spherical_billboard_faces_a_camera_on_positive_z() {
+        let gpu = test_renderer();
+        let mut world = World::new();
+        world.insert_resource(CameraComponent::new(&gpu.device, cgmath::Point3::new(0.0, 0.0, 5.0),
+            cgmath::Point3::origin(), 1.0, cgmath::Deg(45.0)));
+
+        let entity = world.spawn();
+        world.insert(entity, BillboardComponent::new(BillboardMode::Spherical));
+        world.insert(entity, InstanceComponentBuilder::new()
+            .instance(Instance::new(cgmath::Vector3::zero(), cgmath::Quaternion::from_angle_y(cgmath::Deg(90.0))))
+            .build(&gpu.device));
+
+        BillboardSystem.run(&mut world, 0.0);
+
+        let instance = world.get::<InstanceComponent>(entity).unwrap().instances[0];
+        let forward = instance.rotation.rotate_vector(cgmath::Vector3::unit_z());
+        assert!(approx_eq_vec3(forward, cgmath::Vector3::unit_z(), DEFAULT_EPSILON), "{:?}", forward);
+    }
+
+        #[test]
+        fn cylindrical_billboard_stays_upright_under_a_raised_camera() {
+            let eye = cgmath::Vector3::new(0.0, 5.0, 5.0);
+            let (mut spherical, mut cylindrical) = (Instance::default(), Instance::default());
+
+            face(&mut spherical, eye, BillboardMode::Spherical);
+            face(&mut cylindrical, eye, BillboardMode::Cylindrical);
+
+            let forward = |instance: Instance| instance.rotation.rotate_vector(cgmath::Vector3::unit_z());
+            assert!(approx_eq_vec3(forward(spherical), eye.normalize(), DEFAULT_EPSILON), "{:?}", forward(spherical));
+            assert!(approx_eq_vec3(forward(cylindrical), cgmath::Vector3::unit_z(), DEFAULT_EPSILON), "{:?}", forward(cylindrical));
+        }
+}
@@ -0,0 +1,46 @@
+use crate::ecs::component::Component;
+use crate::ecs::world::{EntityId, World};
+
+/// A group of components inserted together, so spawning something doesn't take one `insert`
+/// per component. Implemented for tuples of up to six components.
+pub trait Bundle {
+    fn insert_into(self, world: &mut World, entity: EntityId);
+}
+
+macro_rules! impl_bundle {
+    ($($component: ident),+) => {
+        impl<$($component: Component + 'static),+> Bundle for ($($component,)+) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, entity: EntityId) {
+                let ($($component,)+) = self;
+                $(world.insert(entity, $component);)+
+            }
+        }
+    }
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+impl_bundle!(A, B, C, D);
+impl_bundle!(A, B, C, D, E);
+impl_bundle!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::name::NameComponent;
+    use crate::ecs::component::transform::TransformComponent;
+    use crate::ecs::component::velocity::VelocityComponent;
+
+    #[test]
+    fn three_component_bundle_is_queryable() {
+        let mut world = World::new();
+        let velocity = VelocityComponent::new(cgmath::Vector3::new(1.0, 0.0, 0.0), cgmath::Vector3::new(0.0, 0.0, 0.0));
+        let entity = world.spawn_bundle((TransformComponent::identity(), NameComponent("Bundled".to_owned()), velocity));
+
+        assert!(world.query::<TransformComponent>().any(|(found, _)| found == entity));
+        assert_eq!(world.query::<NameComponent>().find(|(found, _)| *found == entity).map(|(_, name)| name.0.as_str()), Some("Bundled"));
+        assert_eq!(world.query::<VelocityComponent>().find(|(found, _)| *found == entity).map(|(_, velocity)| *velocity), Some(velocity));
+    }
+}
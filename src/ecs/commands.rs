@@ -0,0 +1,174 @@
+use log::warn;
+
+use crate::ecs::bundle::Bundle;
+use crate::ecs::component::Component;
+use crate::ecs::world::{EntityId, World};
+
+/// An entity queued with `Commands::spawn`, which doesn't exist until the commands are applied.
+/// Commands queued after the spawn can still target it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PendingEntity(usize);
+
+/// Who a queued command applies to: an entity that's already there, or one spawned by an earlier
+/// command in the same buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CommandEntity {
+    Existing(EntityId),
+    Pending(PendingEntity),
+}
+
+impl From<EntityId> for CommandEntity {
+    fn from(entity: EntityId) -> Self {
+        CommandEntity::Existing(entity)
+    }
+}
+
+impl From<PendingEntity> for CommandEntity {
+    fn from(entity: PendingEntity) -> Self {
+        CommandEntity::Pending(entity)
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut World, &mut Vec<EntityId>)>;
+
+/// Structural changes (spawns, despawns, inserts) recorded now and made later with `apply`, for
+/// when the world is borrowed by a query that can't have them made underneath it. Commands are
+/// applied in the order they were queued.
+///
+/// `Scheduler` hands one to each system (see `System::run_with_commands`) and applies it as soon
+/// as the system is done.
+pub struct Commands {
+    commands: Vec<Command>,
+    spawned: usize,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self {
+            commands: vec![],
+            spawned: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn spawn(&mut self) -> PendingEntity {
+        let pending = PendingEntity(self.spawned);
+        self.spawned += 1;
+
+        self.commands.push(Box::new(|world, spawned| spawned.push(world.spawn())));
+
+        pending
+    }
+
+    pub fn spawn_bundle<B: Bundle + 'static>(&mut self, bundle: B) -> PendingEntity {
+        let pending = PendingEntity(self.spawned);
+        self.spawned += 1;
+
+        self.commands.push(Box::new(move |world, spawned| spawned.push(world.spawn_bundle(bundle))));
+
+        pending
+    }
+
+    pub fn despawn(&mut self, entity: impl Into<CommandEntity>) {
+        let entity = entity.into();
+
+        self.commands.push(Box::new(move |world, spawned| {
+            if let Some(entity) = resolve(entity, spawned) {
+                world.despawn(entity);
+            }
+        }));
+    }
+
+    pub fn insert<C: Component + 'static>(&mut self, entity: impl Into<CommandEntity>, component: C) {
+        let entity = entity.into();
+
+        self.commands.push(Box::new(move |world, spawned| {
+            if let Some(entity) = resolve(entity, spawned) {
+                world.insert(entity, component);
+            }
+        }));
+    }
+
+    /// Makes every queued change, leaving the buffer empty (and ready for reuse). Returns the
+    /// entities that got spawned, in the order their `spawn`s were queued.
+    pub fn apply(&mut self, world: &mut World) -> Vec<EntityId> {
+        let mut spawned = Vec::with_capacity(self.spawned);
+
+        for command in self.commands.drain(..) {
+            command(world, &mut spawned);
+        }
+
+        self.spawned = 0;
+        spawned
+    }
+}
+
+/// `None` (with a warning) for a pending entity that isn't one of `spawned`, e.g. one handed out
+/// by a buffer that's since been applied.
+fn resolve(entity: CommandEntity, spawned: &[EntityId]) -> Option<EntityId> {
+    match entity {
+        CommandEntity::Existing(entity) => Some(entity),
+        CommandEntity::Pending(PendingEntity(index)) => {
+            let entity = spawned.get(index).copied();
+            if entity.is_none() {
+                warn!("Pending entity {} hasn't been spawned by this command buffer. Skipping the command.", index);
+            }
+
+            entity
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::name::NameComponent;
+    use crate::ecs::component::transform::TransformComponent;
+
+    #[test]
+    fn spawn_during_a_query_only_happens_on_apply() {
+        let mut world = World::new();
+        world.spawn_bundle((TransformComponent::identity(),));
+        world.spawn_bundle((TransformComponent::identity(),));
+
+        let mut commands = Commands::new();
+        for (entity, _) in world.query::<TransformComponent>() {
+            let pending = commands.spawn();
+            commands.insert(pending, NameComponent(format!("Spawned by {:?}", entity)));
+        }
+
+        assert_eq!(world.query::<NameComponent>().count(), 0);
+        assert_eq!(commands.len(), 4);
+
+        let spawned = commands.apply(&mut world);
+        assert_eq!(spawned.len(), 2);
+        assert!(spawned.iter().all(|entity| world.get::<NameComponent>(*entity).is_some()));
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn stale_pending_entity_is_skipped() {
+        let mut world = World::new();
+        let mut commands = Commands::new();
+        let stale = commands.spawn();
+        commands.apply(&mut world);
+
+        // Nothing has been spawned by this round of commands, so there's nothing for it to mean.
+        commands.insert(stale, NameComponent("Stale".to_owned()));
+        assert!(commands.apply(&mut world).is_empty());
+        assert_eq!(world.query::<NameComponent>().count(), 0);
+    }
+}
@@ -0,0 +1,40 @@
+use std::any::Any;
+
+use crate::ecs::component::Component;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BillboardMode {
+    /// Turns every which way to face the camera head on, like a particle.
+    #[default]
+    Spherical,
+    /// Only turns about Y, so it stays upright however high the camera goes, like a tree.
+    Cylindrical,
+}
+
+/// Has `BillboardSystem` keep every instance on the entity facing the active camera.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BillboardComponent {
+    pub mode: BillboardMode,
+}
+
+impl Component for BillboardComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "BillboardComponent"
+    }
+}
+
+impl BillboardComponent {
+    pub fn new(mode: BillboardMode) -> Self {
+        Self {
+            mode,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::any::Any;
+use cgmath::{ortho, perspective, Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::camera::{CameraUniform, OPENGL_TO_WGPU_MATRIX};
+use crate::ecs::component::Component;
+
+pub const DEFAULT_ZNEAR: f32 = 0.1;
+pub const DEFAULT_ZFAR: f32 = 5000.0;
+
+/// How a `CameraComponent` maps view space onto the screen. Orthographic cameras have no
+/// perspective divide, so things don't shrink with distance; handy for 2D, isometric views and
+/// shadow setups. The bounds are in view space, around the line from `eye` to `target`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { fovy: Rad<f32>, aspect: f32, znear: f32, zfar: f32 },
+    Orthographic { left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fovy, aspect, znear, zfar } => perspective(fovy, aspect, znear, zfar),
+            Projection::Orthographic { left, right, bottom, top, near, far } => ortho(left, right, bottom, top, near, far),
+        }
+    }
+
+    /// Perspective projections take `aspect` as is. Orthographic ones keep their height and
+    /// center, and widen or narrow around it to match.
+    pub fn set_aspect(&mut self, new_aspect: f32) {
+        match self {
+            Projection::Perspective { aspect, .. } => *aspect = new_aspect,
+            Projection::Orthographic { left, right, bottom, top, .. } => {
+                let center = (*left + *right) / 2.0;
+                let half_width = (*top - *bottom) / 2.0 * new_aspect;
+
+                *left = center - half_width;
+                *right = center + half_width;
+            },
+        }
+    }
+}
+
+pub struct CameraComponent {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    /// Perspective (with `DEFAULT_ZNEAR` and `DEFAULT_ZFAR`) unless set otherwise. Set `dirty`
+    /// after changing it by hand, or use `set_projection`.
+    pub projection: Projection,
+    pub uniform: CameraUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    // Set whenever something changes that the uniform doesn't know about yet.
+    pub dirty: bool,
+}
+
+impl Component for CameraComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "CameraComponent"
+    }
+}
+
+impl CameraComponent {
+    pub fn new<F: Into<Rad<f32>>>(device: &wgpu::Device, eye: Point3<f32>, target: Point3<f32>, aspect: f32, fovy: F) -> Self {
+        let up = Vector3::unit_y();
+        let projection = Projection::Perspective { fovy: fovy.into(), aspect, znear: DEFAULT_ZNEAR, zfar: DEFAULT_ZFAR };
+
+        let mut uniform = CameraUniform::new();
+        uniform.update_raw(eye, Self::calc_matrix(eye, target, up, &projection));
+
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Camera Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &Self::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("camera_bind_group"),
+        });
+
+        Self {
+            eye,
+            target,
+            up,
+            projection,
+            uniform,
+            buffer,
+            bind_group,
+            dirty: false,
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("camera_bind_group_layout"),
+        })
+    }
+
+    /// Swaps the projection, uniform and all (on the next `update_buffer`). Builder flavoured,
+    /// for setting up an orthographic camera straight away.
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.set_projection(projection);
+        self
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.dirty = true;
+    }
+
+    pub fn build_view_projection_matrix(&self) -> Matrix4<f32> {
+        Self::calc_matrix(self.eye, self.target, self.up, &self.projection)
+    }
+
+    fn calc_matrix(eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>, projection: &Projection) -> Matrix4<f32> {
+        let view = Matrix4::look_at_rh(eye, target, up);
+
+        OPENGL_TO_WGPU_MATRIX * projection.calc_matrix() * view
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        // A minimized window can report a height of 0, which would make the aspect NaN.
+        self.projection.set_aspect(width as f32 / height.max(1) as f32);
+        self.dirty = true;
+    }
+
+    pub fn update_buffer(&mut self, queue: &wgpu::Queue) {
+        self.uniform.update_raw(self.eye, self.build_view_projection_matrix());
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::approx::{approx_eq, DEFAULT_EPSILON};
+    use crate::render::headless::test_renderer;
+    use cgmath::{Matrix, Vector4};
+
+    fn perspective() -> Projection {
+        Projection::Perspective { fovy: cgmath::Deg(45.0).into(), aspect: 1.5, znear: DEFAULT_ZNEAR, zfar: DEFAULT_ZFAR }
+    }
+
+    #[test]
+    fn moving_eye_changes_matrix() {
+        let target = Point3::new(0.0, 0.0, 0.0);
+        let before = CameraComponent::calc_matrix(Point3::new(0.0, 1.0, 5.0), target, Vector3::unit_y(), &perspective());
+        let after = CameraComponent::calc_matrix(Point3::new(2.0, 1.0, 5.0), target, Vector3::unit_y(), &perspective());
+
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn resizing_to_800x600_sets_the_aspect() {
+        let gpu = test_renderer();
+        let mut camera = CameraComponent::new(&gpu.device, Point3::new(0.0, 1.0, 5.0), Point3::new(0.0, 0.0, 0.0), 1.0, cgmath::Deg(45.0));
+
+        camera.resize(800, 600);
+
+        let Projection::Perspective { aspect, .. } = camera.projection else { panic!("resize changed the projection") };
+        assert!(approx_eq(aspect, 4.0 / 3.0, DEFAULT_EPSILON), "{}", aspect);
+        assert!(camera.dirty);
+    }
+
+    #[test]
+    fn orthographic_camera_has_no_perspective_divide() {
+        let projection = Projection::Orthographic { left: -4.0, right: 4.0, bottom: -3.0, top: 3.0, near: 0.1, far: 100.0 };
+        let matrix = CameraComponent::calc_matrix(Point3::new(1.0, 2.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y(), &projection);
+
+        assert_eq!(matrix.row(3), Vector4::new(0.0, 0.0, 0.0, 1.0));
+    }
+
+        #[test]
+        fn orthographic_aspect_keeps_the_height_and_center() {
+            let mut projection = Projection::Orthographic { left: 0.0, right: 2.0, bottom: -3.0, top: 3.0, near: 0.1, far: 100.0 };
+
+            projection.set_aspect(4.0 / 3.0);
+
+            assert_eq!(projection, Projection::Orthographic { left: -3.0, right: 5.0, bottom: -3.0, top: 3.0, near: 0.1, far: 100.0 });
+        }
+}
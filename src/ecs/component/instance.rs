@@ -1,37 +1,280 @@
 use std::any::Any;
+use std::collections::HashMap;
 use cgmath::prelude::*;
+use rand::{Rng, SeedableRng};
 use wgpu::util::DeviceExt;
 
 use crate::ecs::component::Component;
+use crate::error::WafError;
+use crate::render::frustum::Frustum;
+use crate::render::error_scope::with_error_scope;
+use crate::render::buffer_pool::{BufferPool, PooledBuffer};
+use crate::math::convert::{mat4_to_array, array_to_mat4, mat3_to_array, vec4_to_array, array_to_vec4,
+    quat_to_array, array_to_quat};
 
 const DEFAULT_INSTANCES_PER_ROW: u32 = 10;
 pub const SINGLE_INSTANCE_DISPLACEMENT: cgmath::Vector3<f32> = cgmath::Vector3::new(0.0, 0.0, 0.0);
 pub const FANCY_MULTI_INSTANCE_DISPLACEMENT: cgmath::Vector3<f32> = cgmath::Vector3::new(DEFAULT_INSTANCES_PER_ROW
                                                                         as f32 * 0.5, 0.0,DEFAULT_INSTANCES_PER_ROW as f32 * 0.5);
+const DEFAULT_INSTANCE_BUFFER_GROWTH_FACTOR: usize = 2;
+const DEFAULT_INSTANCE_BUFFER_SHRINK_THRESHOLD: f32 = 0.25;
+const SERIALIZED_INSTANCES_VERSION: u32 = 2;
+/// How close `sin(pitch)` gets to ±1 before `Instance::euler_angles` treats it as gimbal locked.
+pub const GIMBAL_LOCK_THRESHOLD: f32 = 0.9999;
+const SERIALIZED_HEADER_SIZE: usize = 8;
+// How far along its cycle each instance animated with `bob` is compared to the one before.
+const BOB_PHASE_STEP: f32 = 0.5;
+// Position (3), rotation (4) and scale (3), all f32, then the texture layer as a u32.
+const SERIALIZED_INSTANCE_SIZE: usize = 11 * 4;
+// Version 1 had no texture layer.
+const SERIALIZED_INSTANCE_SIZE_V1: usize = 10 * 4;
 
+/// When `update_instances` reallocates the instance buffer. It grows by `growth_factor` once the
+/// instances outgrow it, and only shrinks once removals leave them using less than
+/// `shrink_threshold` of it, so hovering around a boundary doesn't reallocate every frame. It
+/// never goes below `min_capacity`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InstanceBufferPolicy {
+    pub min_capacity: usize,
+    pub growth_factor: usize,
+    pub shrink_threshold: f32,
+}
+
+impl Default for InstanceBufferPolicy {
+    fn default() -> Self {
+        Self {
+            min_capacity: 1,
+            growth_factor: DEFAULT_INSTANCE_BUFFER_GROWTH_FACTOR,
+            shrink_threshold: DEFAULT_INSTANCE_BUFFER_SHRINK_THRESHOLD,
+        }
+    }
+}
+
+/// `instance_buffer` always has room for `capacity` raw instances, and `capacity` is never less
+/// than `instances.len()`. Only the first `instances.len()` slots are meaningful; the rest is
+/// headroom so `update_instances` can keep writing into the same buffer as the set grows.
+///
+/// Components built with a `BufferPool` take their buffers from it and hand them back when
+/// they're reallocated or dropped. A recycled buffer can't be filled without a queue though, so
+/// after `build` (and the other device-only reallocations) a pooled component starts out dirty:
+/// call `update_if_dirty` before drawing it.
 pub struct InstanceComponent {
     pub num_instances_per_row: u32,
     pub instance_displacement: cgmath::Vector3<f32>,
-    pub instance_buffer: wgpu::Buffer,
+    pub instance_buffer: PooledBuffer,
     pub instances: Vec<Instance>,
+    pub buffer_policy: InstanceBufferPolicy,
+    capacity: usize,
+    // How many instances the last upload had. Only shrinking below that (i.e. actually removing
+    // instances) can shrink the buffer, so filling up a reserved buffer never does.
+    uploaded_len: usize,
+    // Set by `iter_mut` and the bulk edits (`translate_all`, ...), and cleared by every upload.
+    pub dirty: bool,
+    // Where the owning entity sits in the world. Instances are placed relative to this, and it's
+    // the identity unless something like the transform hierarchy says otherwise.
+    pub transform: cgmath::Matrix4<f32>,
+    highlights: Highlights,
+    pool: Option<BufferPool>,
+    // With double buffering, the buffer `instance_buffer` gets swapped with before each upload,
+    // so the GPU can still be reading last frame's while this one's is written.
+    spare_buffer: Option<PooledBuffer>,
+    // Which of the two buffers `instance_buffer` currently is (always 0 without a spare).
+    current_buffer_index: usize,
+    // How many times `instance_buffer` has been replaced with a new one since `build`.
+    reallocations: usize,
 }
 
+// The colors highlighted instances had before, keyed by index.
+type Highlights = HashMap<usize, cgmath::Vector4<f32>>;
+
+pub enum InstanceLayout {
+    Single,
+    Grid { per_row: u32 },
+    Line { count: u32, spacing: f32 },
+    /// `count` instances strewn about (see `scatter`), reproducibly for a given `seed`.
+    /// `scale_jitter` of 0 leaves them all at unit scale.
+    Scatter { count: u32, bounds: (cgmath::Vector3<f32>, cgmath::Vector3<f32>), seed: u64, scale_jitter: f32 },
+    Custom(Vec<Instance>),
+}
+
+#[derive(Copy, Clone, Debug)]
 pub struct Instance {
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
+    pub scale: cgmath::Vector3<f32>,
+    pub color: cgmath::Vector4<f32>,
+    // Radius of a sphere (before scaling) enclosing whatever's drawn at this instance. Zero means
+    // it's treated as a point, which is fine for small meshes.
+    pub bounding_radius: f32,
+    /// Which layer of a texture array material (see `MaterialComponent::from_layers`) this
+    /// instance is drawn with. Ignored by single layer materials.
+    pub tex_layer: u32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
     model: [[f32; 4]; 4],
+    // Inverse-transpose of the model's upper-left 3x3. Everything here is a plain 4 byte
+    // scalar, so the struct has no implicit padding and stays sound to `Pod'.
+    normal: [[f32; 3]; 3],
+    color: [f32; 4],
+    tex_layer: u32,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self::new(cgmath::Vector3::zero(), cgmath::Quaternion::one())
+    }
 }
 
 impl Instance {
+    pub fn new(position: cgmath::Vector3<f32>, rotation: cgmath::Quaternion<f32>) -> Self {
+        Self::with_scale(position, rotation, Self::unit_scale())
+    }
+
+    pub fn with_scale(position: cgmath::Vector3<f32>, rotation: cgmath::Quaternion<f32>, scale: cgmath::Vector3<f32>) -> Self {
+        Self {
+            position, rotation, scale,
+            color: Self::white(),
+            bounding_radius: 0.0,
+            tex_layer: 0,
+        }
+    }
+
+    pub fn white() -> cgmath::Vector4<f32> {
+        cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0)
+    }
+
+    pub fn unit_scale() -> cgmath::Vector3<f32> {
+        cgmath::Vector3::new(1.0, 1.0, 1.0)
+    }
+
+    /// Turns the instance so its forward axis (-Z, same as the camera's) points at `target`. If
+    /// `target` is the instance's own position the rotation is left alone, and if it's straight
+    /// along `up` some other up axis is used instead.
+    pub fn look_at(&mut self, target: cgmath::Vector3<f32>, up: cgmath::Vector3<f32>) {
+        let forward = target - self.position;
+        if forward.magnitude2() <= f32::EPSILON {
+            return;
+        }
+
+        let forward = forward.normalize();
+        let up = if forward.cross(up).magnitude2() <= f32::EPSILON {
+            if forward.x.abs() < 0.9 { cgmath::Vector3::unit_x() } else { cgmath::Vector3::unit_z() }
+        } else {
+            up
+        };
+
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+
+        self.rotation = cgmath::Quaternion::from(cgmath::Matrix3::from_cols(right, up, -forward)).normalize();
+    }
+
+    /// Sets the rotation from angles in degrees, applied yaw (around Y) first, then pitch
+    /// (around X), then roll (around Z), all about the instance's own axes. That's the YXZ order,
+    /// i.e. `Ry * Rx * Rz`.
+    pub fn set_euler(&mut self, pitch_deg: f32, yaw_deg: f32, roll_deg: f32) {
+        self.rotation = cgmath::Quaternion::from_angle_y(cgmath::Deg(yaw_deg))
+            * cgmath::Quaternion::from_angle_x(cgmath::Deg(pitch_deg))
+            * cgmath::Quaternion::from_angle_z(cgmath::Deg(roll_deg));
+    }
+
+    /// `(pitch, yaw, roll)` in degrees, in the same order `set_euler` takes them. Pitch comes
+    /// back in `[-90, 90]`. Within `GIMBAL_LOCK_THRESHOLD` of straight up or down yaw and roll
+    /// turn about the same axis, so it's all put into yaw and roll comes back as zero.
+    pub fn euler_angles(&self) -> (f32, f32, f32) {
+        let m = cgmath::Matrix3::from(self.rotation.normalize());
+        let sin_pitch = (-m.z.y).clamp(-1.0, 1.0);
+        let pitch = sin_pitch.asin();
+
+        let (yaw, roll) = if sin_pitch.abs() < GIMBAL_LOCK_THRESHOLD {
+            (m.z.x.atan2(m.z.z), m.x.y.atan2(m.y.y))
+        } else {
+            ((-m.x.z).atan2(m.x.x), 0.0)
+        };
+
+        (pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees())
+    }
+
+    /// Blends towards `other`, with `t` clamped to `[0, 1]`. Rotations are slerped (cgmath's
+    /// slerp already drops down to nlerp when the two are close enough to NaN out otherwise).
+    /// Texture layers can't be blended, so it switches to `other`'s halfway.
+    pub fn lerp(&self, other: &Instance, t: f32) -> Instance {
+        let t = t.clamp(0.0, 1.0);
+
+        Instance {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+            color: self.color.lerp(other.color, t),
+            bounding_radius: self.bounding_radius + (other.bounding_radius - self.bounding_radius) * t,
+            tex_layer: if t < 0.5 { self.tex_layer } else { other.tex_layer },
+        }
+    }
+
+    pub fn scaled_bounding_radius(&self) -> f32 {
+        self.bounding_radius * self.scale.x.abs().max(self.scale.y.abs()).max(self.scale.z.abs())
+    }
+
     pub fn to_raw(&self) -> InstanceRaw {
+        self.to_raw_in(cgmath::Matrix4::identity())
+    }
+
+    /// Like `to_raw`, but with the instance placed relative to `parent`.
+    pub fn to_raw_in(&self, parent: cgmath::Matrix4<f32>) -> InstanceRaw {
         InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position) * cgmath::Matrix4::from(self.rotation)).into(),
+            model: mat4_to_array(self.model_matrix_in(parent)),
+            normal: mat3_to_array(self.normal_matrix(parent)),
+            color: vec4_to_array(self.color),
+            tex_layer: self.tex_layer,
+        }
+    }
+
+    pub fn model_matrix_in(&self, parent: cgmath::Matrix4<f32>) -> cgmath::Matrix4<f32> {
+        parent
+            * cgmath::Matrix4::from_translation(self.position)
+            * cgmath::Matrix4::from(self.rotation)
+            * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    /// Recovers an instance from its raw model matrix, assuming it was composed as
+    /// translation * rotation * scale (which `to_raw` does). Any shear is lost, and the bounding
+    /// radius isn't stored in the raw data at all so it comes back as 0.
+    pub fn from_raw(raw: &InstanceRaw) -> Instance {
+        let model = array_to_mat4(raw.model);
+        let position = model.w.truncate();
+        let (x, y, z) = (model.x.truncate(), model.y.truncate(), model.z.truncate());
+
+        let mut scale = cgmath::Vector3::new(x.magnitude(), y.magnitude(), z.magnitude());
+        // A mirrored basis can't be a rotation, so push the reflection into the scale instead.
+        if x.cross(y).dot(z) < 0.0 {
+            scale.x = -scale.x;
         }
+
+        // Gram-Schmidt the columns, so float error doesn't leave us with a not-quite-rotation.
+        let x = (x / scale.x).normalize();
+        let y = (y - x * x.dot(y)).normalize();
+        let z = x.cross(y);
+        let rotation = cgmath::Quaternion::from(cgmath::Matrix3::from_cols(x, y, z)).normalize();
+
+        let mut instance = Instance::with_scale(position, rotation, scale);
+        instance.color = array_to_vec4(raw.color);
+        instance.tex_layer = raw.tex_layer;
+
+        instance
+    }
+
+    fn normal_matrix(&self, parent: cgmath::Matrix4<f32>) -> cgmath::Matrix3<f32> {
+        let parent = cgmath::Matrix3::from_cols(parent.x.truncate(), parent.y.truncate(), parent.z.truncate());
+        let rotation = parent * cgmath::Matrix3::from(self.rotation);
+        let model = rotation * cgmath::Matrix3::from_diagonal(self.scale);
+
+        // A zero scale on any axis isn't invertible; the rotation alone is the best we can do.
+        model.invert()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(rotation)
     }
 }
 
@@ -70,6 +313,33 @@ impl InstanceRaw {
                     shader_location: 8,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // Same deal for the normal matrix, except it's a mat3 split into 3 vec3s.
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 19]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // The color would have been 9, but the normal matrix already took 9 to 11.
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 29]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Uint32,
+                },
             ],
         }
     }
@@ -79,55 +349,1288 @@ impl Component for InstanceComponent {
     fn as_any(&self) -> &dyn Any {
         self
     }
-}
 
-impl InstanceComponent {
-    pub fn default(device: &wgpu::Device) -> Self {
-        Self::new(device, 0, SINGLE_INSTANCE_DISPLACEMENT)
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 
-    pub fn new(device: &wgpu::Device, num_instances_per_row: u32, instance_displacement: cgmath::Vector3<f32>) -> Self {
-        let instances = Self::create_instances(num_instances_per_row, instance_displacement);
-        let instance_buffer = Self::create_instance_buffer(device, &instances);
+    fn type_name(&self) -> &'static str {
+        "InstanceComponent"
+    }
+}
+
+/// Describes an `InstanceComponent` without needing a device, so scenes can be put together
+/// before the GPU is up. Nothing is allocated until `build`.
+pub struct InstanceComponentBuilder {
+    layout: InstanceLayout,
+    instance_displacement: cgmath::Vector3<f32>,
+    instances: Vec<Instance>,
+    buffer_policy: InstanceBufferPolicy,
+    capacity: usize,
+    pool: Option<BufferPool>,
+    double_buffered: bool,
+}
+
+impl Default for InstanceComponentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl InstanceComponentBuilder {
+    pub fn new() -> Self {
         Self {
+            layout: InstanceLayout::Custom(vec![]),
+            instance_displacement: SINGLE_INSTANCE_DISPLACEMENT,
+            instances: vec![],
+            buffer_policy: InstanceBufferPolicy::default(),
+            capacity: 0,
+            pool: None,
+            double_buffered: false,
+        }
+    }
+
+    pub fn layout(mut self, layout: InstanceLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn displacement(mut self, instance_displacement: cgmath::Vector3<f32>) -> Self {
+        self.instance_displacement = instance_displacement;
+        self
+    }
+
+    // Added on top of whatever the layout generates.
+    pub fn instance(mut self, instance: Instance) -> Self {
+        self.instances.push(instance);
+        self
+    }
+
+    pub fn buffer_policy(mut self, buffer_policy: InstanceBufferPolicy) -> Self {
+        self.buffer_policy = buffer_policy;
+        self
+    }
+
+    /// Allocates room for at least `capacity` instances up front, however many the layout
+    /// starts with, so filling it in afterwards doesn't reallocate until it's exceeded.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Takes the instance buffer (and every reallocation after it) from `pool`.
+    pub fn pool(mut self, pool: BufferPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Keeps a second instance buffer to alternate with (see `InstanceComponent::current_buffer`),
+    /// for instances that get rewritten every frame. Twice the memory, so off by default.
+    pub fn double_buffered(mut self, double_buffered: bool) -> Self {
+        self.double_buffered = double_buffered;
+        self
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> InstanceComponent {
+        let num_instances_per_row = match self.layout {
+            InstanceLayout::Grid { per_row } => per_row,
+            _ => 0,
+        };
+        let mut instances = InstanceComponent::create_instances(self.layout, self.instance_displacement);
+        instances.extend(self.instances);
+
+        let instances_len = instances.len();
+        let capacity = instances_len.max(self.buffer_policy.min_capacity).max(self.capacity);
+        let (instance_buffer, needs_upload) = InstanceComponent::create_instance_buffer(device, self.pool.as_ref(),
+            &instances.iter().map(Instance::to_raw).collect::<Vec<_>>(), capacity);
+        let spare_buffer = self.double_buffered
+            .then(|| InstanceComponent::create_instance_buffer(device, self.pool.as_ref(), &[], capacity).0);
+
+        InstanceComponent {
             num_instances_per_row,
-            instance_displacement,
+            instance_displacement: self.instance_displacement,
             instance_buffer,
             instances,
+            buffer_policy: self.buffer_policy,
+            capacity,
+            uploaded_len: instances_len,
+            dirty: needs_upload,
+            transform: cgmath::Matrix4::identity(),
+            highlights: HashMap::new(),
+            pool: self.pool,
+            spare_buffer,
+            current_buffer_index: 0,
+            reallocations: 0,
+        }
+    }
+}
+
+impl InstanceComponent {
+    pub fn default(device: &wgpu::Device) -> Self {
+        InstanceComponentBuilder::new().build(device)
+    }
+
+    pub fn new(device: &wgpu::Device, layout: InstanceLayout, instance_displacement: cgmath::Vector3<f32>) -> Self {
+        InstanceComponentBuilder::new()
+            .layout(layout)
+            .displacement(instance_displacement)
+            .build(device)
+    }
+
+    /// No instances yet, but room for `capacity` of them.
+    pub fn with_capacity(device: &wgpu::Device, capacity: usize) -> Self {
+        InstanceComponentBuilder::new()
+            .capacity(capacity)
+            .build(device)
+    }
+
+    pub fn push_instance(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instance: Instance) {
+        self.instances.push(instance);
+        self.update_instances(device, queue);
+    }
+
+    pub fn remove_instance(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, index: usize) -> Option<Instance> {
+        if index >= self.instances.len() {
+            return None;
+        }
+
+        let mut instance = self.instances.remove(index);
+        if let Some(original) = self.highlights.remove(&index) {
+            instance.color = original;
+        }
+
+        // Everything after the hole shifted down one, and so do their highlights.
+        self.highlights = self.highlights.drain()
+            .map(|(highlighted, original)| (if highlighted > index { highlighted - 1 } else { highlighted }, original))
+            .collect();
+
+        self.update_instances(device, queue);
+
+        Some(instance)
+    }
+
+    /// Appends `instances` after the existing ones, with a single buffer update for all of them.
+    /// The result is no longer a grid, so `num_instances_per_row` is reset to 0.
+    pub fn extend_from(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[Instance]) {
+        self.instances.extend_from_slice(instances);
+        self.num_instances_per_row = 0;
+        self.update_instances(device, queue);
+    }
+
+    /// Fuses `other` into this component (its instances go after ours), so both get drawn in one
+    /// call. They're taken as-is, i.e. end up relative to our `transform` rather than `other`'s.
+    pub fn merge(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, other: InstanceComponent) {
+        self.extend_from(device, queue, &other.instances);
+    }
+
+    /// Recolors the instance at `index` (e.g. the one `pick` found) until `clear_highlight`. Its
+    /// own color is kept aside, so highlighting it again with another color still restores the
+    /// original. Like the bulk edits, this only marks the buffer dirty.
+    pub fn set_highlight(&mut self, index: usize, color: cgmath::Vector4<f32>) -> bool {
+        let highlighted = set_highlight(&mut self.instances, &mut self.highlights, index, color);
+        self.dirty |= highlighted;
+
+        highlighted
+    }
+
+    /// Puts back the color the instance had before `set_highlight`. Returns `false` if it wasn't
+    /// highlighted.
+    pub fn clear_highlight(&mut self, index: usize) -> bool {
+        let cleared = clear_highlight(&mut self.instances, &mut self.highlights, index);
+        self.dirty |= cleared;
+
+        cleared
+    }
+
+    pub fn is_highlighted(&self, index: usize) -> bool {
+        self.highlights.contains_key(&index)
+    }
+
+    /// Moves every instance by `delta`. This only touches `instances`, so batch up as many edits
+    /// as you like and follow up with `update_instances` once you're done.
+    pub fn translate_all(&mut self, delta: cgmath::Vector3<f32>) {
+        self.dirty = true;
+
+        for instance in &mut self.instances {
+            instance.position += delta;
+        }
+    }
+
+    /// Spins every instance in place by `delta` (applied on top of its current rotation). The
+    /// positions don't move.
+    pub fn rotate_all(&mut self, delta: cgmath::Quaternion<f32>) {
+        self.dirty = true;
+
+        for instance in &mut self.instances {
+            instance.rotation = (delta * instance.rotation).normalize();
+        }
+    }
+
+    /// Rebuilds every instance with `f` from its index and `t` (the elapsed time, say), then
+    /// uploads them. Nothing animates on its own: call this each frame for the components that
+    /// should move. Highlighted instances keep their highlight color. See `spin` and `bob` for
+    /// ready-made animations.
+    pub fn animate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, t: f32, f: impl Fn(usize, f32) -> Instance) {
+        for (index, instance) in self.instances.iter_mut().enumerate() {
+            let color = instance.color;
+            *instance = f(index, t);
+
+            if self.highlights.contains_key(&index) {
+                instance.color = color;
+            }
+        }
+
+        self.update_instances(device, queue);
+    }
+
+    /// Like `remove_instance`, but moves the last instance into the hole instead of shifting
+    /// everything down, so only that one slot needs re-uploading. This doesn't keep the instances
+    /// in order. If the buffer was already out of date (or never written), it all gets uploaded
+    /// instead, as patching one slot wouldn't bring it up to date.
+    pub fn swap_remove_instance(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, index: usize) -> Option<Instance> {
+        if index >= self.instances.len() {
+            return None;
+        }
+
+        let pending = self.dirty || self.uploaded_len != self.instances.len();
+
+        let mut instance = self.instances.swap_remove(index);
+        if let Some(original) = self.highlights.remove(&index) {
+            instance.color = original;
+        }
+
+        // The last instance moved into the hole, highlight and all.
+        if let Some(original) = self.highlights.remove(&self.instances.len()) {
+            self.highlights.insert(index, original);
+        }
+
+        if pending {
+            self.update_instances(device, queue);
+            return Some(instance);
+        }
+
+        self.uploaded_len = self.instances.len();
+
+        // Nothing got swapped in if we removed the last one; the draw just gets one shorter.
+        if let Some(swapped) = self.instances.get(index) {
+            let offset = (index * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+            let raw = [swapped.to_raw_in(self.transform)];
+
+            // The spare gets patched too, so it isn't a slot behind if it's swapped in as is.
+            for buffer in std::iter::once(&self.instance_buffer).chain(&self.spare_buffer) {
+                queue.write_buffer(buffer, offset, bytemuck::cast_slice(&raw));
+            }
+        }
+
+        Some(instance)
+    }
+
+    /// Raw data for just the instances that are (at least partially) inside `frustum`.
+    pub fn cull(&self, frustum: &Frustum) -> Vec<InstanceRaw> {
+        self.instances.iter()
+            .filter(|instance| frustum.contains_sphere(self.world_position(instance), instance.scaled_bounding_radius()))
+            .map(|instance| instance.to_raw_in(self.transform))
+            .collect()
+    }
+
+    /// Orders the instances furthest-to-nearest from `camera_position`, so they blend correctly
+    /// through a transparent pipeline. The sort is stable, and takes effect on the next upload.
+    pub fn sort_by_depth(&mut self, camera_position: cgmath::Vector3<f32>) {
+        self.dirty = true;
+
+        let order = depth_order(&self.instances, self.transform, camera_position);
+        reorder(&mut self.instances, &mut self.highlights, &order);
+    }
+
+    /// Index of the nearest instance hit by the ray, treating each instance as a box of
+    /// `half_extents` around its origin (in its own local space, so rotation and scale apply).
+    /// Instances the ray starts inside of count as hit right at the origin.
+    pub fn pick(
+        &self,
+        ray_origin: cgmath::Point3<f32>,
+        ray_direction: cgmath::Vector3<f32>,
+        half_extents: cgmath::Vector3<f32>,
+    ) -> Option<usize> {
+        pick_in(&self.instances, self.transform, ray_origin, ray_direction, half_extents)
+    }
+
+    /// The `(min, max)` corners of a box around every instance (and its bounding radius), or
+    /// `None` if there aren't any instances.
+    pub fn aabb(&self) -> Option<(cgmath::Vector3<f32>, cgmath::Vector3<f32>)> {
+        aabb_in(&self.instances, self.transform)
+    }
+
+    /// Where `instance` ends up once it's placed relative to `transform`.
+    pub fn world_position(&self, instance: &Instance) -> cgmath::Vector3<f32> {
+        self.transform.transform_point(cgmath::Point3::from_vec(instance.position)).to_vec()
+    }
+
+    /// Packs every instance's position, rotation (`x, y, z, w`) and scale as little-endian f32s,
+    /// followed by its texture layer as a little-endian u32, after a header of the format
+    /// version and instance count (both little-endian u32s).
+    pub fn serialize(&self) -> Vec<u8> {
+        serialize_instances(&self.instances)
+    }
+
+    pub fn deserialize(device: &wgpu::Device, bytes: &[u8]) -> Result<Self, WafError> {
+        Ok(Self::new(device, InstanceLayout::Custom(deserialize_instances(bytes)?), SINGLE_INSTANCE_DISPLACEMENT))
+    }
+
+    /// What actually ends up in `instance_buffer`: every instance, placed relative to `transform`.
+    pub fn raw_instances(&self) -> Vec<InstanceRaw> {
+        self.instances.iter().map(|instance| instance.to_raw_in(self.transform)).collect()
+    }
+
+    /// Swaps in a whole new set of instances with a single buffer rebuild. The result is no
+    /// longer a grid, so `num_instances_per_row` is reset to 0.
+    pub fn set_instances(&mut self, device: &wgpu::Device, instances: Vec<Instance>) {
+        self.instances = instances;
+        self.num_instances_per_row = 0;
+        self.highlights.clear();
+        self.reallocate(device, self.capacity.max(self.instances.len()));
+    }
+
+    /// Drops every instance `f` returns false for, keeping the rest in order, and rebuilds the
+    /// buffer once at the end (unless nothing was dropped). Highlights stay with their instances.
+    pub fn retain(&mut self, device: &wgpu::Device, f: impl FnMut(&Instance) -> bool) {
+        let len = self.instances.len();
+        let order = retain_order(&self.instances, f);
+        reorder(&mut self.instances, &mut self.highlights, &order);
+
+        if self.instances.len() < len {
+            self.num_instances_per_row = 0;
+            self.reallocate(device, self.capacity.max(self.instances.len()));
+        }
+    }
+
+    /// The buffer to bind for drawing: the one `update_instances` last wrote to. Without double
+    /// buffering that's always `instance_buffer`, and with it, `instance_buffer` is always swapped
+    /// to be it, so binding either is the same.
+    pub fn current_buffer(&self) -> wgpu::BufferSlice<'_> {
+        self.instance_buffer.slice(..)
+    }
+
+    /// Which of the two buffers `current_buffer` is, 0 or 1. Flips with every `update_instances`
+    /// that doesn't reallocate, and stays 0 without double buffering.
+    pub fn current_buffer_index(&self) -> usize {
+        self.current_buffer_index
+    }
+
+    pub fn is_double_buffered(&self) -> bool {
+        self.spare_buffer.is_some()
+    }
+
+    /// How many times the instance buffer has been swapped for a new one since the component was
+    /// built. Uploads that fit never change this.
+    pub fn reallocation_count(&self) -> usize {
+        self.reallocations
+    }
+
+    /// How many instances fit in `instance_buffer` before it has to be reallocated.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Makes room for at least `additional` more instances up front, so pushing them doesn't
+    /// reallocate along the way.
+    pub fn reserve(&mut self, device: &wgpu::Device, additional: usize) {
+        let required = self.instances.len() + additional;
+
+        if required > self.capacity {
+            self.reallocate(device, required);
+        }
+    }
+
+    /// Hands out every instance for editing, and marks the buffer as needing an upload (see
+    /// `update_if_dirty`) whether or not anything actually changes.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Instance> {
+        self.dirty = true;
+        self.instances.iter_mut()
+    }
+
+    /// `update_instances`, but only if something might have changed since the last upload.
+    pub fn update_if_dirty(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.dirty {
+            self.update_instances(device, queue);
         }
     }
 
-    fn create_instance_buffer(device: &wgpu::Device, instances: &Vec<Instance>) -> wgpu::Buffer {
-        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Re-uploads `instances` into the existing buffer. A new buffer is only created when
+    /// `buffer_policy` says the current one is too small (or far too big).
+    pub fn update_instances(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let len = self.instances.len();
+        let policy = self.buffer_policy;
+        let removed = len < self.uploaded_len;
+        self.uploaded_len = len;
 
-        device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX,
+        // Leave the same headroom a fresh grow would, so the next few pushes are free.
+        let shrunk_capacity = (len * policy.growth_factor).max(policy.min_capacity);
+
+        // Fresh buffers come with the instances already in (`reallocate` leaves `dirty` unset);
+        // recycled ones, like the current buffer, still need writing to.
+        let needs_upload = if len > self.capacity {
+            self.reallocate(device, (self.capacity * policy.growth_factor).max(len));
+            self.dirty
+        } else if removed && (len as f32) < self.capacity as f32 * policy.shrink_threshold && shrunk_capacity < self.capacity {
+            self.reallocate(device, shrunk_capacity);
+            self.dirty
+        } else {
+            // Whatever's in the spare is stale, but it's all about to be overwritten anyway.
+            if let Some(spare_buffer) = &mut self.spare_buffer {
+                std::mem::swap(&mut self.instance_buffer, spare_buffer);
+                self.current_buffer_index ^= 1;
             }
-        )
+
+            true
+        };
+
+        self.dirty = false;
+
+        if needs_upload {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.raw_instances()));
+        }
+    }
+
+    /// What the GPU actually has for each instance, copied out of `current_buffer`, for checking
+    /// it against `raw_instances`. This submits a copy and then blocks until it's done and mapped,
+    /// so it stalls the CPU on everything queued before it. Meant for debugging, not every frame.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<InstanceRaw> {
+        let size = (self.instances.len() * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        if size == 0 {
+            return vec![];
+        }
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Instance Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.instance_buffer, 0, &buffer, 0, size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+
+        let instances = slice.get_mapped_range()
+            .chunks_exact(std::mem::size_of::<InstanceRaw>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+
+        buffer.unmap();
+
+        instances
     }
 
-    fn create_instances(num_instances_per_row: u32, instance_displacement: cgmath::Vector3<f32>) -> Vec<Instance> {
-        (0..num_instances_per_row).flat_map(|z| {
-            (0..num_instances_per_row).map(move |x| {
-                let position = cgmath::Vector3 { x: x as f32, y: 0.0, z: z as f32 } - instance_displacement;
+    /// The old buffer goes back to the pool (if there is one) as it's replaced.
+    fn reallocate(&mut self, device: &wgpu::Device, capacity: usize) {
+        self.reallocations += 1;
+        self.capacity = capacity.max(self.buffer_policy.min_capacity).max(self.instances.len());
+        self.uploaded_len = self.instances.len();
+        (self.instance_buffer, self.dirty) = Self::create_instance_buffer(device, self.pool.as_ref(),
+            &self.raw_instances(), self.capacity);
 
-                let rotation = if position.is_zero() {
-                    // This is needed so an object at (0, 0, 0) won't get scaled to zero
-                    // as Quaternions can effect scale if they're not created correctly
-                    cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_z(), cgmath::Deg(0.0))
-                } else {
-                    cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
-                };
+        if self.spare_buffer.is_some() {
+            self.spare_buffer = Some(Self::create_instance_buffer(device, self.pool.as_ref(), &[], self.capacity).0);
+        }
+    }
 
-                Instance {
-                    position, rotation,
+    /// The buffer, and whether it still needs `instances` written into it (only ever the case for
+    /// recycled buffers).
+    fn create_instance_buffer(
+        device: &wgpu::Device,
+        pool: Option<&BufferPool>,
+        instances: &[InstanceRaw],
+        capacity: usize,
+    ) -> (PooledBuffer, bool) {
+        // `COPY_SRC` is only there for `read_back`.
+        let usage = wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let size = (capacity.max(instances.len()) * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+
+        if let Some(pool) = pool {
+            return (pool.acquire(device, size, usage, "Instance Buffer"), !instances.is_empty());
+        }
+
+        let mut instance_data = instances.to_vec();
+        instance_data.resize(capacity.max(instances.len()), bytemuck::Zeroable::zeroed());
+
+        let (buffer, _) = with_error_scope(device, "Instance Buffer creation", || {
+            device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Instance Buffer"),
+                    contents: bytemuck::cast_slice(&instance_data),
+                    usage,
                 }
-            })
-        }).collect::<Vec<_>>()
+            )
+        });
+
+        (PooledBuffer::unpooled(buffer, size, usage), false)
+    }
+
+    fn create_instances(layout: InstanceLayout, instance_displacement: cgmath::Vector3<f32>) -> Vec<Instance> {
+        match layout {
+            InstanceLayout::Single => vec![Self::create_instance(cgmath::Vector3::zero() - instance_displacement)],
+            InstanceLayout::Grid { per_row } => (0..per_row).flat_map(|z| {
+                (0..per_row).map(move |x| {
+                    Self::create_instance(cgmath::Vector3 { x: x as f32, y: 0.0, z: z as f32 } - instance_displacement)
+                })
+            }).collect::<Vec<_>>(),
+            InstanceLayout::Line { count, spacing } => (0..count).map(|x| {
+                Self::create_instance(cgmath::Vector3 { x: x as f32 * spacing, y: 0.0, z: 0.0 } - instance_displacement)
+            }).collect::<Vec<_>>(),
+            InstanceLayout::Scatter { count, bounds: (min, max), seed, scale_jitter } => {
+                scatter(count, (min - instance_displacement, max - instance_displacement), seed, scale_jitter)
+            },
+            InstanceLayout::Custom(instances) => instances,
+        }
+    }
+
+    fn create_instance(position: cgmath::Vector3<f32>) -> Instance {
+        let rotation = if position.is_zero() {
+            // Normalizing the origin would give us a NaN axis, so just don't rotate it.
+            // Scale is carried separately now, so the rotation only has to stay a unit
+            // quaternion.
+            cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_z(), cgmath::Deg(0.0))
+        } else {
+            cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
+        };
+
+        Instance::new(position, rotation)
+    }
+}
+
+/// `count` instances at random positions between the corners of `bounds`, each turned a random
+/// amount about Y (so they stay upright, like trees or rocks would) and scaled uniformly by up to
+/// `scale_jitter` either way. The same seed always gives the same instances.
+pub fn scatter(count: u32, bounds: (cgmath::Vector3<f32>, cgmath::Vector3<f32>), seed: u64, scale_jitter: f32) -> Vec<Instance> {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+    let (min, max) = bounds;
+    // `gen_range` panics on an empty range, which a flat (or inside out) axis would be.
+    let mut between = |a: f32, b: f32| if a < b { rng.gen_range(a..b) } else { a };
+
+    (0..count).map(|_| {
+        let position = cgmath::Vector3::new(between(min.x, max.x), between(min.y, max.y), between(min.z, max.z));
+        let rotation = cgmath::Quaternion::from_angle_y(cgmath::Rad(between(0.0, std::f32::consts::TAU)));
+        let scale = between(1.0 - scale_jitter.abs(), 1.0 + scale_jitter.abs());
+
+        Instance::with_scale(position, rotation, cgmath::Vector3::new(scale, scale, scale))
+    }).collect()
+}
+
+/// An animation for `InstanceComponent::animate` turning each of `base` a full circle about
+/// `axis` every `period` seconds, on top of its own rotation.
+pub fn spin(base: Vec<Instance>, axis: cgmath::Vector3<f32>, period: f32) -> impl Fn(usize, f32) -> Instance {
+    let axis = axis.normalize();
+
+    move |index, t| {
+        let mut instance = base.get(index).copied().unwrap_or_default();
+        let angle = cgmath::Rad(std::f32::consts::TAU * t / period);
+
+        instance.rotation = (cgmath::Quaternion::from_axis_angle(axis, angle) * instance.rotation).normalize();
+        instance
+    }
+}
+
+/// An animation for `InstanceComponent::animate` moving each of `base` up and down by up to
+/// `height` every `period` seconds. Each instance is a little further along than the one before,
+/// so rows of them ripple instead of moving in lockstep.
+pub fn bob(base: Vec<Instance>, height: f32, period: f32) -> impl Fn(usize, f32) -> Instance {
+    move |index, t| {
+        let mut instance = base.get(index).copied().unwrap_or_default();
+        let phase = std::f32::consts::TAU * t / period + index as f32 * BOB_PHASE_STEP;
+
+        instance.position.y += height * phase.sin();
+        instance
+    }
+}
+
+// What follows is the CPU side of the `InstanceComponent` methods of the same (or a similar)
+// name, over the bare instances so it works (and can be tested) without a device.
+
+fn set_highlight(instances: &mut [Instance], highlights: &mut Highlights, index: usize, color: cgmath::Vector4<f32>) -> bool {
+    let instance = match instances.get_mut(index) {
+        Some(instance) => instance,
+        None => return false,
+    };
+
+    highlights.entry(index).or_insert(instance.color);
+    instance.color = color;
+
+    true
+}
+
+fn clear_highlight(instances: &mut [Instance], highlights: &mut Highlights, index: usize) -> bool {
+    let (original, instance) = match (highlights.remove(&index), instances.get_mut(index)) {
+        (Some(original), Some(instance)) => (original, instance),
+        _ => return false,
+    };
+
+    instance.color = original;
+
+    true
+}
+
+/// Rearranges `instances` so each ends up where its old index is in `order`, dropping the ones
+/// that aren't in it. Highlights follow their instances (and are dropped along with them).
+fn reorder(instances: &mut Vec<Instance>, highlights: &mut Highlights, order: &[usize]) {
+    let mut reordered = HashMap::with_capacity(highlights.len());
+    *instances = order.iter().enumerate()
+        .map(|(new_index, &old_index)| {
+            if let Some(original) = highlights.remove(&old_index) {
+                reordered.insert(new_index, original);
+            }
+
+            instances[old_index]
+        })
+        .collect();
+    *highlights = reordered;
+}
+
+/// The indices of `instances` furthest-to-nearest from `camera_position`, once placed relative
+/// to `transform`. Instances just as far away keep their order.
+fn depth_order(instances: &[Instance], transform: cgmath::Matrix4<f32>, camera_position: cgmath::Vector3<f32>) -> Vec<usize> {
+    let distance = |instance: &Instance| {
+        transform.transform_point(cgmath::Point3::from_vec(instance.position)).to_vec().distance2(camera_position)
+    };
+
+    let mut order = (0..instances.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| distance(&instances[b]).partial_cmp(&distance(&instances[a]))
+        .unwrap_or(std::cmp::Ordering::Equal));
+
+    order
+}
+
+/// The indices of the `instances` that `f` returns true for, in order.
+fn retain_order(instances: &[Instance], mut f: impl FnMut(&Instance) -> bool) -> Vec<usize> {
+    (0..instances.len()).filter(|&index| f(&instances[index])).collect()
+}
+
+fn pick_in(
+    instances: &[Instance],
+    transform: cgmath::Matrix4<f32>,
+    ray_origin: cgmath::Point3<f32>,
+    ray_direction: cgmath::Vector3<f32>,
+    half_extents: cgmath::Vector3<f32>,
+) -> Option<usize> {
+    instances.iter().enumerate()
+        .filter_map(|(index, instance)| {
+            // Instances scaled down to nothing have no inverse, and nothing to hit anyway.
+            let inverse = instance.model_matrix_in(transform).invert()?;
+
+            // `t' along the local ray is the same `t' as along the world ray, since the
+            // direction isn't renormalized.
+            let origin = inverse.transform_point(ray_origin);
+            let direction = inverse.transform_vector(ray_direction);
+
+            ray_box_intersection(origin, direction, half_extents).map(|t| (index, t))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(index, _)| index)
+}
+
+fn aabb_in(instances: &[Instance], transform: cgmath::Matrix4<f32>) -> Option<(cgmath::Vector3<f32>, cgmath::Vector3<f32>)> {
+    instances.iter().fold(None, |aabb, instance| {
+        let position = transform.transform_point(cgmath::Point3::from_vec(instance.position)).to_vec();
+        let radius = cgmath::Vector3::new(1.0, 1.0, 1.0) * instance.scaled_bounding_radius();
+        let (min, max) = (position - radius, position + radius);
+
+        Some(match aabb {
+            Some((aabb_min, aabb_max)) => (
+                cgmath::Vector3::new(aabb_min.x.min(min.x), aabb_min.y.min(min.y), aabb_min.z.min(min.z)),
+                cgmath::Vector3::new(aabb_max.x.max(max.x), aabb_max.y.max(max.y), aabb_max.z.max(max.z)),
+            ),
+            None => (min, max),
+        })
+    })
+}
+
+fn serialize_instances(instances: &[Instance]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SERIALIZED_HEADER_SIZE + instances.len() * SERIALIZED_INSTANCE_SIZE);
+
+    bytes.extend_from_slice(&SERIALIZED_INSTANCES_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(instances.len() as u32).to_le_bytes());
+
+    for instance in instances {
+        let [x, y, z, w] = quat_to_array(instance.rotation);
+        let floats = [
+            instance.position.x, instance.position.y, instance.position.z,
+            x, y, z, w,
+            instance.scale.x, instance.scale.y, instance.scale.z,
+        ];
+
+        for float in floats {
+            bytes.extend_from_slice(&float.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&instance.tex_layer.to_le_bytes());
+    }
+
+    bytes
+}
+
+fn deserialize_instances(bytes: &[u8]) -> Result<Vec<Instance>, WafError> {
+    if bytes.len() < SERIALIZED_HEADER_SIZE {
+        return Err(WafError::BufferTooSmall { expected: SERIALIZED_HEADER_SIZE, actual: bytes.len() });
+    }
+
+    let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let version = read_u32(0);
+    let count = read_u32(4) as usize;
+
+    // Older data still loads, with every instance on layer 0.
+    let instance_size = match version {
+        1 => SERIALIZED_INSTANCE_SIZE_V1,
+        SERIALIZED_INSTANCES_VERSION => SERIALIZED_INSTANCE_SIZE,
+        _ => return Err(WafError::DecodeFailed(format!(
+            "unsupported serialized instances version {} (expected {})", version, SERIALIZED_INSTANCES_VERSION))),
+    };
+
+    let expected = SERIALIZED_HEADER_SIZE + count * instance_size;
+    if bytes.len() < expected {
+        return Err(WafError::BufferTooSmall { expected, actual: bytes.len() });
+    }
+
+    Ok(bytes[SERIALIZED_HEADER_SIZE..expected]
+        .chunks_exact(instance_size)
+        .map(|chunk| {
+            let word = |i: usize| -> [u8; 4] { chunk[i * 4..i * 4 + 4].try_into().unwrap() };
+            let f = |i: usize| f32::from_le_bytes(word(i));
+
+            let mut instance = Instance::with_scale(
+                cgmath::Vector3::new(f(0), f(1), f(2)),
+                array_to_quat([f(3), f(4), f(5), f(6)]),
+                cgmath::Vector3::new(f(7), f(8), f(9)),
+            );
+
+            if instance_size == SERIALIZED_INSTANCE_SIZE {
+                instance.tex_layer = u32::from_le_bytes(word(10));
+            }
+
+            instance
+        })
+        .collect())
+}
+
+/// Slab test of a ray against the box from `-half_extents` to `half_extents`. Returns the
+/// nearest non-negative `t' the ray is inside the box at.
+fn ray_box_intersection(
+    origin: cgmath::Point3<f32>,
+    direction: cgmath::Vector3<f32>,
+    half_extents: cgmath::Vector3<f32>,
+) -> Option<f32> {
+    let mut t_near = 0.0_f32;
+    let mut t_far = f32::INFINITY;
+
+    for axis in 0..3 {
+        let (origin, direction, extent) = (origin[axis], direction[axis], half_extents[axis]);
+
+        if direction == 0.0 {
+            // Parallel to this slab, so it has to start between its planes.
+            if origin < -extent || origin > extent {
+                return None;
+            }
+
+            continue;
+        }
+
+        let (t0, t1) = ((-extent - origin) / direction, (extent - origin) / direction);
+        t_near = t_near.max(t0.min(t1));
+        t_far = t_far.min(t0.max(t1));
+
+        if t_near > t_far {
+            return None;
+        }
     }
+
+    Some(t_near)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::{test_renderer, HeadlessRenderer};
+    use crate::math::approx::{approx_eq, approx_eq_quat, approx_eq_vec3, DEFAULT_EPSILON};
+
+    /// Checks what's in the GPU's copy of the instances (see `read_back`) byte for byte against
+    /// what `instances` says should be there.
+    #[track_caller]
+    fn assert_gpu_matches(instances: &InstanceComponent, gpu: &HeadlessRenderer) {
+        let on_gpu = instances.read_back(&gpu.device, &gpu.queue);
+        assert_eq!(bytemuck::cast_slice::<_, u8>(&on_gpu), bytemuck::cast_slice::<_, u8>(&instances.raw_instances()));
+    }
+
+    #[test]
+    fn scale_doubles_basis_vectors() {
+        let instance = Instance::with_scale(cgmath::Vector3::zero(), cgmath::Quaternion::one(), cgmath::Vector3::new(2.0, 2.0, 2.0));
+        let model = instance.to_raw().model;
+
+        assert_eq!(model[0], [2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(model[1], [0.0, 2.0, 0.0, 0.0]);
+        assert_eq!(model[2], [0.0, 0.0, 2.0, 0.0]);
+        assert_eq!(model[3], [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn push_instance_onto_empty_component() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::default(&gpu.device);
+        assert!(instances.instances.is_empty());
+
+        for x in 0..3 {
+            instances.push_instance(&gpu.device, &gpu.queue, Instance::new(cgmath::Vector3::new(x as f32, 0.0, 0.0), cgmath::Quaternion::one()));
+        }
+
+        assert_eq!(instances.instances.len(), 3);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn consecutive_updates_reuse_the_buffer() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 3, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+
+        instances.update_instances(&gpu.device, &gpu.queue);
+        instances.translate_all(cgmath::Vector3::unit_x());
+        instances.update_instances(&gpu.device, &gpu.queue);
+
+        assert_eq!(instances.reallocation_count(), 0);
+        assert_eq!(instances.capacity(), 3);
+    }
+
+    #[test]
+    fn pure_rotation_normal_matrix_is_the_rotation() {
+        let rotation = cgmath::Quaternion::from_axis_angle(cgmath::Vector3::new(1.0, 2.0, 3.0).normalize(), cgmath::Deg(40.0));
+        let raw = Instance::new(cgmath::Vector3::new(5.0, -1.0, 2.0), rotation).to_raw();
+        let expected: [[f32; 3]; 3] = cgmath::Matrix3::from(rotation).into();
+
+        for (column, expected) in raw.normal.iter().zip(expected) {
+            for (a, b) in column.iter().zip(expected) {
+                assert!(approx_eq(*a, b, DEFAULT_EPSILON), "{:?} != {:?}", raw.normal, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn line_layout_spaces_instances_along_x() {
+        let instances = InstanceComponent::create_instances(InstanceLayout::Line { count: 4, spacing: 2.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+        let positions = instances.iter().map(|instance| instance.position).collect::<Vec<_>>();
+
+        assert_eq!(positions, (0..4).map(|x| cgmath::Vector3::new(x as f32 * 2.0, 0.0, 0.0)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn set_instances_replaces_ten_with_two() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 10, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+
+        let replacements = vec![
+            Instance::new(cgmath::Vector3::new(0.0, 5.0, 0.0), cgmath::Quaternion::one()),
+            Instance::new(cgmath::Vector3::new(0.0, -5.0, 0.0), cgmath::Quaternion::one()),
+        ];
+        instances.set_instances(&gpu.device, replacements);
+
+        assert_eq!(instances.len(), 2);
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    fn lerp_halfway_is_the_midpoint() {
+        let a = Instance::new(cgmath::Vector3::new(0.0, 2.0, -4.0), cgmath::Quaternion::one());
+        let b = Instance::new(cgmath::Vector3::new(6.0, -2.0, 4.0), cgmath::Quaternion::one());
+
+        let position = a.lerp(&b, 0.5).position;
+        assert!(approx_eq_vec3(position, cgmath::Vector3::new(3.0, 0.0, 0.0), DEFAULT_EPSILON), "{:?}", position);
+    }
+
+    #[test]
+    fn fifty_random_instances_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(27);
+
+        let originals = (0..50).map(|_| {
+            let position = cgmath::Vector3::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0));
+            let axis = cgmath::Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 1.0f32).normalize();
+            let rotation = cgmath::Quaternion::from_axis_angle(axis, cgmath::Rad(rng.gen_range(0.0..std::f32::consts::TAU)));
+            let scale = cgmath::Vector3::new(rng.gen_range(0.1..4.0), rng.gen_range(0.1..4.0), rng.gen_range(0.1..4.0));
+
+            let mut instance = Instance::with_scale(position, rotation, scale);
+            instance.tex_layer = rng.gen_range(0..8);
+            instance
+        }).collect::<Vec<_>>();
+
+        let loaded = deserialize_instances(&serialize_instances(&originals)).unwrap();
+
+        assert_eq!(loaded.len(), originals.len());
+        for (loaded, original) in loaded.iter().zip(&originals) {
+            assert_eq!(loaded.position, original.position);
+            assert_eq!(loaded.rotation, original.rotation);
+            assert_eq!(loaded.scale, original.scale);
+            assert_eq!(loaded.tex_layer, original.tex_layer);
+        }
+    }
+
+    #[test]
+    fn builder_collects_instances_until_it_builds() {
+        let builder = (0..3).fold(InstanceComponentBuilder::new(), |builder, x| {
+            builder.instance(Instance::new(cgmath::Vector3::new(x as f32, 0.0, 0.0), cgmath::Quaternion::one()))
+        });
+
+        assert_eq!(builder.instances.len(), 3);
+        assert_eq!(builder.instances[2].position, cgmath::Vector3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn builder_with_three_instances_builds_three() {
+        let gpu = test_renderer();
+        let instances = (0..3).fold(InstanceComponentBuilder::new(), |builder, x| {
+            builder.instance(Instance::new(cgmath::Vector3::new(x as f32, 0.0, 0.0), cgmath::Quaternion::one()))
+        }).build(&gpu.device);
+
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances.instances[2].position, cgmath::Vector3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn no_instances_have_no_aabb() {
+        assert!(aabb_in(&[], cgmath::Matrix4::identity()).is_none());
+    }
+
+    #[test]
+    fn aabb_encloses_all_three_instances() {
+        let positions = [
+            cgmath::Vector3::new(-1.0, 2.0, 0.0),
+            cgmath::Vector3::new(3.0, -4.0, 1.0),
+            cgmath::Vector3::new(0.0, 0.0, -5.0),
+        ];
+        let instances = positions.iter().map(|position| Instance::new(*position, cgmath::Quaternion::one())).collect::<Vec<_>>();
+
+        let (min, max) = aabb_in(&instances, cgmath::Matrix4::identity()).unwrap();
+        assert_eq!((min, max), (cgmath::Vector3::new(-1.0, -4.0, -5.0), cgmath::Vector3::new(3.0, 2.0, 1.0)));
+    }
+
+    #[test]
+    fn from_raw_recovers_a_rotated_instance() {
+        let rotation = cgmath::Quaternion::from_axis_angle(cgmath::Vector3::new(0.0, 1.0, 1.0).normalize(), cgmath::Deg(70.0));
+        let instance = Instance::new(cgmath::Vector3::new(4.0, -2.0, 7.5), rotation);
+
+        let decoded = Instance::from_raw(&instance.to_raw());
+
+        assert!(approx_eq_vec3(decoded.position, instance.position, DEFAULT_EPSILON), "{:?}", decoded.position);
+        assert!(approx_eq_quat(decoded.rotation, instance.rotation, 1e-4), "{:?}", decoded.rotation);
+        assert!(approx_eq_vec3(decoded.scale, instance.scale, 1e-4), "{:?}", decoded.scale);
+    }
+
+    #[test]
+    fn sorted_distances_never_increase() {
+        let mut instances = scatter(32, (cgmath::Vector3::new(-10.0, -10.0, -10.0), cgmath::Vector3::new(10.0, 10.0, 10.0)), 34, 0.0);
+        let camera_position = cgmath::Vector3::new(0.0, 2.0, 15.0);
+
+        let order = depth_order(&instances, cgmath::Matrix4::identity(), camera_position);
+        reorder(&mut instances, &mut Highlights::new(), &order);
+
+        let distances = instances.iter().map(|instance| instance.position.distance(camera_position)).collect::<Vec<_>>();
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", distances);
+    }
+
+    #[test]
+    fn ray_down_negative_z_picks_the_nearest_hit() {
+        let positions = [cgmath::Vector3::new(0.0, 0.0, 0.0), cgmath::Vector3::new(5.0, 0.0, 0.0), cgmath::Vector3::new(0.0, 0.0, 3.0)];
+        let instances = positions.iter().map(|position| Instance::new(*position, cgmath::Quaternion::one())).collect::<Vec<_>>();
+        let half_extents = cgmath::Vector3::new(0.5, 0.5, 0.5);
+        let origin = cgmath::Point3::new(0.0, 0.0, 10.0);
+        let pick = |instances: &[Instance], direction| pick_in(instances, cgmath::Matrix4::identity(), origin, direction, half_extents);
+
+        let single = InstanceComponent::create_instances(InstanceLayout::Single, SINGLE_INSTANCE_DISPLACEMENT);
+        assert_eq!(pick(&single, -cgmath::Vector3::unit_z()), Some(0));
+
+        assert_eq!(pick(&instances, -cgmath::Vector3::unit_z()), Some(2));
+        assert_eq!(pick(&instances, cgmath::Vector3::unit_z()), None);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn translating_by_one_x_moves_each_instance_one_unit() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Grid { per_row: 3 }, SINGLE_INSTANCE_DISPLACEMENT);
+        let before = instances.instances.iter().map(|instance| instance.position).collect::<Vec<_>>();
+
+        instances.translate_all(cgmath::Vector3::unit_x());
+
+        for (instance, before) in instances.instances.iter().zip(before) {
+            assert_eq!(instance.position - before, cgmath::Vector3::unit_x());
+        }
+        assert!(instances.dirty);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn pushing_past_capacity_grows_by_the_factor() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 4, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+        assert_eq!(instances.capacity(), 4);
+
+        instances.push_instance(&gpu.device, &gpu.queue, Instance::default());
+
+        assert_eq!(instances.capacity(), 4 * DEFAULT_INSTANCE_BUFFER_GROWTH_FACTOR);
+        assert_eq!(instances.reallocation_count(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn removing_most_instances_does_not_shrink_straight_away() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 8, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+
+        // Five of eight gone still leaves more than the shrink threshold in use.
+        for _ in 0..5 {
+            instances.remove_instance(&gpu.device, &gpu.queue, 0);
+        }
+
+        assert_eq!(instances.capacity(), 8);
+        assert_eq!(instances.reallocation_count(), 0);
+
+        // Down to one of eight, it finally does.
+        instances.remove_instance(&gpu.device, &gpu.queue, 0);
+        instances.remove_instance(&gpu.device, &gpu.queue, 0);
+        assert!(instances.capacity() < 8);
+    }
+
+    #[test]
+    fn look_at_minus_z_is_identity_and_plus_x_yaws_ninety() {
+        let mut instance = Instance::default();
+        instance.look_at(cgmath::Vector3::new(0.0, 0.0, -1.0), cgmath::Vector3::unit_y());
+        assert!(approx_eq_quat(instance.rotation, cgmath::Quaternion::one(), DEFAULT_EPSILON));
+
+        instance.look_at(cgmath::Vector3::new(1.0, 0.0, 0.0), cgmath::Vector3::unit_y());
+        let yaw = cgmath::Quaternion::from_angle_y(cgmath::Deg(-90.0));
+        assert!(approx_eq_quat(instance.rotation, yaw, DEFAULT_EPSILON));
+        assert!(approx_eq_vec3(instance.rotation * -cgmath::Vector3::unit_z(), cgmath::Vector3::unit_x(), DEFAULT_EPSILON));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn iter_mut_dirties_and_update_if_dirty_cleans() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 3, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+        instances.update_if_dirty(&gpu.device, &gpu.queue);
+        assert!(!instances.dirty);
+
+        for instance in instances.iter_mut() {
+            instance.position.y += 1.0;
+        }
+        assert!(instances.dirty);
+
+        instances.update_if_dirty(&gpu.device, &gpu.queue);
+        assert!(!instances.dirty);
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn swap_removing_the_first_of_three_moves_the_last_in() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 3, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+        instances.update_if_dirty(&gpu.device, &gpu.queue);
+        let [first, second, third]: [Instance; 3] = instances.instances.clone().try_into().unwrap();
+
+        let removed = instances.swap_remove_instance(&gpu.device, &gpu.queue, 0).unwrap();
+
+        assert_eq!(removed.position, first.position);
+        let positions = instances.instances.iter().map(|instance| instance.position).collect::<Vec<_>>();
+        assert_eq!(positions, vec![third.position, second.position]);
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn reserving_a_thousand_then_pushing_five_hundred_never_reallocates() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponent::with_capacity(&gpu.device, 1000);
+
+        for x in 0..500 {
+            instances.push_instance(&gpu.device, &gpu.queue, Instance::new(cgmath::Vector3::new(x as f32, 0.0, 0.0), cgmath::Quaternion::one()));
+        }
+
+        assert_eq!(instances.len(), 500);
+        assert_eq!(instances.capacity(), 1000);
+        assert_eq!(instances.reallocation_count(), 0);
+    }
+
+    #[test]
+    fn truncated_blob_is_too_small() {
+        let instances = InstanceComponent::create_instances(InstanceLayout::Line { count: 3, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT);
+        let bytes = serialize_instances(&instances);
+
+        let truncated = deserialize_instances(&bytes[..bytes.len() - 1]);
+        assert!(matches!(truncated, Err(WafError::BufferTooSmall { expected, actual }) if expected == bytes.len() && actual == bytes.len() - 1));
+
+        let header_only = deserialize_instances(&bytes[..3]);
+        assert!(matches!(header_only, Err(WafError::BufferTooSmall { expected: SERIALIZED_HEADER_SIZE, actual: 3 })));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn merging_three_into_two_appends_in_order() {
+        let gpu = test_renderer();
+        let at = |x: f32| Instance::new(cgmath::Vector3::new(x, 0.0, 0.0), cgmath::Quaternion::one());
+
+        let mut instances = InstanceComponent::new(&gpu.device, InstanceLayout::Custom(vec![at(0.0), at(1.0)]), SINGLE_INSTANCE_DISPLACEMENT);
+        let other = InstanceComponent::new(&gpu.device, InstanceLayout::Custom(vec![at(2.0), at(3.0), at(4.0)]), SINGLE_INSTANCE_DISPLACEMENT);
+        instances.merge(&gpu.device, &gpu.queue, other);
+
+        let xs = instances.instances.iter().map(|instance| instance.position.x).collect::<Vec<_>>();
+        assert_eq!(xs, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    fn highlighting_twice_then_clearing_restores_the_original_color() {
+        let instance = Instance { color: cgmath::Vector4::new(0.1, 0.2, 0.3, 0.4), ..Instance::default() };
+        let (mut instances, mut highlights) = (vec![instance], Highlights::new());
+
+        assert!(set_highlight(&mut instances, &mut highlights, 0, cgmath::Vector4::new(1.0, 1.0, 0.0, 1.0)));
+        assert!(set_highlight(&mut instances, &mut highlights, 0, cgmath::Vector4::new(0.0, 1.0, 1.0, 1.0)));
+        assert!(highlights.contains_key(&0));
+
+        assert!(clear_highlight(&mut instances, &mut highlights, 0));
+        assert!(!highlights.contains_key(&0));
+        assert_eq!(instances[0].color, instance.color);
+        assert!(!clear_highlight(&mut instances, &mut highlights, 0));
+    }
+
+    #[test]
+    fn euler_angles_round_trip_away_from_the_poles() {
+        for (pitch, yaw, roll) in [(10.0, 20.0, 30.0), (-45.0, 170.0, -60.0), (80.0, -120.0, 5.0), (0.0, 0.0, 0.0)] {
+            let mut instance = Instance::default();
+            instance.set_euler(pitch, yaw, roll);
+            let (p, y, r) = instance.euler_angles();
+
+            assert!(approx_eq(p, pitch, 1e-3) && approx_eq(y, yaw, 1e-3) && approx_eq(r, roll, 1e-3),
+                "({}, {}, {}) came back as ({}, {}, {})", pitch, yaw, roll, p, y, r);
+        }
+    }
+
+    #[test]
+    fn retaining_non_negative_y_drops_the_negatives_in_order() {
+        let at = |y: f32| Instance::new(cgmath::Vector3::new(0.0, y, 0.0), cgmath::Quaternion::one());
+        let (mut instances, mut highlights) = (vec![at(1.0), at(-1.0), at(0.0), at(-2.0), at(3.0)], Highlights::new());
+        set_highlight(&mut instances, &mut highlights, 4, Instance::white());
+
+        let order = retain_order(&instances, |instance| instance.position.y >= 0.0);
+        reorder(&mut instances, &mut highlights, &order);
+
+        let ys = instances.iter().map(|instance| instance.position.y).collect::<Vec<_>>();
+        assert_eq!(ys, vec![1.0, 0.0, 3.0]);
+        // The highlight went along with the instance at 3.
+        assert_eq!(highlights.keys().collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn retain_uploads_what_it_kept() {
+        let gpu = test_renderer();
+        let at = |y: f32| Instance::new(cgmath::Vector3::new(0.0, y, 0.0), cgmath::Quaternion::one());
+        let mut instances = InstanceComponent::new(&gpu.device,
+            InstanceLayout::Custom(vec![at(1.0), at(-1.0), at(0.0), at(-2.0), at(3.0)]), SINGLE_INSTANCE_DISPLACEMENT);
+
+        instances.retain(&gpu.device, |instance| instance.position.y >= 0.0);
+
+        assert_eq!(instances.len(), 3);
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    fn spin_comes_back_round_after_a_period() {
+        let base = Instance::new(cgmath::Vector3::zero(), cgmath::Quaternion::from_angle_x(cgmath::Deg(30.0)));
+        let animation = spin(vec![base], cgmath::Vector3::new(0.0, 2.0, 0.0), 1.5);
+
+        for t in [0.0, 0.4, 2.9] {
+            let (now, later) = (animation(0, t), animation(0, t + 1.5));
+            assert!(approx_eq_quat(now.rotation, later.rotation, 1e-4), "{:?} != {:?}", now.rotation, later.rotation);
+        }
+
+        assert!(!approx_eq_quat(animation(0, 0.0).rotation, animation(0, 0.75).rotation, 1e-4));
+    }
+
+    #[test]
+    fn tex_layers_survive_into_raw_and_serialized_instances() {
+        let layer = |tex_layer| Instance { tex_layer, ..Instance::default() };
+        let (first, second) = (layer(1), layer(3));
+
+        assert_eq!((first.to_raw().tex_layer, second.to_raw().tex_layer), (1, 3));
+
+        let bytes = serialize_instances(&[first, second]);
+
+        // The layer is the last word of each instance.
+        let layer_at = |instance: usize| {
+            let end = SERIALIZED_HEADER_SIZE + (instance + 1) * SERIALIZED_INSTANCE_SIZE;
+            u32::from_le_bytes(bytes[end - 4..end].try_into().unwrap())
+        };
+        assert_eq!((layer_at(0), layer_at(1)), (1, 3));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn double_buffered_updates_alternate_between_buffers() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponentBuilder::new()
+            .instance(Instance::default())
+            .double_buffered(true)
+            .build(&gpu.device);
+        assert_eq!(instances.current_buffer_index(), 0);
+
+        instances.update_instances(&gpu.device, &gpu.queue);
+        assert_eq!(instances.current_buffer_index(), 1);
+
+        instances.update_instances(&gpu.device, &gpu.queue);
+        assert_eq!(instances.current_buffer_index(), 0);
+    }
+
+    #[test]
+    fn same_seed_scatters_the_same_positions() {
+        let scatter_with = |seed| InstanceComponent::create_instances(InstanceLayout::Scatter {
+            count: 16,
+            bounds: (cgmath::Vector3::new(-10.0, 0.0, -10.0), cgmath::Vector3::new(10.0, 2.0, 10.0)),
+            seed,
+            scale_jitter: 0.25,
+        }, cgmath::Vector3::zero());
+        let positions = |instances: Vec<Instance>| instances.iter().map(|instance| instance.position).collect::<Vec<_>>();
+
+        assert_eq!(positions(scatter_with(7)), positions(scatter_with(7)));
+        assert_ne!(positions(scatter_with(7)), positions(scatter_with(8)));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn read_back_matches_the_raw_instances() {
+        let gpu = test_renderer();
+        let mut instances = InstanceComponentBuilder::new()
+            .layout(InstanceLayout::Line { count: 4, spacing: 2.0 })
+            .build(&gpu.device);
+        instances.iter_mut().for_each(|instance| instance.tex_layer = 2);
+
+        instances.update_instances(&gpu.device, &gpu.queue);
+
+        assert_gpu_matches(&instances, &gpu);
+    }
+
+    #[test]
+    fn default_color_is_opaque_white() {
+        assert_eq!(Instance::default().to_raw().color, [1.0; 4]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::any::Any;
+use cgmath::{ortho, InnerSpace, EuclideanSpace, Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::ecs::component::Component;
+use crate::math::convert::vec3_to_array;
+
+/// Half the width of what a `DirectionalLight` shadows by default, in world units.
+pub const DEFAULT_SHADOW_EXTENT: f32 = 50.0;
+
+/// Uniforms are laid out with std140 rules, where a `vec3` is aligned (and padded) to 16 bytes.
+/// That padding has to be spelled out here, otherwise `color` lands at byte 12 on our side and
+/// byte 16 on the GPU's and the shader reads garbage. Any new field must keep the struct size a
+/// multiple of 16.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniform {
+    position: [f32; 3],
+    _padding: u32,
+    color: [f32; 3],
+    _padding2: u32,
+}
+
+impl LightUniform {
+    pub fn new(position: Vector3<f32>, color: Vector3<f32>) -> Self {
+        Self {
+            position: vec3_to_array(position),
+            _padding: 0,
+            color: vec3_to_array(color),
+            _padding2: 0,
+        }
+    }
+}
+
+pub struct LightComponent {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub buffer: wgpu::Buffer,
+}
+
+impl Component for LightComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "LightComponent"
+    }
+}
+
+impl LightComponent {
+    pub fn new(device: &wgpu::Device, position: Vector3<f32>, color: Vector3<f32>) -> Self {
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Light Buffer"),
+                contents: bytemuck::cast_slice(&[LightUniform::new(position, color)]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        Self {
+            position,
+            color,
+            buffer,
+        }
+    }
+
+    pub fn update_buffer(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[LightUniform::new(self.position, self.color)]));
+    }
+}
+
+/// A light infinitely far away (the sun, say), shining the same way everywhere. It's what
+/// `ShadowPass` casts shadows from, over a box `2 * extent` wide around `center` and reaching
+/// `distance` either side of it along the light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// Which way the light travels (so from the light, towards the scene).
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub center: Point3<f32>,
+    pub extent: f32,
+    pub distance: f32,
+}
+
+impl Component for DirectionalLight {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "DirectionalLight"
+    }
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3<f32>, color: Vector3<f32>) -> Self {
+        Self {
+            direction: direction.normalize(),
+            color,
+            center: Point3::origin(),
+            extent: DEFAULT_SHADOW_EXTENT,
+            distance: DEFAULT_SHADOW_EXTENT,
+        }
+    }
+
+    /// Moves the shadowed box to sit around `center` (usually wherever the camera is looking).
+    pub fn with_bounds(mut self, center: Point3<f32>, extent: f32, distance: f32) -> Self {
+        self.center = center;
+        self.extent = extent;
+        self.distance = distance;
+        self
+    }
+
+    /// The orthographic view-projection shadows are rendered (and looked up) with. `center` ends
+    /// up in the middle of clip space, halfway through its depth.
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        let direction = self.direction.normalize();
+        // Looking straight up or down, the usual up vector would be parallel to the view.
+        let up = if direction.y.abs() > 0.99 {
+            Vector3::unit_z()
+        } else {
+            Vector3::unit_y()
+        };
+
+        let eye = self.center - direction * self.distance;
+        let view = Matrix4::look_at_rh(eye, self.center, up);
+        let projection = ortho(-self.extent, self.extent, -self.extent, self.extent, 0.0, 2.0 * self.distance);
+
+        OPENGL_TO_WGPU_MATRIX * projection * view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_uniform_size_is_a_multiple_of_16() {
+        assert_eq!(std::mem::size_of::<LightUniform>() % 16, 0);
+    }
+
+    #[test]
+    fn view_projection_puts_the_scene_center_inside_ndc() {
+        let light = DirectionalLight::new(Vector3::new(-1.0, -2.0, 0.5), Vector3::new(1.0, 1.0, 1.0))
+            .with_bounds(Point3::new(3.0, 1.0, -2.0), 10.0, 20.0);
+
+        let clip = light.view_projection() * light.center.to_homogeneous();
+        let ndc = clip.truncate() / clip.w;
+
+        for axis in [ndc.x, ndc.y, ndc.z] {
+            assert!((-1.0..=1.0).contains(&axis), "{:?} is outside NDC", ndc);
+        }
+        assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+    }
+}
@@ -0,0 +1,66 @@
+use std::any::Any;
+
+use crate::ecs::component::Component;
+use crate::ecs::component::renderable::MeshHandle;
+
+/// Swaps an `InstanceComponent`'s mesh for cheaper ones as its instances get further from the
+/// camera. Each level is `(max_distance, mesh)`, kept sorted nearest first: an instance uses the
+/// first level it's closer than, and anything past the last threshold uses the last level.
+pub struct LodComponent {
+    pub levels: Vec<(f32, MeshHandle)>,
+}
+
+impl Component for LodComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "LodComponent"
+    }
+}
+
+impl LodComponent {
+    pub fn new(mut levels: Vec<(f32, MeshHandle)>) -> Self {
+        levels.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        Self {
+            levels,
+        }
+    }
+
+    /// Index into `levels` for something `distance` away from the camera, or `None` if there
+    /// aren't any levels.
+    pub fn select(&self, distance: f32) -> Option<usize> {
+        if self.levels.is_empty() {
+            return None;
+        }
+
+        Some(self.levels.iter()
+            .position(|(max_distance, _)| distance < *max_distance)
+            .unwrap_or(self.levels.len() - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::world::World;
+
+    #[test]
+    fn near_picks_the_first_level_and_far_the_last() {
+        let mut world = World::new();
+        let (high, medium, low) = (world.spawn(), world.spawn(), world.spawn());
+        let lod = LodComponent::new(vec![(50.0, low), (10.0, high), (25.0, medium)]);
+
+        assert_eq!(lod.select(1.0), Some(0));
+        assert_eq!(lod.levels[0].1, high);
+        assert_eq!(lod.select(1000.0), Some(lod.levels.len() - 1));
+        assert_eq!(lod.levels[lod.levels.len() - 1].1, low);
+        assert_eq!(LodComponent::new(vec![]).select(1.0), None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::any::Any;
+#[cfg(feature = "image-loading")]
+use anyhow::{Context, Result};
+
+use crate::ecs::component::Component;
+use crate::texture::Texture;
+
+pub struct MaterialComponent {
+    pub diffuse_texture: Texture,
+    pub bind_group: wgpu::BindGroup,
+    /// Alpha blends instead of overwriting, and leaves the depth buffer alone. Drawn after
+    /// everything opaque (see `ScenePipeline::material_variant`).
+    pub transparent: bool,
+    /// More than 1 for texture arrays (see `from_layers`), where each instance picks its layer
+    /// with `Instance::tex_layer`.
+    pub layers: u32,
+}
+
+impl Component for MaterialComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "MaterialComponent"
+    }
+}
+
+impl MaterialComponent {
+    /// `bytes` must already be decoded RGBA8, `dimensions.0 * dimensions.1 * 4` bytes long.
+    pub fn from_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8], dimensions: (u32, u32), label: &str) -> Self {
+        let diffuse_texture = Texture::from_rgba(device, queue, bytes, dimensions, Some(label));
+
+        Self::from_texture(device, diffuse_texture, label)
+    }
+
+    /// Decodes a PNG/JPEG (or anything else `image` understands) and uploads it. Whatever the
+    /// source format, it's converted to RGBA8 first.
+    #[cfg(feature = "image-loading")]
+    pub fn from_image_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8]) -> Result<Self> {
+        let img = image::load_from_memory(bytes)
+            .context("Unable to decode material image data (is it actually a PNG or JPEG?)")?;
+        let diffuse_texture = Texture::from_image(device, queue, &img, Some("Material Texture"))?;
+
+        Ok(Self::from_texture(device, diffuse_texture, "Material Texture"))
+    }
+
+    /// A texture array with one layer per entry of `layers`, each `dimensions` big and decoded
+    /// RGBA8 (like `from_bytes`).
+    pub fn from_layers(device: &wgpu::Device, queue: &wgpu::Queue, layers: &[&[u8]], dimensions: (u32, u32), label: &str) -> Self {
+        let diffuse_texture = Texture::from_rgba_layers(device, queue, layers, dimensions,
+            wgpu::TextureViewDimension::D2Array, Some(label));
+
+        Self {
+            layers: layers.len().max(1) as u32,
+            ..Self::from_texture(device, diffuse_texture, label)
+        }
+    }
+
+    /// Any 2D texture will do. It gets viewed as an array (of one, unless it came from
+    /// `from_layers`), as that's what `bind_group_layout` and the scene shaders take.
+    pub fn from_texture(device: &wgpu::Device, mut diffuse_texture: Texture, label: &str) -> Self {
+        diffuse_texture.view = diffuse_texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let bind_group = Self::create_bind_group(device, &Self::bind_group_layout(device), &diffuse_texture, label);
+
+        Self {
+            diffuse_texture,
+            bind_group,
+            transparent: false,
+            layers: 1,
+        }
+    }
+
+    fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, diffuse_texture: &Texture, label: &str) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+            ],
+            label: Some(format!("material_bind_group ({})", label).as_str()),
+        })
+    }
+
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// What the pipeline drawing this material blends with.
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        if self.transparent {
+            wgpu::BlendState::ALPHA_BLENDING
+        } else {
+            wgpu::BlendState::REPLACE
+        }
+    }
+
+    pub fn writes_depth(&self) -> bool {
+        !self.transparent
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("material_bind_group_layout"),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "image-loading"))]
+mod tests {
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    // A 3x2 opaque red PNG.
+    const TINY_PNG: [u8; 74] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x9d, 0x74, 0x66,
+        0x1a, 0x00, 0x00, 0x00, 0x11, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0xf0,
+        0x1f, 0x86, 0x19, 0x90, 0x39, 0x00, 0x9b, 0x7e, 0x0b, 0xf5, 0x72, 0xb0, 0xb9, 0x3c, 0x00, 0x00,
+        0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn tiny_png_decodes_to_its_dimensions() {
+        let img = image::load_from_memory(&TINY_PNG).unwrap();
+        assert_eq!(img.dimensions(), (3, 2));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn tiny_png_makes_a_material() {
+        let gpu = test_renderer();
+        assert!(MaterialComponent::from_image_bytes(&gpu.device, &gpu.queue, &TINY_PNG).is_ok());
+    }
+}
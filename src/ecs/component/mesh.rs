@@ -3,6 +3,16 @@ use wgpu::util::DeviceExt;
 
 use crate::vertex::PureVertex;
 use crate::ecs::component::Component;
+#[cfg(feature = "obj-loading")]
+use crate::error::WafError;
+#[cfg(feature = "obj-loading")]
+use cgmath::prelude::*;
+#[cfg(feature = "obj-loading")]
+use crate::render::primitives::normal_color;
+
+/// A model's name, vertices and indices, as read out of an OBJ file.
+#[cfg(feature = "obj-loading")]
+type ObjGeometry = (String, Vec<PureVertex>, Vec<u32>);
 
 pub struct MeshComponent {
     pub desc: String,
@@ -14,12 +24,25 @@ pub struct MeshComponent {
     pub num_indices: u32,
     pub parent_index: usize,
     pub instance_component_index: usize,
+    /// Along with `cull_mode`, picks which of the scene pipeline's variants the mesh is drawn
+    /// with (see `ScenePipeline::variant`).
+    pub topology: wgpu::PrimitiveTopology,
+    /// `None` draws both sides.
+    pub cull_mode: Option<wgpu::Face>,
 }
 
 impl Component for MeshComponent {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "MeshComponent"
+    }
 }
 
 impl MeshComponent {
@@ -83,6 +106,87 @@ impl MeshComponent {
         Self::new("EMPTY".to_owned(), device, vec![], vec![], 0, 0)
     }
 
+    /// A unit square on the XY plane facing +Z, in white so instance colors and materials show
+    /// as they are. Handy for billboards and sprites.
+    pub fn quad(device: &wgpu::Device) -> Self {
+        let vertices = vec![
+            PureVertex { position: [-0.5, -0.5, 0.0], color: [1.0, 1.0, 1.0] },
+            PureVertex { position: [0.5, -0.5, 0.0], color: [1.0, 1.0, 1.0] },
+            PureVertex { position: [0.5, 0.5, 0.0], color: [1.0, 1.0, 1.0] },
+            PureVertex { position: [-0.5, 0.5, 0.0], color: [1.0, 1.0, 1.0] },
+        ];
+
+        let indices = vec![
+            0, 1, 2,
+            0, 2, 3,
+        ];
+
+        Self::new("QUAD".to_owned(), device, vertices, indices, 0, 0)
+    }
+
+    /// One mesh per model (object or group) in `bytes`, which tobj already splits by material.
+    /// Faces are triangulated. Vertices keep the file's colors if it has any, and are otherwise
+    /// colored by their normals like the built-in primitives (see `primitives::normal_color`),
+    /// with flat normals for models that don't come with their own. Materials (`mtllib`) are
+    /// ignored, since there's nowhere to load them from.
+    #[cfg(feature = "obj-loading")]
+    pub fn from_obj(device: &wgpu::Device, bytes: &[u8]) -> Result<Vec<Self>, WafError> {
+        Ok(Self::obj_geometry(bytes)?.into_iter()
+            .map(|(name, vertices, indices)| Self::new(name, device, vertices, indices, 0, 0))
+            .collect())
+    }
+
+    /// Each of `from_obj`'s meshes, before any of it goes up.
+    #[cfg(feature = "obj-loading")]
+    fn obj_geometry(bytes: &[u8]) -> Result<Vec<ObjGeometry>, WafError> {
+        let options = tobj::LoadOptions {
+            single_index: true,
+            triangulate: true,
+            ..Default::default()
+        };
+
+        let (models, _) = tobj::load_obj_buf(&mut std::io::Cursor::new(bytes), &options, |_| Err(tobj::LoadError::OpenFileFailed))
+            .map_err(|e| WafError::DecodeFailed(format!("Unable to parse OBJ: {}", e)))?;
+
+        Ok(models.into_iter().map(|model| {
+            let mesh = model.mesh;
+            let triple = |values: &[f32], index: usize| [values[index * 3], values[index * 3 + 1], values[index * 3 + 2]];
+            let position = |index: usize| triple(&mesh.positions, index);
+
+            let (vertices, indices) = if !mesh.vertex_color.is_empty() || !mesh.normals.is_empty() {
+                let vertices = (0..mesh.positions.len() / 3).map(|index| PureVertex {
+                    position: position(index),
+                    color: if mesh.vertex_color.is_empty() {
+                        normal_color(triple(&mesh.normals, index))
+                    } else {
+                        triple(&mesh.vertex_color, index)
+                    },
+                }).collect();
+
+                (vertices, mesh.indices.clone())
+            } else {
+                // Flat normals need vertices that aren't shared between faces.
+                let mut vertices = Vec::with_capacity(mesh.indices.len());
+
+                for face in mesh.indices.chunks_exact(3) {
+                    let [a, b, c] = [0, 1, 2].map(|i| cgmath::Vector3::from(position(face[i] as usize)));
+                    let normal = (b - a).cross(c - a);
+                    let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { normal };
+
+                    vertices.extend(face.iter().map(|&index| PureVertex {
+                        position: position(index as usize),
+                        color: normal_color(normal.into()),
+                    }));
+                }
+
+                let indices = (0..vertices.len() as u32).collect();
+                (vertices, indices)
+            };
+
+            (model.name, vertices, indices)
+        }).collect())
+    }
+
     pub fn new(desc: String, device: &wgpu::Device, vertices: Vec<PureVertex>, indices: Vec<u32>, parent_index: usize, instance_component_index: usize) -> Self {
         let buffers = Self::generate_buffers("UNINIT".to_owned(), &vertices, &indices, device);
         let vertex_buffer = buffers.0;
@@ -101,9 +205,18 @@ impl MeshComponent {
             num_indices,
             parent_index,
             instance_component_index,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: Some(wgpu::Face::Back),
         }
     }
 
+    /// E.g. `PointList` and no culling for a point cloud.
+    pub fn with_primitive(mut self, topology: wgpu::PrimitiveTopology, cull_mode: Option<wgpu::Face>) -> Self {
+        self.topology = topology;
+        self.cull_mode = cull_mode;
+        self
+    }
+
     pub fn update_buffers(&mut self, device: &wgpu::Device) {
         let buffers = Self::generate_buffers(self.desc.clone(), &self.vertices, &self.indices, device);
 
@@ -111,11 +224,11 @@ impl MeshComponent {
         self.index_buffer = buffers.1;
     }
 
-    fn generate_buffers(desc: String, vertices: &Vec<PureVertex>, indices: &Vec<u32>, device: &wgpu::Device) -> (wgpu::Buffer, wgpu::Buffer) {
+    fn generate_buffers(desc: String, vertices: &[PureVertex], indices: &[u32], device: &wgpu::Device) -> (wgpu::Buffer, wgpu::Buffer) {
         let vertex_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some(format!("Vertex Buffer ({})", desc).as_str()),
-                contents: bytemuck::cast_slice(&vertices),
+                contents: bytemuck::cast_slice(vertices),
                 usage: wgpu::BufferUsages::VERTEX,
             }
         );
@@ -123,12 +236,77 @@ impl MeshComponent {
         let index_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some(format!("Index Buffer ({})", desc).as_str()),
-                contents: bytemuck::cast_slice(&indices),
+                contents: bytemuck::cast_slice(indices),
                 usage: wgpu::BufferUsages::INDEX,
             }
         );
 
-        return (vertex_buffer, index_buffer);
+        (vertex_buffer, index_buffer)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn quad_has_six_indices() {
+        let gpu = test_renderer();
+        let quad = MeshComponent::quad(&gpu.device);
+
+        assert_eq!(quad.num_indices, 6);
+        assert_eq!(quad.indices.len(), 6);
+    }
+
+    #[cfg(feature = "obj-loading")]
+    const TRIANGLE_OBJ: &[u8] = b"o Triangle\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    #[cfg(feature = "obj-loading")]
+    #[test]
+    fn embedded_triangle_obj_has_three_vertices() {
+        let meshes = MeshComponent::obj_geometry(TRIANGLE_OBJ).unwrap();
+        assert_eq!(meshes.len(), 1);
+
+        let (_, vertices, indices) = &meshes[0];
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(indices.len(), 3);
+        // No normals in the file, so it's colored by the flat one facing +Z.
+        assert_eq!(vertices[0].color, normal_color([0.0, 0.0, 1.0]));
+    }
+
+    #[cfg(feature = "obj-loading")]
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn loaded_obj_is_drawn_by_the_render_system() {
+        use crate::ecs::system::System;
+        use crate::ecs::world::World;
+        use crate::ecs::component::instance::{Instance, InstanceComponentBuilder};
+        use crate::ecs::component::material::MaterialComponent;
+        use crate::render::pipeline::test_scene_pipeline;
+        use crate::render::render_system::{RecordingPass, RenderSystem};
+        use crate::render::stats::RenderStats;
+
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        let mut world = World::new();
+        for mesh in MeshComponent::from_obj(device, TRIANGLE_OBJ).unwrap() {
+            world.spawn_bundle((mesh, InstanceComponentBuilder::new().instance(Instance::default()).build(device)));
+        }
+
+        let pipeline = test_scene_pipeline(device);
+        let material = MaterialComponent::from_bytes(device, &gpu.queue, &[255; 4], (1, 1), "Test Material");
+
+        let mut system = RenderSystem::new();
+        system.run(&mut world, 0.0);
+
+        let mut pass = RecordingPass::default();
+        let mut stats = RenderStats::default();
+        system.draw(&world, &pipeline, &material.bind_group, false, &mut pass, &mut stats);
+
+        assert_eq!(pass.draws, 1);
+        assert_eq!(stats.triangles, 1);
+    }
+}
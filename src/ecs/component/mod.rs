@@ -1,8 +1,56 @@
 pub mod mesh;
 pub mod instance;
+pub mod transform;
+pub mod camera;
+pub mod light;
+pub mod material;
+pub mod parent;
+pub mod skybox;
+pub mod name;
+pub mod renderable;
+pub mod velocity;
+pub mod lod;
+pub mod billboard;
 
 use std::any::Any;
 
+/// Both accessors are required (and are almost always just `self`): `as_any` lets systems read a
+/// concrete component back out of a `Box<dyn Component>`, and `as_any_mut` lets them mutate it.
 pub trait Component {
     fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// What to call the component in debug dumps and type tags. The default is the full path
+    /// from `std::any::type_name`, which isn't guaranteed to be stable, so the components in this
+    /// crate return their bare name instead.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::instance::InstanceComponent;
+    use crate::ecs::component::name::NameComponent;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    fn downcast_mut_through_dyn_component() {
+        let mut boxed: Box<dyn Component> = Box::new(NameComponent("before".to_owned()));
+
+        let component: &mut dyn Component = boxed.as_mut();
+        component.as_any_mut().downcast_mut::<NameComponent>().unwrap().0 = "after".to_owned();
+
+        assert_eq!(boxed.as_any().downcast_ref::<NameComponent>().unwrap().0, "after");
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn boxed_instance_component_reports_its_name() {
+        let gpu = test_renderer();
+        let boxed: Box<dyn Component> = Box::new(InstanceComponent::default(&gpu.device));
+
+        assert_eq!(boxed.type_name(), "InstanceComponent");
+    }
 }
@@ -0,0 +1,22 @@
+use std::any::Any;
+
+use crate::ecs::component::Component;
+
+/// A human readable name, mostly so entities can be looked up (and logged) by something nicer
+/// than their `EntityId`. Names don't have to be unique.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameComponent(pub String);
+
+impl Component for NameComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "NameComponent"
+    }
+}
@@ -0,0 +1,21 @@
+use std::any::Any;
+
+use crate::ecs::component::Component;
+use crate::ecs::world::EntityId;
+
+/// Makes the owning entity's `TransformComponent` relative to another entity's.
+pub struct ParentComponent(pub EntityId);
+
+impl Component for ParentComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "ParentComponent"
+    }
+}
@@ -0,0 +1,32 @@
+use std::any::Any;
+
+use crate::ecs::component::Component;
+use crate::ecs::world::EntityId;
+
+/// Meshes live on their own entities (as a `MeshComponent`), and get referred to by that entity.
+pub type MeshHandle = EntityId;
+
+/// Draws the owning entity (at its `TransformComponent`) with a mesh and material that live on
+/// other entities, so any number of entities can share them. Everything pointing at the same
+/// mesh and material gets drawn in a single batch by the `RenderSystem`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderableComponent {
+    // Entity holding the `MeshComponent`.
+    pub mesh: MeshHandle,
+    // Entity holding the `MaterialComponent`, if any.
+    pub material: Option<EntityId>,
+}
+
+impl Component for RenderableComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "RenderableComponent"
+    }
+}
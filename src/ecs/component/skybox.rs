@@ -0,0 +1,248 @@
+use std::any::Any;
+use anyhow::{bail, Result};
+#[cfg(feature = "image-loading")]
+use anyhow::Context;
+use cgmath::SquareMatrix;
+use wgpu::util::DeviceExt;
+
+use crate::ecs::component::Component;
+use crate::math::convert::mat4_to_array;
+
+/// The inverse of the camera's rotation-only view-projection, so the skybox shader can turn a
+/// screen position back into a direction to sample the cubemap with.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyboxUniform {
+    inverse_view_proj: [[f32; 4]; 4],
+}
+
+pub struct SkyboxComponent {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Component for SkyboxComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "SkyboxComponent"
+    }
+}
+
+impl SkyboxComponent {
+    /// Faces go in the usual cubemap order: +X, -X, +Y, -Y, +Z, -Z. Each one must already be
+    /// decoded RGBA8, `dimensions.0 * dimensions.1 * 4` bytes long.
+    pub fn from_faces(device: &wgpu::Device, queue: &wgpu::Queue, faces: [&[u8]; 6], dimensions: (u32, u32)) -> Result<Self> {
+        let face_size = dimensions.0 as usize * dimensions.1 as usize * 4;
+
+        if dimensions.0 != dimensions.1 {
+            bail!("Skybox faces must be square, but got {}x{}", dimensions.0, dimensions.1);
+        }
+
+        if let Some((index, face)) = faces.iter().enumerate().find(|(_, face)| face.len() != face_size) {
+            bail!("Skybox face {} is {} bytes, but {}x{} RGBA8 needs {}", index, face.len(), dimensions.0,
+                dimensions.1, face_size);
+        }
+
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Skybox Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        for (layer, face) in faces.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                },
+                face,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
+                    rows_per_image: std::num::NonZeroU32::new(dimensions.1),
+                },
+                wgpu::Extent3d { depth_or_array_layers: 1, ..size },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Skybox Texture View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniform = SkyboxUniform {
+            inverse_view_proj: mat4_to_array(cgmath::Matrix4::identity()),
+        };
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Skybox Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &Self::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("skybox_bind_group"),
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            buffer,
+            bind_group,
+        })
+    }
+
+    /// Decodes six PNGs/JPEGs (same order as `from_faces'), which all have to be the same size.
+    #[cfg(feature = "image-loading")]
+    pub fn from_image_faces(device: &wgpu::Device, queue: &wgpu::Queue, faces: [&[u8]; 6]) -> Result<Self> {
+        let mut decoded: Vec<image::RgbaImage> = Vec::with_capacity(6);
+
+        for (index, face) in faces.iter().enumerate() {
+            let face = image::load_from_memory(face)
+                .with_context(|| format!("Unable to decode skybox face {}", index))?
+                .to_rgba8();
+
+            if let Some(first) = decoded.first() {
+                if face.dimensions() != first.dimensions() {
+                    bail!("Skybox face {} is {:?}, but face 0 is {:?}", index, face.dimensions(),
+                        first.dimensions());
+                }
+            }
+
+            decoded.push(face);
+        }
+
+        let dimensions = decoded[0].dimensions();
+        let faces = [&decoded[0], &decoded[1], &decoded[2], &decoded[3], &decoded[4], &decoded[5]]
+            .map(|face| face.as_raw().as_slice());
+
+        Self::from_faces(device, queue, faces, dimensions)
+    }
+
+    /// The skybox only follows the camera's rotation, so any translation in `view` is dropped.
+    pub fn update_buffer(&self, queue: &wgpu::Queue, view: cgmath::Matrix4<f32>, projection: cgmath::Matrix4<f32>) {
+        let mut rotation = view;
+        rotation.w = cgmath::Vector4::unit_w();
+
+        let uniform = SkyboxUniform {
+            inverse_view_proj: mat4_to_array((projection * rotation).invert().unwrap_or_else(cgmath::Matrix4::identity)),
+        };
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("skybox_bind_group_layout"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::error_scope::with_error_scope;
+    use crate::render::headless::test_renderer;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn six_1x1_faces_make_a_cube_view() {
+        let gpu = test_renderer();
+
+        // The bind group is laid out for a `Cube` view, so anything else would fail validation.
+        let (skybox, error) = with_error_scope(&gpu.device, "Skybox creation", || {
+            SkyboxComponent::from_faces(&gpu.device, &gpu.queue, [&RED; 6], (1, 1))
+        });
+
+        assert!(skybox.is_ok());
+        assert!(error.is_none(), "{:?}", error);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn mismatched_face_is_an_error() {
+        let gpu = test_renderer();
+        let big = [255; 16];
+
+        let faces: [&[u8]; 6] = [&RED, &RED, &big, &RED, &RED, &RED];
+        assert!(SkyboxComponent::from_faces(&gpu.device, &gpu.queue, faces, (1, 1)).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::any::Any;
+use std::cell::Cell;
+use cgmath::prelude::*;
+
+use crate::ecs::component::Component;
+use crate::ecs::component::instance::Instance;
+
+/// Where an entity is, independent of how (or whether) it ends up batched for the GPU.
+///
+/// The fields are only reachable through setters so `matrix` can cache its result: a transform
+/// that doesn't move only ever gets composed once.
+pub struct TransformComponent {
+    position: cgmath::Vector3<f32>,
+    rotation: cgmath::Quaternion<f32>,
+    scale: cgmath::Vector3<f32>,
+    // Cleared by every setter.
+    matrix: Cell<Option<cgmath::Matrix4<f32>>>,
+    // How many times `matrix' has actually composed one, for the tests to check the cache with.
+    #[cfg(test)]
+    recomputes: Cell<usize>,
+}
+
+impl Component for TransformComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "TransformComponent"
+    }
+}
+
+impl TransformComponent {
+    pub fn identity() -> Self {
+        Self::new(cgmath::Vector3::zero(), cgmath::Quaternion::one(), Instance::unit_scale())
+    }
+
+    pub fn new(position: cgmath::Vector3<f32>, rotation: cgmath::Quaternion<f32>, scale: cgmath::Vector3<f32>) -> Self {
+        Self {
+            position,
+            rotation,
+            scale,
+            matrix: Cell::new(None),
+            #[cfg(test)]
+            recomputes: Cell::new(0),
+        }
+    }
+
+    pub fn position(&self) -> cgmath::Vector3<f32> {
+        self.position
+    }
+
+    pub fn rotation(&self) -> cgmath::Quaternion<f32> {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> cgmath::Vector3<f32> {
+        self.scale
+    }
+
+    pub fn set_position(&mut self, position: cgmath::Vector3<f32>) {
+        self.position = position;
+        self.matrix.set(None);
+    }
+
+    pub fn set_rotation(&mut self, rotation: cgmath::Quaternion<f32>) {
+        self.rotation = rotation;
+        self.matrix.set(None);
+    }
+
+    pub fn set_scale(&mut self, scale: cgmath::Vector3<f32>) {
+        self.scale = scale;
+        self.matrix.set(None);
+    }
+
+    /// Whether the next `matrix` call can skip recomposing.
+    pub fn is_cached(&self) -> bool {
+        self.matrix.get().is_some()
+    }
+
+    // Same composition as `Instance::to_raw', so the two always agree.
+    pub fn matrix(&self) -> cgmath::Matrix4<f32> {
+        if let Some(matrix) = self.matrix.get() {
+            return matrix;
+        }
+
+        let matrix = cgmath::Matrix4::from_translation(self.position)
+            * cgmath::Matrix4::from(self.rotation)
+            * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
+        self.matrix.set(Some(matrix));
+
+        #[cfg(test)]
+        self.recomputes.set(self.recomputes.get() + 1);
+
+        matrix
+    }
+
+    pub fn to_instance(&self) -> Instance {
+        Instance::with_scale(self.position, self.rotation, self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_matrix() {
+        assert_eq!(TransformComponent::identity().matrix(), cgmath::Matrix4::identity());
+    }
+
+    #[test]
+    fn matrix_is_only_composed_again_after_a_change() {
+        let mut transform = TransformComponent::new(cgmath::Vector3::new(1.0, 2.0, 3.0), cgmath::Quaternion::one(), Instance::unit_scale());
+        assert!(!transform.is_cached());
+
+        let first = transform.matrix();
+        let second = transform.matrix();
+        assert_eq!(first, second);
+        assert_eq!(transform.recomputes.get(), 1);
+
+        transform.set_position(cgmath::Vector3::new(4.0, 5.0, 6.0));
+        assert!(!transform.is_cached());
+        assert_eq!(transform.matrix().w, cgmath::Vector4::new(4.0, 5.0, 6.0, 1.0));
+        assert_eq!(transform.recomputes.get(), 2);
+    }
+}
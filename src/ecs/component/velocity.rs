@@ -0,0 +1,41 @@
+use std::any::Any;
+use cgmath::prelude::*;
+
+use crate::ecs::component::Component;
+
+/// How fast an entity's `TransformComponent` moves (units per second) and spins (radians per
+/// second around each axis). The `MotionSystem` does the actual moving.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VelocityComponent {
+    pub linear: cgmath::Vector3<f32>,
+    pub angular: cgmath::Vector3<f32>,
+}
+
+impl Component for VelocityComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "VelocityComponent"
+    }
+}
+
+impl Default for VelocityComponent {
+    fn default() -> Self {
+        Self::new(cgmath::Vector3::zero(), cgmath::Vector3::zero())
+    }
+}
+
+impl VelocityComponent {
+    pub fn new(linear: cgmath::Vector3<f32>, angular: cgmath::Vector3<f32>) -> Self {
+        Self {
+            linear,
+            angular,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use cgmath::{Matrix4, SquareMatrix};
+use log::warn;
+
+use crate::ecs::{
+    world::{EntityId, World},
+    system::System,
+    component::transform::TransformComponent,
+    component::parent::ParentComponent,
+    component::instance::InstanceComponent,
+};
+
+/// Resolves every `TransformComponent` into world space by walking up its `ParentComponent`
+/// chain, then hands the result to the entity's `InstanceComponent` (if it has one) as its
+/// `transform`. Nothing is uploaded here; that still happens on the next `update_instances`.
+///
+/// Entities missing a `TransformComponent` count as the identity, and a parent that has been
+/// despawned ends the chain. A cycle is logged and broken by treating the entity where it was
+/// found as a root, so a bad parent can't hang the frame.
+pub struct TransformPropagationSystem;
+
+impl System for TransformPropagationSystem {
+    fn run(&mut self, world: &mut World, _dt: f32) {
+        let world_matrices = Self::resolve(world);
+
+        for (entity, instances) in world.query_mut::<InstanceComponent>() {
+            if let Some(matrix) = world_matrices.get(&entity) {
+                instances.transform = *matrix;
+            }
+        }
+    }
+}
+
+impl TransformPropagationSystem {
+    pub fn resolve(world: &World) -> HashMap<EntityId, Matrix4<f32>> {
+        let locals = world.query::<TransformComponent>()
+            .map(|(entity, transform)| (entity, transform.matrix()))
+            .collect::<HashMap<_, _>>();
+        let parents = world.query::<ParentComponent>()
+            .filter(|(_, parent)| world.is_alive(parent.0))
+            .map(|(entity, parent)| (entity, parent.0))
+            .collect::<HashMap<_, _>>();
+
+        let mut resolved: HashMap<EntityId, Matrix4<f32>> = HashMap::new();
+
+        // In a fixed order, so it's always the same entity of a cycle that ends up as its root.
+        let mut entities = locals.keys().copied().collect::<Vec<_>>();
+        entities.sort_by_key(|entity| (entity.index, entity.generation));
+
+        for entity in &entities {
+            // Already done as some earlier entity's ancestor. Going again could walk a cycle from
+            // a different spot, and come out with a different answer.
+            if resolved.contains_key(entity) {
+                continue;
+            }
+
+            // Walk up until we hit something already resolved (or a root), remembering the way so
+            // parents are always resolved before their children on the way back down.
+            let mut chain = vec![*entity];
+            let mut base = Matrix4::identity();
+
+            while let Some(parent) = parents.get(chain.last().unwrap()) {
+                if let Some(matrix) = resolved.get(parent) {
+                    base = *matrix;
+                    break;
+                }
+
+                if chain.contains(parent) {
+                    warn!("Entity {:?} is its own ancestor. Treating it as a root!", chain.last().unwrap());
+                    break;
+                }
+
+                chain.push(*parent);
+            }
+
+            for link in chain.iter().rev() {
+                base = base * locals.get(link).copied().unwrap_or_else(Matrix4::identity);
+                resolved.insert(*link, base);
+            }
+        }
+
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Quaternion, Vector3};
+    use crate::ecs::component::instance::Instance;
+
+    fn translation(x: f32, y: f32, z: f32) -> TransformComponent {
+        TransformComponent::new(Vector3::new(x, y, z), Quaternion::new(1.0, 0.0, 0.0, 0.0), Instance::unit_scale())
+    }
+
+    #[test]
+    fn moving_the_root_moves_the_grandchild() {
+        let mut world = World::new();
+        let root = world.spawn_bundle((translation(1.0, 0.0, 0.0),));
+        let child = world.spawn_bundle((translation(0.0, 2.0, 0.0), ParentComponent(root)));
+        let grandchild = world.spawn_bundle((translation(0.0, 0.0, 3.0), ParentComponent(child)));
+
+        let before = TransformPropagationSystem::resolve(&world)[&grandchild].w.truncate();
+        assert_eq!(before, Vector3::new(1.0, 2.0, 3.0));
+
+        world.get_mut::<TransformComponent>(root).unwrap().set_position(Vector3::new(-4.0, 0.0, 0.0));
+        let after = TransformPropagationSystem::resolve(&world)[&grandchild].w.truncate();
+        assert_eq!(after, Vector3::new(-4.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn cycle_breaks_at_the_same_entity_every_time() {
+        let mut world = World::new();
+        let a = world.spawn_bundle((translation(1.0, 0.0, 0.0),));
+        let b = world.spawn_bundle((translation(0.0, 1.0, 0.0), ParentComponent(a)));
+        world.insert(a, ParentComponent(b));
+
+        let resolved = TransformPropagationSystem::resolve(&world);
+        // `b' is reached first from `a', so it's the one that becomes the root.
+        assert_eq!(resolved[&b].w.truncate(), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(resolved[&a].w.truncate(), Vector3::new(1.0, 1.0, 0.0));
+
+        for _ in 0..8 {
+            assert_eq!(TransformPropagationSystem::resolve(&world), resolved);
+        }
+    }
+}
@@ -1,4 +1,15 @@
 pub mod object;
 pub mod scene;
 pub mod component;
-
+pub mod world;
+pub mod system;
+pub mod commands;
+pub mod assets;
+pub mod hierarchy;
+pub mod time;
+pub mod bundle;
+pub mod motion;
+pub mod spatial;
+pub mod billboard;
+#[cfg(feature = "serde")]
+pub mod scene_file;
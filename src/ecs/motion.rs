@@ -0,0 +1,57 @@
+use cgmath::prelude::*;
+
+use crate::ecs::{
+    world::World,
+    system::System,
+    component::transform::TransformComponent,
+    component::velocity::VelocityComponent,
+};
+
+/// Moves every entity with both a `TransformComponent` and a `VelocityComponent` along by one
+/// step of `dt` seconds.
+pub struct MotionSystem;
+
+impl System for MotionSystem {
+    fn run(&mut self, world: &mut World, dt: f32) {
+        let velocities = world.query::<VelocityComponent>()
+            .map(|(entity, velocity)| (entity, *velocity))
+            .collect::<Vec<_>>();
+
+        for (entity, velocity) in velocities {
+            let transform = match world.get_mut::<TransformComponent>(entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+
+            if velocity.linear != cgmath::Vector3::zero() {
+                transform.set_position(transform.position() + velocity.linear * dt);
+            }
+
+            let angle = velocity.angular.magnitude() * dt;
+            if angle != 0.0 {
+                let spin = cgmath::Quaternion::from_axis_angle(velocity.angular.normalize(), cgmath::Rad(angle));
+
+                // Renormalize every step, or the rounding error adds up into a scale.
+                transform.set_rotation((spin * transform.rotation()).normalize());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::approx::{approx_eq_vec3, DEFAULT_EPSILON};
+
+    #[test]
+    fn one_second_at_one_x_moves_one_unit() {
+        let mut world = World::new();
+        let velocity = VelocityComponent::new(cgmath::Vector3::new(1.0, 0.0, 0.0), cgmath::Vector3::zero());
+        let entity = world.spawn_bundle((TransformComponent::identity(), velocity));
+
+        MotionSystem.run(&mut world, 1.0);
+
+        let position = world.get::<TransformComponent>(entity).unwrap().position();
+        assert!(approx_eq_vec3(position, cgmath::Vector3::new(1.0, 0.0, 0.0), DEFAULT_EPSILON), "{:?}", position);
+    }
+}
@@ -2,6 +2,12 @@ pub struct Object {
     pub components: Vec<usize>,
 }
 
+impl Default for Object {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Object {
     pub fn new() -> Self {
         Self {
@@ -6,6 +6,12 @@ pub struct Scene {
     pub components: Vec<Box<dyn Component>>,
 }
 
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scene {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{
+    world::{EntityId, World},
+    component::transform::TransformComponent,
+    component::instance::{Instance, InstanceComponent, InstanceComponentBuilder, InstanceLayout},
+    component::name::NameComponent,
+    component::parent::ParentComponent,
+};
+use crate::math::convert::{vec3_to_array, array_to_vec3, vec4_to_array, array_to_vec4, quat_to_array, array_to_quat};
+
+const SCENE_FILE_VERSION: u32 = 1;
+
+// Only the components that can be rebuilt from plain data are saved. Meshes, materials and the
+// like own GPU resources (and there's no asset system to refer to them by yet), so they have to
+// be re-attached after loading.
+#[derive(Serialize, Deserialize)]
+struct SceneFile {
+    version: u32,
+    entities: Vec<SceneEntity>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneEntity {
+    name: Option<String>,
+    transform: Option<SceneTransform>,
+    instances: Option<Vec<SceneInstance>>,
+    // Index into `SceneFile::entities', since `EntityId`s don't survive a reload.
+    parent: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneTransform {
+    position: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneInstance {
+    position: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    color: [f32; 4],
+    bounding_radius: f32,
+    // Missing from scenes saved before instances had one.
+    #[serde(default)]
+    tex_layer: u32,
+}
+
+impl World {
+    /// Writes the world's names, transforms, instances and parent links out as a versioned JSON
+    /// document. Everything else is skipped (see `load`).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        let mut entities = self.entities().collect::<Vec<_>>();
+        entities.sort_by_key(|entity| entity.index);
+        let indices = entities.iter().enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect::<HashMap<EntityId, usize>>();
+
+        let entities = entities.iter().map(|entity| SceneEntity {
+            name: self.get::<NameComponent>(*entity).map(|name| name.0.clone()),
+            transform: self.get::<TransformComponent>(*entity).map(|transform| SceneTransform {
+                position: vec3_to_array(transform.position()),
+                rotation: quat_to_array(transform.rotation()),
+                scale: vec3_to_array(transform.scale()),
+            }),
+            instances: self.get::<InstanceComponent>(*entity).map(|instances| {
+                instances.instances.iter().map(|instance| SceneInstance {
+                    position: vec3_to_array(instance.position),
+                    rotation: quat_to_array(instance.rotation),
+                    scale: vec3_to_array(instance.scale),
+                    color: vec4_to_array(instance.color),
+                    bounding_radius: instance.bounding_radius,
+                    tex_layer: instance.tex_layer,
+                }).collect()
+            }),
+            // A parent that's since been despawned just isn't saved.
+            parent: self.get::<ParentComponent>(*entity).and_then(|parent| indices.get(&parent.0).copied()),
+        }).collect();
+
+        let json = serde_json::to_string_pretty(&SceneFile { version: SCENE_FILE_VERSION, entities })
+            .context("Unable to serialize the scene")?;
+
+        std::fs::write(path, json).with_context(|| format!("Unable to write scene {}", path.display()))
+    }
+
+    /// Rebuilds a world saved with `save`. Only what `save` writes comes back, so meshes,
+    /// materials and so on need attaching again afterwards.
+    pub fn load(path: impl AsRef<Path>, device: &wgpu::Device) -> Result<World> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read scene {}", path.display()))?;
+        let file: SceneFile = serde_json::from_str(&json)
+            .with_context(|| format!("Scene {} isn't a valid scene file", path.display()))?;
+
+        if file.version != SCENE_FILE_VERSION {
+            bail!("Unsupported scene file version {} (expected {})", file.version, SCENE_FILE_VERSION);
+        }
+
+        let mut world = World::new();
+        let entities = file.entities.iter().map(|_| world.spawn()).collect::<Vec<_>>();
+
+        for (entity, scene_entity) in entities.iter().zip(file.entities) {
+            if let Some(name) = scene_entity.name {
+                world.insert(*entity, NameComponent(name));
+            }
+
+            if let Some(transform) = scene_entity.transform {
+                world.insert(*entity, TransformComponent::new(array_to_vec3(transform.position),
+                    array_to_quat(transform.rotation), array_to_vec3(transform.scale)));
+            }
+
+            if let Some(instances) = scene_entity.instances {
+                let instances = instances.into_iter().map(|instance| {
+                    let mut loaded = Instance::with_scale(array_to_vec3(instance.position),
+                        array_to_quat(instance.rotation), array_to_vec3(instance.scale));
+                    loaded.color = array_to_vec4(instance.color);
+                    loaded.bounding_radius = instance.bounding_radius;
+                    loaded.tex_layer = instance.tex_layer;
+
+                    loaded
+                }).collect();
+
+                world.insert(*entity, InstanceComponentBuilder::new()
+                    .layout(InstanceLayout::Custom(instances))
+                    .build(device));
+            }
+
+            if let Some(parent) = scene_entity.parent {
+                match entities.get(parent) {
+                    Some(parent) => { world.insert(*entity, ParentComponent(*parent)); },
+                    None => bail!("Scene entity {:?} has parent {}, which doesn't exist", entity, parent),
+                }
+            }
+        }
+
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn three_entity_scene_round_trips() {
+        let gpu = test_renderer();
+
+        let mut world = World::new();
+        let root = world.spawn_bundle((NameComponent("Root".to_owned()), TransformComponent::identity()));
+        let child = world.spawn_bundle((NameComponent("Child".to_owned()), ParentComponent(root)));
+        world.spawn_bundle((NameComponent("Grandchild".to_owned()), ParentComponent(child)));
+
+        let path = std::env::temp_dir().join(format!("sit-scene-{}.json", std::process::id()));
+        world.save(&path).unwrap();
+        let loaded = World::load(&path, &gpu.device);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        let root = loaded.find_by_name("Root").unwrap();
+        let child = loaded.find_by_name("Child").unwrap();
+        let grandchild = loaded.find_by_name("Grandchild").unwrap();
+
+        assert!(loaded.get::<ParentComponent>(root).is_none());
+        assert_eq!(loaded.get::<ParentComponent>(child).map(|parent| parent.0), Some(root));
+        assert_eq!(loaded.get::<ParentComponent>(grandchild).map(|parent| parent.0), Some(child));
+        assert!(loaded.get::<TransformComponent>(root).is_some());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use cgmath::prelude::*;
+use cgmath::Vector3;
+
+use crate::ecs::component::instance::InstanceComponent;
+
+type Cell = (i32, i32, i32);
+
+/// Buckets an `InstanceComponent`'s instances by which `cell_size` sized cube their (world space)
+/// position falls in, so box and sphere queries only look at the cells they overlap instead of
+/// every instance. Only positions are considered, not bounding radii.
+///
+/// Indices are the ones into `InstanceComponent::instances`, so the hash needs bringing up to date
+/// (`update`, or `rebuild`) whenever instances move, or get added or removed.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<usize>>,
+    positions: Vec<Vector3<f32>>,
+}
+
+impl SpatialHash {
+    /// Cells about the size of a typical query work best: much smaller means visiting lots of
+    /// empty cells, much bigger means checking lots of instances that aren't in range.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "Spatial hash cell size must be positive (got {})!", cell_size);
+
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: vec![],
+        }
+    }
+
+    pub fn from_instances(cell_size: f32, instances: &InstanceComponent) -> Self {
+        let mut hash = Self::new(cell_size);
+        hash.rebuild(instances);
+        hash
+    }
+
+    /// Like `from_instances`, for positions that don't come from an `InstanceComponent`. Indices
+    /// are then the order the positions came in.
+    pub fn from_positions(cell_size: f32, positions: impl IntoIterator<Item = Vector3<f32>>) -> Self {
+        let mut hash = Self::new(cell_size);
+        hash.insert_all(positions);
+        hash
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Throws everything away and buckets every instance again.
+    pub fn rebuild(&mut self, instances: &InstanceComponent) {
+        self.cells.clear();
+        self.positions.clear();
+
+        self.insert_all(instances.instances.iter().map(|instance| instances.world_position(instance)));
+    }
+
+    // Buckets `positions` after the ones already in the hash.
+    fn insert_all(&mut self, positions: impl IntoIterator<Item = Vector3<f32>>) {
+        for position in positions {
+            let index = self.positions.len();
+
+            self.cells.entry(self.cell(position)).or_default().push(index);
+            self.positions.push(position);
+        }
+    }
+
+    /// Only moves the instances that changed cell (and drops or adds any past the old count),
+    /// which is much cheaper than `rebuild` when most of them stay put.
+    pub fn update(&mut self, instances: &InstanceComponent) {
+        let len = instances.instances.len();
+
+        while self.positions.len() > len {
+            let index = self.positions.len() - 1;
+            let position = self.positions.pop().unwrap_or_else(Vector3::zero);
+            self.remove_from_cell(self.cell(position), index);
+        }
+
+        for (index, instance) in instances.instances.iter().enumerate() {
+            let position = instances.world_position(instance);
+
+            if index == self.positions.len() {
+                self.cells.entry(self.cell(position)).or_default().push(index);
+                self.positions.push(position);
+                continue;
+            }
+
+            let (old_cell, new_cell) = (self.cell(self.positions[index]), self.cell(position));
+            if old_cell != new_cell {
+                self.remove_from_cell(old_cell, index);
+                self.cells.entry(new_cell).or_default().push(index);
+            }
+
+            self.positions[index] = position;
+        }
+    }
+
+    /// Every instance whose position is inside the box, edges included, in index order.
+    pub fn query_aabb(&self, min: Vector3<f32>, max: Vector3<f32>) -> Vec<usize> {
+        let inside = |position: Vector3<f32>| {
+            position.x >= min.x && position.y >= min.y && position.z >= min.z
+                && position.x <= max.x && position.y <= max.y && position.z <= max.z
+        };
+
+        self.query_cells(min, max, inside)
+    }
+
+    /// Every instance whose position is within `radius` of `center`, in index order.
+    pub fn query_radius(&self, center: Vector3<f32>, radius: f32) -> Vec<usize> {
+        let extent = Vector3::new(radius, radius, radius);
+
+        self.query_cells(center - extent, center + extent, |position| position.distance2(center) <= radius * radius)
+    }
+
+    fn query_cells(&self, min: Vector3<f32>, max: Vector3<f32>, inside: impl Fn(Vector3<f32>) -> bool) -> Vec<usize> {
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return vec![];
+        }
+
+        let (low, high) = (self.cell(min), self.cell(max));
+        let span = |low: i32, high: i32| (high as i64 - low as i64 + 1) as u64;
+        let cell_count = span(low.0, high.0).saturating_mul(span(low.1, high.1)).saturating_mul(span(low.2, high.2));
+
+        let mut found = vec![];
+        let mut check = |indices: &Vec<usize>| {
+            found.extend(indices.iter().copied().filter(|&index| inside(self.positions[index])));
+        };
+
+        // A box covering more cells than are in use is quicker to answer by going through the
+        // ones that are.
+        if cell_count > self.cells.len() as u64 {
+            for (cell, indices) in &self.cells {
+                if (low.0..=high.0).contains(&cell.0) && (low.1..=high.1).contains(&cell.1) && (low.2..=high.2).contains(&cell.2) {
+                    check(indices);
+                }
+            }
+        } else {
+            for x in low.0..=high.0 {
+                for y in low.1..=high.1 {
+                    for z in low.2..=high.2 {
+                        if let Some(indices) = self.cells.get(&(x, y, z)) {
+                            check(indices);
+                        }
+                    }
+                }
+            }
+        }
+
+        found.sort_unstable();
+        found
+    }
+
+    fn cell(&self, position: Vector3<f32>) -> Cell {
+        // `as` saturates, so positions way out past the `i32` range just share the edge cells.
+        let quantize = |a: f32| (a / self.cell_size).floor() as i32;
+
+        (quantize(position.x), quantize(position.y), quantize(position.z))
+    }
+
+    fn remove_from_cell(&mut self, cell: Cell, index: usize) {
+        if let Some(indices) = self.cells.get_mut(&cell) {
+            indices.retain(|&other| other != index);
+
+            if indices.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points along X at 0, 0.5, 1, ... 9.5, so there's a neighbor just past every whole unit.
+    fn line() -> impl Iterator<Item = Vector3<f32>> {
+        (0..20).map(|i| Vector3::new(i as f32 * 0.5, 0.0, 0.0))
+    }
+
+    #[test]
+    fn box_query_finds_exactly_whats_inside() {
+        let hash = SpatialHash::from_positions(1.0, line());
+
+        // 1.5 and 4.5 sit just outside.
+        let found = hash.query_aabb(Vector3::new(1.6, -1.0, -1.0), Vector3::new(4.0, 1.0, 1.0));
+        assert_eq!(found, vec![4, 5, 6, 7, 8]);
+
+        // Edges count as inside.
+        assert_eq!(hash.query_aabb(Vector3::new(2.0, 0.0, 0.0), Vector3::new(2.0, 0.0, 0.0)), vec![4]);
+        assert!(hash.query_aabb(Vector3::new(0.0, 0.1, 0.0), Vector3::new(10.0, 1.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn radius_query_excludes_neighbors_just_outside() {
+        let hash = SpatialHash::from_positions(1.0, line());
+
+        assert_eq!(hash.query_radius(Vector3::new(5.0, 0.0, 0.0), 0.9), vec![9, 10, 11]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::ecs::world::World;
+use crate::ecs::commands::Commands;
+use crate::ecs::time::FixedTimestep;
+
+pub trait System {
+    fn run(&mut self, world: &mut World, dt: f32);
+
+    /// What `Scheduler` actually calls. Systems that need to spawn or despawn partway through a
+    /// query can queue it in `commands` instead, which gets applied once they return. Defaults to
+    /// just `run`.
+    fn run_with_commands(&mut self, world: &mut World, _commands: &mut Commands, dt: f32) {
+        self.run(world, dt);
+    }
+}
+
+/// Runs its systems one after another, in the order they were added. Each system's commands are
+/// applied before the next one runs, so it sees them. Change flags (see `World::is_changed`) are
+/// cleared once they've all run, so a system only sees what changed before it in the same frame.
+///
+/// Fixed systems (like `MotionSystem`, whose integration shouldn't depend on the frame rate) run
+/// first, as many times as the world's `FixedTimestep` says fit in the frame, each time with its
+/// `step_seconds` as `dt`.
+pub struct Scheduler {
+    systems: Vec<Box<dyn System>>,
+    fixed_systems: Vec<Box<dyn System>>,
+    commands: Commands,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            systems: vec![],
+            fixed_systems: vec![],
+            commands: Commands::new(),
+        }
+    }
+
+    pub fn add_system<S: System + 'static>(&mut self, system: S) {
+        self.systems.push(Box::new(system));
+    }
+
+    pub fn add_fixed_system<S: System + 'static>(&mut self, system: S) {
+        self.fixed_systems.push(Box::new(system));
+    }
+
+    pub fn update(&mut self, world: &mut World, dt: f32) {
+        if !self.fixed_systems.is_empty() {
+            let mut timestep = world.resource::<FixedTimestep>().copied().unwrap_or_default();
+            let steps = timestep.accumulate(dt);
+            world.insert_resource(timestep);
+
+            for _ in 0..steps {
+                for system in self.fixed_systems.iter_mut() {
+                    system.run_with_commands(world, &mut self.commands, timestep.step_seconds);
+                    self.commands.apply(world);
+                }
+            }
+        }
+
+        for system in self.systems.iter_mut() {
+            system.run_with_commands(world, &mut self.commands, dt);
+            self.commands.apply(world);
+        }
+
+        world.clear_change_flags();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u32);
+
+    impl System for Counter {
+        fn run(&mut self, world: &mut World, _dt: f32) {
+            self.0 += 1;
+            world.insert_resource(self.0);
+        }
+    }
+
+    #[test]
+    fn systems_run_once_per_update() {
+        let mut world = World::new();
+        let mut scheduler = Scheduler::new();
+        scheduler.add_system(Counter(0));
+
+        for _ in 0..3 {
+            scheduler.update(&mut world, 1.0 / 60.0);
+        }
+
+        assert_eq!(world.resource::<u32>(), Some(&3));
+    }
+}
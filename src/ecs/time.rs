@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+/// Anything longer than this (sitting on a breakpoint, dragging the window, ...) gets clamped, so
+/// one huge step doesn't send everything flying.
+pub const MAX_DELTA_SECONDS: f32 = 0.1;
+pub const DEFAULT_FIXED_STEP_SECONDS: f32 = 1.0 / 60.0;
+/// However far behind the fixed steps fall, no more than this many run in one frame. The rest of
+/// the backlog is dropped, or a slow step would only ever make the next frame slower.
+pub const DEFAULT_MAX_FIXED_STEPS: u32 = 5;
+// Frame deltas come in as f32s, so a frame of exactly three steps can add up a hair short.
+const FIXED_STEP_TOLERANCE: f64 = 1e-6;
+
+/// World resource holding the frame clock, ticked once per frame before anything else runs.
+#[derive(Copy, Clone, Debug)]
+pub struct Time {
+    pub delta_seconds: f32,
+    pub elapsed_seconds: f32,
+    pub frame_count: u64,
+    last_tick: Instant,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            delta_seconds: 0.0,
+            elapsed_seconds: 0.0,
+            frame_count: 0,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Advances by however long it's been since the last tick.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let dt = now - self.last_tick;
+
+        self.last_tick = now;
+        self.advance(dt);
+    }
+
+    /// Advances by exactly `dt` (still clamped), without looking at the wall clock.
+    pub fn advance(&mut self, dt: Duration) {
+        self.delta_seconds = dt.as_secs_f32().min(MAX_DELTA_SECONDS);
+        self.elapsed_seconds += self.delta_seconds;
+        self.frame_count += 1;
+    }
+
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.delta_seconds)
+    }
+}
+
+/// World resource that `Scheduler` uses to run its fixed systems (see `Scheduler::add_fixed_system`)
+/// in steps of exactly `step_seconds`, however long the frames are. Insert one to change the step
+/// or the catch-up cap; without one, the defaults are used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    pub step_seconds: f32,
+    pub max_steps: u32,
+    // Time that's passed but not been stepped through yet. Always less than a step after
+    // `accumulate`.
+    accumulator: f64,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_FIXED_STEP_SECONDS)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(step_seconds: f32) -> Self {
+        Self {
+            step_seconds,
+            max_steps: DEFAULT_MAX_FIXED_STEPS,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds `dt` seconds, and takes back out as many whole steps as fit (up to `max_steps`),
+    /// returning how many that was.
+    pub fn accumulate(&mut self, dt: f32) -> u32 {
+        let step = self.step_seconds as f64;
+        if step <= 0.0 {
+            return 0;
+        }
+
+        self.accumulator += dt.max(0.0) as f64;
+
+        let mut steps = 0;
+        while self.accumulator + FIXED_STEP_TOLERANCE >= step && steps < self.max_steps {
+            self.accumulator = (self.accumulator - step).max(0.0);
+            steps += 1;
+        }
+
+        if steps == self.max_steps {
+            self.accumulator %= step;
+        }
+
+        steps
+    }
+
+    /// What's left over after the last `accumulate`, in seconds.
+    pub fn remainder(&self) -> f32 {
+        self.accumulator as f32
+    }
+
+    /// How far between the last two fixed steps the current frame is, from 0 to 1, for
+    /// interpolating whatever they move when drawing it.
+    pub fn alpha(&self) -> f32 {
+        if self.step_seconds <= 0.0 {
+            return 0.0;
+        }
+
+        (self.accumulator / self.step_seconds as f64).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_updates_accumulate_elapsed_time() {
+        let mut time = Time::new();
+
+        time.advance(Duration::from_millis(16));
+        time.advance(Duration::from_millis(34));
+
+        assert!((time.elapsed_seconds - 0.05).abs() < 1e-6, "{}", time.elapsed_seconds);
+        assert!((time.delta_seconds - 0.034).abs() < 1e-6, "{}", time.delta_seconds);
+        assert_eq!(time.frame_count, 2);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut time = Time::new();
+        time.advance(Duration::from_secs(3));
+
+        assert_eq!(time.delta_seconds, MAX_DELTA_SECONDS);
+    }
+
+    #[test]
+    fn fifty_milliseconds_is_three_sixtieth_steps() {
+        let mut timestep = FixedTimestep::new(1.0 / 60.0);
+        assert_eq!(timestep.accumulate(0.05), 3);
+        assert!(timestep.remainder() < 1e-6, "{}", timestep.remainder());
+
+        // Anything short of a fourth step is carried over to the next frame.
+        let mut timestep = FixedTimestep::new(1.0 / 60.0);
+        assert_eq!(timestep.accumulate(0.06), 3);
+        assert!((timestep.remainder() - 0.01).abs() < 1e-6, "{}", timestep.remainder());
+    }
+}
@@ -0,0 +1,430 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use log::warn;
+
+use crate::ecs::bundle::Bundle;
+use crate::ecs::component::Component;
+use crate::ecs::component::name::NameComponent;
+use crate::error::WafError;
+
+/// Handle to an entity owned by a `World`. Slots get reused after a despawn, but every reuse
+/// bumps the slot's generation, so a stale handle never aliases whatever got spawned into the
+/// slot after it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+/// Structural changes recorded by a `World`, so systems that keep their own view of it (a
+/// spatial index, say) don't have to rescan everything each tick.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WorldEvent {
+    EntitySpawned(EntityId),
+    EntityDespawned(EntityId),
+    ComponentInserted(EntityId, TypeId),
+    ComponentRemoved(EntityId, TypeId),
+}
+
+pub struct World {
+    entities: HashMap<EntityId, HashMap<TypeId, Box<dyn Component>>>,
+    generations: Vec<u32>,
+    free_indices: Vec<u32>,
+    // Singletons that don't belong to any one entity (the active camera, clear color, ...).
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    events: Vec<WorldEvent>,
+    // Components handed out by `get_mut` (or inserted) since the last `clear_change_flags`.
+    changed: HashSet<(EntityId, TypeId)>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            entities: HashMap::new(),
+            generations: vec![],
+            free_indices: vec![],
+            resources: HashMap::new(),
+            events: vec![],
+            changed: HashSet::new(),
+        }
+    }
+
+    pub fn spawn(&mut self) -> EntityId {
+        let entity = match self.free_indices.pop() {
+            Some(index) => EntityId { index, generation: self.generations[index as usize] },
+            None => {
+                self.generations.push(0);
+                EntityId { index: self.generations.len() as u32 - 1, generation: 0 }
+            }
+        };
+
+        self.entities.insert(entity, HashMap::new());
+        self.events.push(WorldEvent::EntitySpawned(entity));
+
+        entity
+    }
+
+    pub fn spawn_bundle<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        let entity = self.spawn();
+        bundle.insert_into(self, entity);
+
+        entity
+    }
+
+    pub fn despawn(&mut self, entity: EntityId) {
+        let components = match self.entities.remove(&entity) {
+            Some(components) => components,
+            None => return,
+        };
+
+        // Whoever cares about a component type won't necessarily be listening for despawns.
+        self.events.extend(components.keys().map(|type_id| WorldEvent::ComponentRemoved(entity, *type_id)));
+        self.events.push(WorldEvent::EntityDespawned(entity));
+
+        for type_id in components.keys() {
+            self.changed.remove(&(entity, *type_id));
+        }
+
+        // Bump the generation so every outstanding handle to this slot goes stale.
+        let generation = &mut self.generations[entity.index as usize];
+        *generation = generation.wrapping_add(1);
+        self.free_indices.push(entity.index);
+    }
+
+    /// Despawns every entity, as if one by one (events and all), dropping their components and
+    /// whatever GPU buffers they own. Resources are kept. Generations carry on from where they
+    /// were, so handles from before the clear stay stale after it.
+    pub fn clear(&mut self) {
+        let entities = self.entities().collect::<Vec<_>>();
+
+        for entity in entities {
+            self.despawn(entity);
+        }
+    }
+
+    /// How many entities are alive.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Every live entity, in no particular order.
+    pub fn entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities.keys().copied()
+    }
+
+    pub fn is_alive(&self, entity: EntityId) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+            && self.entities.contains_key(&entity)
+    }
+
+    /// The `type_name` of every component on `entity`, sorted so dumps come out the same every
+    /// time. Empty if the handle is stale.
+    pub fn component_names(&self, entity: EntityId) -> Vec<&'static str> {
+        let mut names = match self.entities.get(&entity).filter(|_| self.is_alive(entity)) {
+            Some(components) => components.values().map(|component| component.type_name()).collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the freshly inserted component, or `None` (leaving the world untouched) if the
+    /// handle is stale. The component starts out changed (see `is_changed`).
+    pub fn insert<C: Component + 'static>(&mut self, entity: EntityId, component: C) -> Option<&mut C> {
+        if !self.is_alive(entity) {
+            warn!("Tried to insert a component into dead entity {:?}. Ignoring!", entity);
+            return None;
+        }
+
+        let components = self.entities.get_mut(&entity)?;
+        components.insert(TypeId::of::<C>(), Box::new(component));
+        self.events.push(WorldEvent::ComponentInserted(entity, TypeId::of::<C>()));
+        self.changed.insert((entity, TypeId::of::<C>()));
+
+        components.get_mut(&TypeId::of::<C>())?
+            .as_any_mut()
+            .downcast_mut::<C>()
+    }
+
+    pub fn get<C: Component + 'static>(&self, entity: EntityId) -> Option<&C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        self.entities.get(&entity)?
+            .get(&TypeId::of::<C>())?
+            .as_any()
+            .downcast_ref::<C>()
+    }
+
+    /// Marks the component changed (see `is_changed`), whether or not it's actually written to.
+    pub fn get_mut<C: Component + 'static>(&mut self, entity: EntityId) -> Option<&mut C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        let component = self.entities.get_mut(&entity)?
+            .get_mut(&TypeId::of::<C>())?
+            .as_any_mut()
+            .downcast_mut::<C>()?;
+
+        self.changed.insert((entity, TypeId::of::<C>()));
+        Some(component)
+    }
+
+    /// Whether `entity`'s `C` has been inserted or borrowed with `get_mut` since the last
+    /// `clear_change_flags`, so systems like the instance uploader can skip everything that
+    /// hasn't. `query_mut` doesn't count, as it hands out every component whether it'll be
+    /// touched or not.
+    pub fn is_changed<C: Component + 'static>(&self, entity: EntityId) -> bool {
+        self.changed.contains(&(entity, TypeId::of::<C>()))
+    }
+
+    /// Forgets every change so far. `Scheduler::update` calls this once all its systems have run,
+    /// so each frame only sees its own changes.
+    pub fn clear_change_flags(&mut self) {
+        self.changed.clear();
+    }
+
+    /// Like `get`, but says which component was missing.
+    pub fn try_get<C: Component + 'static>(&self, entity: EntityId) -> Result<&C, WafError> {
+        self.get::<C>(entity).ok_or_else(|| WafError::MissingComponent(TypeId::of::<C>()))
+    }
+
+    pub fn query<C: Component + 'static>(&self) -> impl Iterator<Item = (EntityId, &C)> {
+        self.entities.iter().filter_map(|(entity, components)| {
+            components.get(&TypeId::of::<C>())?
+                .as_any()
+                .downcast_ref::<C>()
+                .map(|component| (*entity, component))
+        })
+    }
+
+    /// The entities `query` would visit, without the components. Being collected up front, the
+    /// list doesn't keep the world borrowed, so it can drive despawns or inserts on the way
+    /// through (which a `query` in progress won't allow).
+    pub fn entities_with<C: Component + 'static>(&self) -> Vec<EntityId> {
+        self.entities.iter()
+            .filter(|(_, components)| components.contains_key(&TypeId::of::<C>()))
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+
+    pub fn query_mut<C: Component + 'static>(&mut self) -> impl Iterator<Item = (EntityId, &mut C)> {
+        self.entities.iter_mut().filter_map(|(entity, components)| {
+            components.get_mut(&TypeId::of::<C>())?
+                .as_any_mut()
+                .downcast_mut::<C>()
+                .map(|component| (*entity, component))
+        })
+    }
+
+    /// Entities holding both an `A` and a `B`. Components are stored per entity, so this is a
+    /// single pass over every entity, no matter how rare either component is.
+    pub fn query2<A: Component + 'static, B: Component + 'static>(&self) -> impl Iterator<Item = (EntityId, &A, &B)> {
+        self.entities.iter().filter_map(|(entity, components)| {
+            let a = components.get(&TypeId::of::<A>())?.as_any().downcast_ref::<A>()?;
+            let b = components.get(&TypeId::of::<B>())?.as_any().downcast_ref::<B>()?;
+
+            Some((*entity, a, b))
+        })
+    }
+
+    /// Some entity named `name`. If several share it, which one you get is unspecified.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.query::<NameComponent>()
+            .find(|(_, component)| component.0 == name)
+            .map(|(entity, _)| entity)
+    }
+
+    /// `find_by_name` for when the entity has to be there, e.g. during scene setup. Panics if it
+    /// isn't, and (in debug builds) if the name is ambiguous.
+    pub fn named(&self, name: &str) -> EntityId {
+        debug_assert!(self.query::<NameComponent>().filter(|(_, component)| component.0 == name).count() <= 1,
+            "More than one entity is named {:?}", name);
+
+        self.find_by_name(name)
+            .unwrap_or_else(|| panic!("No entity is named {:?}", name))
+    }
+
+    /// There's only ever one resource of a given type; inserting another replaces it.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.resources.insert(TypeId::of::<R>(), Box::new(resource));
+    }
+
+    pub fn resource<R: 'static>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+    }
+
+    pub fn resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut::<R>()
+    }
+
+    /// Hands over everything that happened since the last drain, oldest first.
+    pub fn drain_events(&mut self) -> Vec<WorldEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::clear_color::ClearColor;
+    use crate::ecs::component::transform::TransformComponent;
+    use crate::ecs::component::parent::ParentComponent;
+    use crate::ecs::system::Scheduler;
+
+    #[test]
+    fn get_only_finds_components_on_their_own_entity() {
+        let mut world = World::new();
+
+        let with = world.spawn();
+        let without = world.spawn();
+        world.insert(with, NameComponent("with".to_owned()));
+
+        assert!(world.get::<NameComponent>(with).is_some());
+        assert!(world.get::<NameComponent>(without).is_none());
+    }
+
+    #[test]
+    fn query_yields_only_entities_with_the_component() {
+        let mut world = World::new();
+
+        let first = world.spawn();
+        world.spawn();
+        let third = world.spawn();
+        world.insert(first, NameComponent("first".to_owned()));
+        world.insert(third, NameComponent("third".to_owned()));
+
+        let mut found = world.query::<NameComponent>().map(|(entity, _)| entity).collect::<Vec<_>>();
+        found.sort_by_key(|entity| entity.index);
+
+        assert_eq!(found, vec![first, third]);
+    }
+
+    #[test]
+    fn reused_slot_leaves_the_old_handle_dead() {
+        let mut world = World::new();
+
+        let old = world.spawn();
+        world.despawn(old);
+        let new = world.spawn();
+
+        assert_eq!(new.index, old.index);
+        assert_ne!(new.generation, old.generation);
+        assert!(world.is_alive(new));
+        assert!(!world.is_alive(old));
+    }
+
+    #[test]
+    fn clear_color_resource_reads_back() {
+        let mut world = World::new();
+        assert!(world.resource::<ClearColor>().is_none());
+
+        let color = ClearColor(wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 });
+        world.insert_resource(color);
+        assert_eq!(world.resource::<ClearColor>(), Some(&color));
+
+        world.resource_mut::<ClearColor>().unwrap().0.r = 0.5;
+        assert_eq!(world.resource::<ClearColor>().unwrap().0.r, 0.5);
+    }
+
+    #[test]
+    fn spawn_then_despawn_yields_both_events_in_order() {
+        let mut world = World::new();
+
+        let entity = world.spawn();
+        world.despawn(entity);
+
+        assert_eq!(world.drain_events(), vec![WorldEvent::EntitySpawned(entity), WorldEvent::EntityDespawned(entity)]);
+        assert!(world.drain_events().is_empty());
+    }
+
+    #[test]
+    fn query2_yields_only_entities_with_both() {
+        let mut world = World::new();
+
+        let root = world.spawn();
+        let transform_only = world.spawn_bundle((TransformComponent::identity(),));
+        world.spawn_bundle((ParentComponent(root),));
+        let both = world.spawn_bundle((TransformComponent::identity(), ParentComponent(transform_only)));
+
+        let found = world
+            .query2::<TransformComponent, ParentComponent>()
+            .map(|(entity, _, parent)| (entity, parent.0))
+            .collect::<Vec<_>>();
+
+        assert_eq!(found, vec![(both, transform_only)]);
+    }
+
+    #[test]
+    fn named_entities_are_found_by_name() {
+        let mut world = World::new();
+        let player = world.spawn_bundle((NameComponent("player".to_owned()),));
+        let crate_ = world.spawn_bundle((NameComponent("crate".to_owned()),));
+
+        assert_eq!(world.find_by_name("player"), Some(player));
+        assert_eq!(world.named("crate"), crate_);
+        assert_eq!(world.find_by_name("nobody"), None);
+    }
+
+    #[test]
+    fn entities_with_lists_exactly_the_name_holders() {
+        let mut world = World::new();
+
+        let first = world.spawn();
+        let transform_only = world.spawn_bundle((TransformComponent::identity(),));
+        let third = world.spawn();
+        world.insert(first, NameComponent("first".to_owned()));
+        world.insert(third, NameComponent("third".to_owned()));
+
+        let mut found = world.entities_with::<NameComponent>();
+        found.sort_by_key(|entity| entity.index);
+        assert_eq!(found, vec![first, third]);
+        assert!(!found.contains(&transform_only));
+    }
+
+    #[test]
+    fn clear_empties_the_world_and_leaves_old_handles_stale() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.spawn();
+        world.insert(entity, TransformComponent::identity());
+
+        world.clear();
+
+        assert_eq!(world.len(), 0);
+        assert!(!world.is_alive(entity));
+        // The slot is reused, but with a newer generation.
+        let reused = world.spawn();
+        assert!(world.is_alive(reused));
+        assert!(!world.is_alive(entity));
+    }
+
+    #[test]
+    fn get_mut_marks_changed_until_the_frame_ends() {
+        let mut world = World::new();
+        let mut scheduler = Scheduler::new();
+        let entity = world.spawn();
+        world.insert(entity, TransformComponent::identity());
+        scheduler.update(&mut world, 1.0 / 60.0);
+        assert!(!world.is_changed::<TransformComponent>(entity));
+
+        world.get_mut::<TransformComponent>(entity).unwrap();
+        assert!(world.is_changed::<TransformComponent>(entity));
+
+        scheduler.update(&mut world, 1.0 / 60.0);
+        assert!(!world.is_changed::<TransformComponent>(entity));
+    }
+}
@@ -0,0 +1,36 @@
+use std::any::TypeId;
+use std::fmt;
+
+/// What can go wrong in component and buffer operations, for callers that want to handle a
+/// failure instead of just reporting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WafError {
+    /// Fewer bytes than the data being read (or written) needs.
+    BufferTooSmall {
+        expected: usize,
+        actual: usize,
+    },
+    /// The bytes are there, but don't make sense (wrong version, bad image...).
+    DecodeFailed(String),
+    /// The entity doesn't have a component of this type.
+    MissingComponent(TypeId),
+    /// The device lacks these features.
+    UnsupportedFeature(wgpu::Features),
+    /// These render graph nodes depend on each other in a loop.
+    CyclicDependency(Vec<String>),
+}
+
+impl fmt::Display for WafError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WafError::BufferTooSmall { expected, actual } =>
+                write!(f, "Buffer too small: expected at least {} bytes, got {}", expected, actual),
+            WafError::DecodeFailed(reason) => write!(f, "Decoding failed: {}", reason),
+            WafError::MissingComponent(type_id) => write!(f, "Missing component {:?}", type_id),
+            WafError::UnsupportedFeature(features) => write!(f, "Unsupported device features {:?}", features),
+            WafError::CyclicDependency(nodes) => write!(f, "Cyclic dependency between {}", nodes.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for WafError {}
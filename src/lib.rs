@@ -0,0 +1,14 @@
+pub mod event_loop;
+pub mod window;
+pub mod state;
+#[macro_use]
+pub mod shader;
+pub mod vertex;
+pub mod camera;
+pub mod texture;
+pub mod ecs;
+pub mod render;
+pub mod math;
+pub mod error;
+#[cfg(feature = "image-loading")]
+pub mod asset;
@@ -1,21 +1,10 @@
-pub mod event_loop;
-pub mod window;
-pub mod state;
-#[macro_use]
-pub mod shader;
-pub mod vertex;
-pub mod camera;
-pub mod texture;
-pub mod ecs;
-
-use std::time::Instant;
 use log::LevelFilter;
 use winit::{
     event::*,
     event_loop::ControlFlow,
 };
 
-use state::State;
+use sit::{event_loop, window, state::State};
 
 fn main() {
     env_logger::builder()
@@ -33,7 +22,6 @@ async fn run() {
     let window = window::create_window(&event_loop);
 
     let mut state = State::new(&window).await;
-    let mut last_render_time = Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -71,10 +59,7 @@ async fn run() {
             },
 
             Event::RedrawRequested(window_id) if window_id == window.id() => {
-                let now = Instant::now();
-                let dt = now - last_render_time;
-                last_render_time = now;
-                state.update(dt);
+                state.update();
 
                 match state.render() {
                     Ok(_) => {}
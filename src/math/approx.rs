@@ -0,0 +1,66 @@
+// Epsilon comparisons for the cgmath types, and snapping for editor placement.
+
+use cgmath::{InnerSpace, Matrix4, Quaternion, Vector3};
+
+/// A good default for values of roughly unit scale (positions within a few hundred units,
+/// normalized rotations, ...).
+pub const DEFAULT_EPSILON: f32 = 1e-5;
+
+pub fn approx_eq(a: f32, b: f32, epsilon: f32) -> bool {
+    (a - b).abs() <= epsilon
+}
+
+/// Component-wise, so each axis gets the full `epsilon`.
+pub fn approx_eq_vec3(a: Vector3<f32>, b: Vector3<f32>, epsilon: f32) -> bool {
+    approx_eq(a.x, b.x, epsilon) && approx_eq(a.y, b.y, epsilon) && approx_eq(a.z, b.z, epsilon)
+}
+
+/// Whether `a` and `b` are (about) the same rotation. `q` and `-q` rotate the same way, so
+/// they compare equal here even though every component differs.
+pub fn approx_eq_quat(a: Quaternion<f32>, b: Quaternion<f32>, epsilon: f32) -> bool {
+    let (a, b) = (a.normalize(), b.normalize());
+    let b = if a.dot(b) < 0.0 { -b } else { b };
+
+    approx_eq(a.s, b.s, epsilon) && approx_eq_vec3(a.v, b.v, epsilon)
+}
+
+/// Element-wise.
+pub fn approx_eq_mat4(a: Matrix4<f32>, b: Matrix4<f32>, epsilon: f32) -> bool {
+    let (a, b): ([[f32; 4]; 4], [[f32; 4]; 4]) = (a.into(), b.into());
+
+    a.iter().flatten().zip(b.iter().flatten()).all(|(a, b)| approx_eq(*a, *b, epsilon))
+}
+
+/// Rounds each axis of `position` to the nearest multiple of `cell` (halfway rounds away from
+/// zero). A `cell` of zero or less leaves the position alone.
+pub fn snap_to_grid(position: Vector3<f32>, cell: f32) -> Vector3<f32> {
+    if cell <= 0.0 {
+        return position;
+    }
+
+    position.map(|a| (a / cell).round() * cell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3};
+
+    #[test]
+    fn negated_quaternion_is_the_same_rotation() {
+        let q = Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(70.0));
+
+        assert!(approx_eq_quat(q, -q, DEFAULT_EPSILON));
+        assert!(approx_eq_quat(q, q * 2.0, DEFAULT_EPSILON));
+        assert!(!approx_eq_quat(q, Quaternion::from_angle_y(Deg(70.0)), DEFAULT_EPSILON));
+    }
+
+    #[test]
+    fn snapping_rounds_to_the_nearest_cell() {
+        let snapped = snap_to_grid(Vector3::new(1.2, -0.6, 2.74), 0.5);
+        assert!(approx_eq_vec3(snapped, Vector3::new(1.0, -0.5, 2.5), DEFAULT_EPSILON), "{:?}", snapped);
+
+        assert_eq!(snap_to_grid(Vector3::new(0.25, -0.75, 3.0), 0.5), Vector3::new(0.5, -1.0, 3.0));
+        assert_eq!(snap_to_grid(Vector3::new(1.2, 3.4, 5.6), 0.0), Vector3::new(1.2, 3.4, 5.6));
+    }
+}
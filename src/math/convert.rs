@@ -0,0 +1,95 @@
+// Conversions between cgmath types and the plain arrays that go into GPU buffers.
+//
+// cgmath matrices are column-major, and so are these arrays: `array[i]` is column `i`, which is
+// also how GLSL reads a `mat4` out of a uniform or a run of vertex attributes. Going through
+// these (instead of ad-hoc casts) keeps anything from quietly transposing along the way.
+
+use cgmath::{Matrix3, Matrix4, Quaternion, Vector3, Vector4};
+
+#[inline]
+pub fn mat4_to_array(matrix: Matrix4<f32>) -> [[f32; 4]; 4] {
+    matrix.into()
+}
+
+#[inline]
+pub fn array_to_mat4(array: [[f32; 4]; 4]) -> Matrix4<f32> {
+    array.into()
+}
+
+#[inline]
+pub fn mat3_to_array(matrix: Matrix3<f32>) -> [[f32; 3]; 3] {
+    matrix.into()
+}
+
+#[inline]
+pub fn array_to_mat3(array: [[f32; 3]; 3]) -> Matrix3<f32> {
+    array.into()
+}
+
+#[inline]
+pub fn vec3_to_array(vector: Vector3<f32>) -> [f32; 3] {
+    vector.into()
+}
+
+#[inline]
+pub fn array_to_vec3(array: [f32; 3]) -> Vector3<f32> {
+    array.into()
+}
+
+#[inline]
+pub fn vec4_to_array(vector: Vector4<f32>) -> [f32; 4] {
+    vector.into()
+}
+
+#[inline]
+pub fn array_to_vec4(array: [f32; 4]) -> Vector4<f32> {
+    array.into()
+}
+
+/// `[x, y, z, w]`, the order GLSL (and most file formats) expect. Note that cgmath's own
+/// `Quaternion::new` takes `w` first.
+#[inline]
+pub fn quat_to_array(quaternion: Quaternion<f32>) -> [f32; 4] {
+    [quaternion.v.x, quaternion.v.y, quaternion.v.z, quaternion.s]
+}
+
+#[inline]
+pub fn array_to_quat(array: [f32; 4]) -> Quaternion<f32> {
+    Quaternion::new(array[3], array[0], array[1], array[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_arrays_are_column_major() {
+        // `Matrix4::new' takes a column at a time, so this is laid out transposed.
+        let matrix = Matrix4::new(
+            1.0, 5.0, 9.0, 13.0,
+            2.0, 6.0, 10.0, 14.0,
+            3.0, 7.0, 11.0, 15.0,
+            4.0, 8.0, 12.0, 16.0,
+        );
+        let array = mat4_to_array(matrix);
+
+        assert_eq!(array[0], [1.0, 5.0, 9.0, 13.0]);
+        assert_eq!(array[3], [4.0, 8.0, 12.0, 16.0]);
+        assert_eq!(array[1][2], matrix.y.z);
+        assert_eq!(array_to_mat4(array), matrix);
+
+        let translation = mat4_to_array(Matrix4::from_translation(Vector3::new(7.0, 8.0, 9.0)));
+        assert_eq!(translation[3], [7.0, 8.0, 9.0, 1.0]);
+
+        let matrix3 = Matrix3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+        assert_eq!(mat3_to_array(matrix3), [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]);
+    }
+
+    #[test]
+    fn quaternion_arrays_put_w_last() {
+        let quaternion = Quaternion::new(4.0, 1.0, 2.0, 3.0);
+
+        assert_eq!(quat_to_array(quaternion), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(array_to_quat([1.0, 2.0, 3.0, 4.0]), quaternion);
+    }
+}
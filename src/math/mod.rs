@@ -0,0 +1,4 @@
+pub mod convert;
+pub mod approx;
+
+pub use approx::{approx_eq, approx_eq_vec3, approx_eq_quat, approx_eq_mat4, snap_to_grid};
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// Smallest bucket handed out, in bytes. Anything smaller gets rounded up to this.
+pub const MIN_BUCKET_SIZE: wgpu::BufferAddress = 256;
+pub const DEFAULT_MAX_PER_BUCKET: usize = 16;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Requests served by a recycled buffer.
+    pub hits: usize,
+    /// Requests that had to allocate.
+    pub misses: usize,
+    /// Buffers sitting in the pool right now.
+    pub pooled: usize,
+}
+
+#[derive(Default)]
+struct PoolInner {
+    free: HashMap<(wgpu::BufferAddress, wgpu::BufferUsages), Vec<wgpu::Buffer>>,
+    stats: BufferPoolStats,
+    max_per_bucket: usize,
+}
+
+/// Recycles buffers between things that come and go a lot (streamed `InstanceComponent`s, say),
+/// instead of allocating fresh ones every time. Sizes are rounded up to a power of two so one
+/// buffer can serve many nearby sizes.
+///
+/// Clones share the same pool. Buffers come back on their own when their `PooledBuffer` is
+/// dropped.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<Mutex<PoolInner>>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BufferPool {
+    pub fn new() -> Self {
+        Self::with_max_per_bucket(DEFAULT_MAX_PER_BUCKET)
+    }
+
+    /// Keeps at most `max_per_bucket` idle buffers of each size around; any more get freed.
+    pub fn with_max_per_bucket(max_per_bucket: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PoolInner {
+                max_per_bucket,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// What a request for `size` bytes actually gets.
+    pub fn bucket_size(size: wgpu::BufferAddress) -> wgpu::BufferAddress {
+        size.max(MIN_BUCKET_SIZE).next_power_of_two()
+    }
+
+    /// A buffer of at least `size` bytes. Recycled buffers still hold whatever was in them last,
+    /// so write to it before drawing from it.
+    pub fn acquire(&self, device: &wgpu::Device, size: wgpu::BufferAddress, usage: wgpu::BufferUsages, label: &str) -> PooledBuffer {
+        let size = Self::bucket_size(size);
+        let recycled = self.with_inner(|inner| {
+            let buffer = inner.free.get_mut(&(size, usage)).and_then(|buffers| buffers.pop());
+
+            if buffer.is_some() {
+                inner.stats.hits += 1;
+                inner.stats.pooled -= 1;
+            } else {
+                inner.stats.misses += 1;
+            }
+
+            buffer
+        });
+
+        let buffer = recycled.unwrap_or_else(|| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: false,
+        }));
+
+        PooledBuffer {
+            buffer: Some(buffer),
+            size,
+            usage,
+            pool: Some(self.clone()),
+        }
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        self.with_inner(|inner| inner.stats)
+    }
+
+    /// Frees every idle buffer. Buffers that are still out stay valid, and come back as usual.
+    pub fn clear(&self) {
+        self.with_inner(|inner| {
+            inner.free.clear();
+            inner.stats.pooled = 0;
+        });
+    }
+
+    fn release(&self, buffer: wgpu::Buffer, size: wgpu::BufferAddress, usage: wgpu::BufferUsages) {
+        self.with_inner(|inner| {
+            let max_per_bucket = inner.max_per_bucket;
+            let buffers = inner.free.entry((size, usage)).or_default();
+
+            if buffers.len() < max_per_bucket {
+                buffers.push(buffer);
+                inner.stats.pooled += 1;
+            }
+        });
+    }
+
+    fn with_inner<T>(&self, f: impl FnOnce(&mut PoolInner) -> T) -> T {
+        // A panic while holding the lock can't leave the maps half updated, so carry on.
+        let mut inner = self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        f(&mut inner)
+    }
+}
+
+/// A buffer that goes back to its `BufferPool` (if it came from one) when dropped. Derefs to the
+/// `wgpu::Buffer`, so it can be used anywhere one can.
+pub struct PooledBuffer {
+    // Only ever `None` while being handed back in `drop`.
+    buffer: Option<wgpu::Buffer>,
+    size: wgpu::BufferAddress,
+    usage: wgpu::BufferUsages,
+    pool: Option<BufferPool>,
+}
+
+impl PooledBuffer {
+    /// Wraps a buffer that doesn't belong to any pool; dropping it just frees it.
+    pub fn unpooled(buffer: wgpu::Buffer, size: wgpu::BufferAddress, usage: wgpu::BufferUsages) -> Self {
+        Self {
+            buffer: Some(buffer),
+            size,
+            usage,
+            pool: None,
+        }
+    }
+
+    /// The real size, which for pooled buffers can be more than was asked for.
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.size
+    }
+
+    pub fn is_pooled(&self) -> bool {
+        self.pool.is_some()
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &wgpu::Buffer {
+        self.buffer.as_ref().expect("Pooled buffer used after it was dropped!")
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let (Some(pool), Some(buffer)) = (self.pool.take(), self.buffer.take()) {
+            pool.release(buffer, self.size, self.usage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::instance::{InstanceComponentBuilder, InstanceLayout};
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn recreating_a_dropped_component_reuses_its_buffer() {
+        let gpu = test_renderer();
+        let pool = BufferPool::new();
+        let build = || InstanceComponentBuilder::new()
+            .layout(InstanceLayout::Line { count: 10, spacing: 1.0 })
+            .pool(pool.clone())
+            .build(&gpu.device);
+
+        drop(build());
+        assert_eq!(pool.stats(), BufferPoolStats { hits: 0, misses: 1, pooled: 1 });
+
+        let instances = build();
+        assert!(instances.instance_buffer.is_pooled());
+        assert_eq!(pool.stats(), BufferPoolStats { hits: 1, misses: 1, pooled: 0 });
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+use cgmath::{InnerSpace, Vector3, Zero};
+use winit::{
+    event::{ElementState, MouseScrollDelta, VirtualKeyCode},
+    dpi::PhysicalPosition,
+};
+
+use crate::camera::SAFE_FRAC_PI_2;
+use crate::ecs::component::camera::CameraComponent;
+
+pub const DEFAULT_MIN_DISTANCE: f32 = 0.5;
+
+/// Orbits a `CameraComponent`'s eye around its target, arcball style. Mouse motion spins the eye
+/// around the target (on a sphere), and scrolling moves it along the eye-target line.
+#[derive(Debug)]
+pub struct OrbitCameraController {
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    scroll: f32,
+    pub sensitivity: f32,
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+}
+
+impl OrbitCameraController {
+    pub fn new(sensitivity: f32, zoom_speed: f32) -> Self {
+        Self {
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            scroll: 0.0,
+            sensitivity,
+            zoom_speed,
+            min_distance: DEFAULT_MIN_DISTANCE,
+        }
+    }
+
+    pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.rotate_horizontal += mouse_dx as f32;
+        self.rotate_vertical += mouse_dy as f32;
+    }
+
+    pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        self.scroll += match delta {
+            // I'm assuming a line is about 100 pixels
+            MouseScrollDelta::LineDelta(_, scroll) => -scroll * 0.5,
+            MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => -*scroll as f32,
+        };
+    }
+
+    pub fn update_camera(&mut self, camera: &mut CameraComponent) {
+        let offset = camera.eye - camera.target;
+        let distance = offset.magnitude();
+
+        // Work in spherical coordinates around the target, with y up.
+        let (mut yaw, mut pitch) = if distance > 0.0 {
+            (offset.z.atan2(offset.x), (offset.y / distance).clamp(-1.0, 1.0).asin())
+        } else {
+            (0.0, 0.0)
+        };
+
+        yaw += self.rotate_horizontal * self.sensitivity;
+        // Keep the eye off the poles, where the orbit would flip over.
+        pitch = (pitch + self.rotate_vertical * self.sensitivity).clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+        let distance = (distance + self.scroll * self.zoom_speed).max(self.min_distance);
+
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let eye = camera.target + Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw) * distance;
+
+        // Going through spherical coordinates and back moves the eye a hair even with no input,
+        // so only actual input counts as moving it.
+        if self.rotate_horizontal != 0.0 || self.rotate_vertical != 0.0 || self.scroll != 0.0 {
+            camera.eye = eye;
+            camera.dirty = true;
+        }
+
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+        self.scroll = 0.0;
+    }
+}
+
+/// First-person fly movement for a `CameraComponent`. Held keys move the eye and target together
+/// along the camera's own forward/right/up basis, so translating never changes where it looks.
+#[derive(Debug)]
+pub struct FpsCameraController {
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    pub speed: f32,
+}
+
+impl FpsCameraController {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+            speed,
+        }
+    }
+
+    /// W/A/S/D move along the ground plane of the view, Space and left Shift up and down.
+    /// Returns whether the key was one of those.
+    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+        let pressed = state == ElementState::Pressed;
+
+        match key {
+            VirtualKeyCode::W => self.forward = pressed,
+            VirtualKeyCode::S => self.backward = pressed,
+            VirtualKeyCode::A => self.left = pressed,
+            VirtualKeyCode::D => self.right = pressed,
+            VirtualKeyCode::Space => self.up = pressed,
+            VirtualKeyCode::LShift => self.down = pressed,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// The normalized direction the held keys add up to (zero if nothing's held, or everything
+    /// cancels out). Several keys at once don't move any faster than one.
+    pub fn direction(&self, camera: &CameraComponent) -> Vector3<f32> {
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let direction = forward * axis(self.forward, self.backward)
+            + right * axis(self.right, self.left)
+            + up * axis(self.up, self.down);
+
+        if direction.is_zero() {
+            direction
+        } else {
+            direction.normalize()
+        }
+    }
+
+    pub fn update_camera(&mut self, camera: &mut CameraComponent, dt: Duration) {
+        if camera.eye == camera.target {
+            return;
+        }
+
+        let offset = self.direction(camera) * self.speed * dt.as_secs_f32();
+        if offset.is_zero() {
+            return;
+        }
+
+        camera.eye += offset;
+        camera.target += offset;
+        camera.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{EuclideanSpace, Point3};
+    use crate::math::approx::approx_eq_vec3;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn full_turn_of_yaw_returns_the_eye() {
+        let gpu = test_renderer();
+        let start = Point3::new(3.0, 2.0, 4.0);
+        let mut camera = CameraComponent::new(&gpu.device, start, Point3::new(0.0, 0.0, 0.0), 1.0, cgmath::Deg(45.0));
+
+        // One degree per pixel, a quarter turn at a time.
+        let mut controller = OrbitCameraController::new(std::f32::consts::TAU / 360.0, 1.0);
+        for _ in 0..4 {
+            controller.process_mouse(90.0, 0.0);
+            controller.update_camera(&mut camera);
+        }
+
+        assert!(camera.dirty);
+        assert!(approx_eq_vec3(camera.eye.to_vec(), start.to_vec(), 1e-4), "{:?} != {:?}", camera.eye, start);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn holding_w_for_a_second_moves_one_unit_forward() {
+        let gpu = test_renderer();
+        let mut camera = CameraComponent::new(&gpu.device, Point3::new(0.0, 1.0, 5.0), Point3::new(0.0, 1.0, 0.0), 1.0, cgmath::Deg(45.0));
+
+        let mut controller = FpsCameraController::new(1.0);
+        assert!(controller.process_keyboard(VirtualKeyCode::W, ElementState::Pressed));
+        controller.update_camera(&mut camera, Duration::from_secs(1));
+
+        assert!(camera.dirty);
+        assert!(approx_eq_vec3(camera.eye.to_vec(), cgmath::Vector3::new(0.0, 1.0, 4.0), 1e-5), "{:?}", camera.eye);
+        assert!(approx_eq_vec3(camera.target.to_vec(), cgmath::Vector3::new(0.0, 1.0, -1.0), 1e-5), "{:?}", camera.target);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::render::surface::is_srgb;
+
+/// World resource for the color the scene pass clears to, in linear space. Without one the
+/// background is black.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClearColor(pub wgpu::Color);
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        Self(wgpu::Color::BLACK)
+    }
+}
+
+impl ClearColor {
+    /// Takes the usual 0-255 sRGB values (color pickers, CSS, ...) and converts them to the
+    /// linear color an sRGB swapchain expects. Passing them straight through is how you end up
+    /// with washed out backgrounds.
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(wgpu::Color {
+            r: srgb_to_linear(r),
+            g: srgb_to_linear(g),
+            b: srgb_to_linear(b),
+            a: 1.0,
+        })
+    }
+
+    /// What to actually clear a `format` target to. sRGB targets take the linear color (and
+    /// encode it themselves), but anything else stores it as is, so it gets encoded here.
+    pub fn for_format(&self, format: wgpu::TextureFormat) -> wgpu::Color {
+        if is_srgb(format) {
+            self.0
+        } else {
+            wgpu::Color {
+                r: linear_to_srgb(self.0.r),
+                g: linear_to_srgb(self.0.g),
+                b: linear_to_srgb(self.0.b),
+                a: self.0.a,
+            }
+        }
+    }
+}
+
+fn srgb_to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.0;
+
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(channel: f64) -> f64 {
+    let channel = channel.clamp(0.0, 1.0);
+
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_rgb_red_is_linear_red() {
+        assert_eq!(ClearColor::from_rgb(255, 0, 0).0, wgpu::Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 });
+    }
+
+    #[test]
+    fn from_rgb_midtones_get_darker_in_linear() {
+        let ClearColor(color) = ClearColor::from_rgb(128, 128, 128);
+
+        // sRGB 128 is about 21.6% linear, not half.
+        assert!((color.r - 0.2158).abs() < 1e-3, "{}", color.r);
+    }
+
+    #[test]
+    fn for_format_is_linear_for_srgb_and_encoded_otherwise() {
+        let clear = ClearColor::from_rgb(128, 64, 255);
+
+        assert_eq!(clear.for_format(wgpu::TextureFormat::Bgra8UnormSrgb), clear.0);
+
+        let encoded = clear.for_format(wgpu::TextureFormat::Bgra8Unorm);
+        assert!((encoded.r - 128.0 / 255.0).abs() < 1e-6, "{}", encoded.r);
+        assert!((encoded.g - 64.0 / 255.0).abs() < 1e-6, "{}", encoded.g);
+        assert!((encoded.b - 1.0).abs() < 1e-6, "{}", encoded.b);
+        assert_eq!(encoded.a, 1.0);
+    }
+}
@@ -0,0 +1,281 @@
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
+
+use crate::ecs::component::instance::{InstanceComponent, InstanceRaw};
+use crate::math::convert::vec4_to_array;
+use crate::render::frustum::Frustum;
+use crate::render::indirect::DrawIndexedIndirectArgs;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+    planes: [[f32; 4]; 6],
+    count: u32,
+    stride: u32,
+    _padding: [u32; 2],
+}
+
+/// Frustum culls an `InstanceComponent` in a compute shader instead of on the CPU, for scenes
+/// too big for `InstanceComponent::cull`. The survivors get packed into the culler's own instance
+/// buffer and drawn with `draw_indexed_indirect`, so the CPU never even learns how many made it.
+///
+/// Not every device can do this (see `supported`); keep to `InstanceComponent::cull` on those.
+pub struct ComputeCuller {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    sphere_buffer: wgpu::Buffer,
+    source_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    capacity: usize,
+}
+
+impl ComputeCuller {
+    pub fn supported(downlevel: &wgpu::DownlevelCapabilities) -> bool {
+        downlevel.flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+    }
+
+    /// `capacity` is how many instances fit before the buffers need to grow; `cull` takes care
+    /// of that, so it's only a starting point.
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cull"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../res/shaders/cull.wgsl").into()),
+        });
+
+        let storage = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+            ],
+            label: Some("cull_bind_group_layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Cull Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[CullUniform::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let indirect_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Cull Indirect Buffer"),
+                contents: bytemuck::cast_slice(&[DrawIndexedIndirectArgs::zeroed()]),
+                // `COPY_SRC` for reading the survivor count back when debugging.
+                usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            }
+        );
+
+        let capacity = capacity.max(1);
+        let (sphere_buffer, source_buffer, instance_buffer) = Self::create_instance_buffers(device, capacity);
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &uniform_buffer, &sphere_buffer,
+            &source_buffer, &instance_buffer, &indirect_buffer);
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            sphere_buffer,
+            source_buffer,
+            instance_buffer,
+            indirect_buffer,
+            bind_group,
+            capacity,
+        }
+    }
+
+    /// Uploads `instances` and records the culling pass into `encoder`. `index_count` is the
+    /// index count of the mesh that'll be drawn with the result.
+    pub fn cull(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        instances: &InstanceComponent,
+        frustum: &Frustum,
+        index_count: u32,
+    ) {
+        let count = instances.len();
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            (self.sphere_buffer, self.source_buffer, self.instance_buffer) = Self::create_instance_buffers(device, self.capacity);
+            self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.uniform_buffer,
+                &self.sphere_buffer, &self.source_buffer, &self.instance_buffer, &self.indirect_buffer);
+        }
+
+        let spheres = instances.instances.iter()
+            .map(|instance| instances.world_position(instance).extend(instance.scaled_bounding_radius()))
+            .map(vec4_to_array)
+            .collect::<Vec<_>>();
+
+        let uniform = CullUniform {
+            planes: frustum.planes.map(vec4_to_array),
+            count: count as u32,
+            stride: (std::mem::size_of::<InstanceRaw>() / std::mem::size_of::<u32>()) as u32,
+            _padding: [0; 2],
+        };
+        let args = DrawIndexedIndirectArgs {
+            index_count,
+            instance_count: 0,
+            first_index: 0,
+            base_vertex: 0,
+            first_instance: 0,
+        };
+
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        queue.write_buffer(&self.sphere_buffer, 0, bytemuck::cast_slice(&spheres));
+        queue.write_buffer(&self.source_buffer, 0, bytemuck::cast_slice(&instances.raw_instances()));
+        queue.write_buffer(&self.indirect_buffer, 0, bytemuck::cast_slice(&[args]));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cull Pass"),
+        });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups((count as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Draws whatever survived the last `cull`. The mesh's vertex and index buffers need to be
+    /// bound already; this only binds the instances (slot 1).
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.draw_indexed_indirect(&self.indirect_buffer, 0);
+    }
+
+    /// Where the survivors end up, packed at the front.
+    pub fn instance_buffer(&self) -> &wgpu::Buffer {
+        &self.instance_buffer
+    }
+
+    /// A single `DrawIndexedIndirect`, whose instance count is how many survived.
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.indirect_buffer
+    }
+
+    fn create_instance_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer, wgpu::Buffer) {
+        let buffer = |label, size: usize, usage| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * size) as wgpu::BufferAddress,
+            usage,
+            mapped_at_creation: false,
+        });
+
+        (
+            buffer("Cull Sphere Buffer", std::mem::size_of::<[f32; 4]>(),
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST),
+            buffer("Cull Source Buffer", std::mem::size_of::<InstanceRaw>(),
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST),
+            buffer("Culled Instance Buffer", std::mem::size_of::<InstanceRaw>(),
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX),
+        )
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        sphere_buffer: &wgpu::Buffer,
+        source_buffer: &wgpu::Buffer,
+        instance_buffer: &wgpu::Buffer,
+        indirect_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: sphere_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: source_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: instance_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: indirect_buffer.as_entire_binding() },
+            ],
+            label: Some("cull_bind_group"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{perspective, Deg, Matrix4, One, Point3, Quaternion, Vector3};
+    use crate::camera::OPENGL_TO_WGPU_MATRIX;
+    use crate::ecs::component::instance::{Instance, InstanceLayout, SINGLE_INSTANCE_DISPLACEMENT};
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn instance_behind_the_camera_is_dropped() {
+        let gpu = test_renderer();
+        let (device, queue) = (&gpu.device, &gpu.queue);
+
+        // Looking down -Z from (0, 0, 5), so the first is in view and the second behind.
+        let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let frustum = Frustum::from_matrix(OPENGL_TO_WGPU_MATRIX * perspective(Deg(45.0), 1.0, 0.1, 100.0) * view);
+        let instances = InstanceComponent::new(device, InstanceLayout::Custom(vec![
+            Instance::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::one()),
+            Instance::new(Vector3::new(0.0, 0.0, 20.0), Quaternion::one()),
+        ]), SINGLE_INSTANCE_DISPLACEMENT);
+
+        let mut culler = ComputeCuller::new(device, 2);
+        let size = std::mem::size_of::<DrawIndexedIndirectArgs>() as wgpu::BufferAddress;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cull Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Cull Test Encoder") });
+        culler.cull(device, queue, &mut encoder, &instances, &frustum, 6);
+        encoder.copy_buffer_to_buffer(culler.indirect_buffer(), 0, &readback, 0, size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+        let args: DrawIndexedIndirectArgs = bytemuck::pod_read_unaligned(&slice.get_mapped_range());
+
+        assert_eq!(args.instance_count, 1);
+        assert_eq!(args.index_count, 6);
+    }
+}
@@ -0,0 +1,198 @@
+use cgmath::prelude::*;
+
+use crate::vertex::{LineVertex, Vertex};
+use crate::ecs::{world::World, system::System};
+use crate::ecs::component::camera::CameraComponent;
+use crate::ecs::component::instance::InstanceComponent;
+use crate::render::depth::DepthTexture;
+
+/// How long each axis is drawn, in the instance's local units (so scaling an instance scales
+/// its axes too).
+pub const DEBUG_AXIS_LENGTH: f32 = 1.0;
+
+const AXES: [([f32; 3], [f32; 3]); 3] = [
+    ([1.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, 1.0], [0.0, 0.0, 1.0]),
+];
+
+/// World resource that switches `DebugLines` on and off. Without one nothing is drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugDraw(pub bool);
+
+/// Draws the local X, Y and Z axes (red, green and blue) of every instance in the world.
+///
+/// Works like `RenderSystem`: `run` builds the lines, `prepare` uploads them and
+/// `DebugLinePipeline::draw` draws them. The vertex buffer is kept across frames and only
+/// reallocated once the lines outgrow it.
+pub struct DebugLines {
+    vertices: Vec<LineVertex>,
+    vertex_buffer: Option<wgpu::Buffer>,
+    capacity: usize,
+}
+
+impl Default for DebugLines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for DebugLines {
+    fn run(&mut self, world: &mut World, _dt: f32) {
+        self.vertices.clear();
+
+        if !world.resource::<DebugDraw>().is_some_and(|draw| draw.0) {
+            return;
+        }
+
+        for (_, instances) in world.query::<InstanceComponent>() {
+            for instance in &instances.instances {
+                let model = instance.model_matrix_in(instances.transform);
+                let origin = model.transform_point(cgmath::Point3::origin());
+
+                for (axis, color) in AXES {
+                    let end = model.transform_point(cgmath::Point3::from(axis.map(|a| a * DEBUG_AXIS_LENGTH)));
+
+                    self.vertices.push(LineVertex { position: origin.into(), color });
+                    self.vertices.push(LineVertex { position: end.into(), color });
+                }
+            }
+        }
+    }
+}
+
+impl DebugLines {
+    pub fn new() -> Self {
+        Self {
+            vertices: vec![],
+            vertex_buffer: None,
+            capacity: 0,
+        }
+    }
+
+    /// Two per segment, so six per instance.
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn vertices(&self) -> &[LineVertex] {
+        &self.vertices
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        if self.vertex_buffer.is_none() || self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Debug Line Vertex Buffer"),
+                size: (self.capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+
+        if let Some(vertex_buffer) = &self.vertex_buffer {
+            queue.write_buffer(vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+    }
+}
+
+/// Unlit `LineList` pipeline for `DebugLines`. Bind group 0 is the camera uniform
+/// (`CameraComponent::bind_group_layout`), same as the scene pipeline.
+pub struct DebugLinePipeline {
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugLinePipeline {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/debug_line_vertex.spv", "debug_line_vertex");
+        let fragment_shader = create_spv_shader!(device, "../../target/debug_line_fragment.spv", "debug_line_fragment");
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Line Pipeline Layout"),
+            bind_group_layouts: &[
+                &CameraComponent::bind_group_layout(device),
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Line Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[LineVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthTexture::read_only_depth_stencil_state()),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+        }
+    }
+
+    /// Needs to go after the geometry the lines should be hidden behind.
+    pub fn draw<'a>(&'a self, lines: &'a DebugLines, camera_bind_group: &'a wgpu::BindGroup, render_pass: &mut wgpu::RenderPass<'a>) {
+        let vertex_buffer = match &lines.vertex_buffer {
+            Some(vertex_buffer) if !lines.vertices.is_empty() => vertex_buffer,
+            _ => return,
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..lines.vertices.len() as u32, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::instance::{InstanceLayout, SINGLE_INSTANCE_DISPLACEMENT};
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn each_instance_gets_six_vertices() {
+        let gpu = test_renderer();
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, InstanceComponent::new(&gpu.device, InstanceLayout::Line { count: 7, spacing: 1.0 }, SINGLE_INSTANCE_DISPLACEMENT));
+
+        let mut lines = DebugLines::new();
+        lines.run(&mut world, 0.0);
+        assert_eq!(lines.vertex_count(), 0);
+
+        world.insert_resource(DebugDraw(true));
+        lines.run(&mut world, 0.0);
+        assert_eq!(lines.vertex_count(), 6 * 7);
+    }
+}
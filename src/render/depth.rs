@@ -0,0 +1,75 @@
+use crate::texture::Texture;
+
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    sample_count: u32,
+}
+
+impl DepthTexture {
+    pub const FORMAT: wgpu::TextureFormat = Texture::DEPTH_FORMAT;
+
+    pub fn create(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        Self::create_multisampled(device, config, 1)
+    }
+
+    // Must match the sample count of the color target it's paired with.
+    pub fn create_multisampled(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Self {
+        let Texture { texture, view, sampler } =
+            Texture::create_multisampled_depth_texture(device, config, sample_count, "depth_texture");
+
+        Self { texture, view, sampler, sample_count }
+    }
+
+    // The depth texture has to match the surface exactly, so it's recreated on every resize.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        *self = Self::create_multisampled(device, config, self.sample_count);
+    }
+
+    pub fn depth_stencil_state() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: Self::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+
+    /// Same test as `depth_stencil_state', but leaves the depth buffer alone.
+    pub fn read_only_depth_stencil_state() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            depth_write_enabled: false,
+            ..Self::depth_stencil_state()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::error_scope::with_error_scope;
+    use crate::render::headless::{test_config, test_renderer};
+
+    // Views have to share the texture's format, so viewing it as `format` only passes validation
+    // if that's what it was created with.
+    fn has_format(device: &wgpu::Device, depth: &DepthTexture, format: wgpu::TextureFormat) -> bool {
+        let (_, error) = with_error_scope(device, "Depth view", || depth.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(format),
+            ..Default::default()
+        }));
+
+        error.is_none()
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn create_uses_the_depth_format() {
+        let gpu = test_renderer();
+        let depth = DepthTexture::create(&gpu.device, &test_config(4, 4));
+
+        assert!(has_format(&gpu.device, &depth, DepthTexture::FORMAT));
+        assert!(!has_format(&gpu.device, &depth, wgpu::TextureFormat::Rgba8Unorm));
+    }
+}
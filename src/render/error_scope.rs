@@ -0,0 +1,49 @@
+use log::warn;
+
+/// Runs `f` inside a validation error scope, so a mistake in it gets logged against `context`
+/// (e.g. "Instance Buffer creation") instead of landing in the device's global error handler,
+/// which by default just panics with no idea where it came from. The error, if any, is handed
+/// back as well.
+pub fn with_error_scope<T>(device: &wgpu::Device, context: &str, f: impl FnOnce() -> T) -> (T, Option<wgpu::Error>) {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = f();
+
+    (result, pop_error_scope(device, context))
+}
+
+/// The other half of a `device.push_error_scope(wgpu::ErrorFilter::Validation)`, for when the
+/// work doesn't fit in a closure. Logs the error against `context`, same as `with_error_scope`.
+pub fn pop_error_scope(device: &wgpu::Device, context: &str) -> Option<wgpu::Error> {
+    let error = pollster::block_on(device.pop_error_scope());
+
+    if let Some(error) = &error {
+        warn!("{} failed validation: {}", context, error);
+    }
+
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn mis_sized_buffer_is_captured() {
+        let gpu = test_renderer();
+
+        // Buffers mapped at creation have to be a multiple of 4 bytes.
+        let (_, error) = with_error_scope(&gpu.device, "Mis-sized Buffer creation", || {
+            gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Mis-sized Buffer"),
+                size: 3,
+                usage: wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: true,
+            })
+        });
+
+        let error = error.expect("a mis-sized buffer should fail validation");
+        assert!(!error.to_string().is_empty());
+    }
+}
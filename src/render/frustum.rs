@@ -0,0 +1,64 @@
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+/// The six planes of a view frustum, each stored as `(normal, distance)` in a `Vector4`, with the
+/// normals pointing inwards.
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix (Gribb/Hartmann). This assumes wgpu's
+    /// 0..1 clip-space depth, i.e. a matrix that already went through `OPENGL_TO_WGPU_MATRIX`.
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Self {
+        let row = |i: usize| Vector4::new(view_proj.x[i], view_proj.y[i], view_proj.z[i], view_proj.w[i]);
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let planes = [
+            r3 + r0, // Left
+            r3 - r0, // Right
+            r3 + r1, // Bottom
+            r3 - r1, // Top
+            r2,      // Near
+            r3 - r2, // Far
+        ].map(|plane| plane / plane.truncate().magnitude());
+
+        Self { planes }
+    }
+
+    pub fn contains_point(&self, point: Vector3<f32>) -> bool {
+        self.contains_sphere(point, 0.0)
+    }
+
+    pub fn contains_sphere(&self, center: Vector3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{perspective, Deg, Point3};
+    use crate::camera::OPENGL_TO_WGPU_MATRIX;
+
+    /// Looking down -Z from (0, 0, 5) at the origin.
+    fn frustum() -> Frustum {
+        let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        Frustum::from_matrix(OPENGL_TO_WGPU_MATRIX * perspective(Deg(45.0), 1.0, 0.1, 100.0) * view)
+    }
+
+    #[test]
+    fn point_in_front_survives() {
+        assert!(frustum().contains_point(Vector3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn point_behind_is_culled() {
+        assert!(!frustum().contains_point(Vector3::new(0.0, 0.0, 10.0)));
+    }
+
+    #[test]
+    fn large_radius_behind_the_camera_still_reaches_in() {
+        assert!(!frustum().contains_sphere(Vector3::new(0.0, 0.0, 6.0), 0.5));
+        assert!(frustum().contains_sphere(Vector3::new(0.0, 0.0, 6.0), 2.0));
+    }
+}
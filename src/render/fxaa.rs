@@ -0,0 +1,244 @@
+use crate::state::MSAA_SAMPLE_COUNT;
+
+/// World resource picking how edges get smoothed. `State` rebuilds its render targets and
+/// pipelines whenever it changes; without one it sticks to `Msaa(MSAA_SAMPLE_COUNT)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    /// With this many samples per pixel (knocked down to what the adapter can do).
+    Msaa(u32),
+    /// A post pass (see `FxaaPass`), much cheaper than MSAA though blurrier.
+    Fxaa,
+}
+
+impl Default for AntiAliasing {
+    fn default() -> Self {
+        AntiAliasing::Msaa(MSAA_SAMPLE_COUNT)
+    }
+}
+
+impl AntiAliasing {
+    /// How many samples the scene should be drawn with, before knocking it down to what's
+    /// supported.
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            AntiAliasing::Msaa(sample_count) => *sample_count,
+            _ => 1,
+        }
+    }
+
+    /// Whether the scene needs drawing offscreen and an `FxaaPass` running over it.
+    pub fn uses_post_pass(&self) -> bool {
+        *self == AntiAliasing::Fxaa
+    }
+}
+
+/// FXAA over the finished frame. The scene gets drawn into `input_view` instead of the swapchain
+/// (single sampled, as FXAA needs to read plain texels), and `draw` then writes the smoothed
+/// result out to the swapchain with one fullscreen triangle.
+pub struct FxaaPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    input: (wgpu::Texture, wgpu::TextureView),
+    bind_group: wgpu::BindGroup,
+}
+
+impl FxaaPass {
+    /// `format` is the swapchain's, which the input texture shares so the scene pipelines don't
+    /// need to change.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/fxaa_vertex.spv", "fxaa_vertex");
+        let fragment_shader = create_spv_shader!(device, "../../target/fxaa_fragment.spv", "fxaa_fragment");
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("FXAA Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FXAA Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("FXAA Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Linear filtering is what lets the shader blend between texels along an edge.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("FXAA Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let input = Self::create_input(device, format, width, height);
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &input.1, &sampler);
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            format,
+            input,
+            bind_group,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.input = Self::create_input(device, self.format, width, height);
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.input.1, &self.sampler);
+    }
+
+    /// Where the scene should be drawn (or resolved to) instead of the swapchain.
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        &self.input.1
+    }
+
+    /// Smooths the input into `view` (the swapchain, usually), overwriting whatever is there.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("FXAA Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Every pixel gets written, so there's nothing worth loading.
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_input(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("FXAA Input Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("FXAA Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::error_scope::with_error_scope;
+    use crate::render::headless::{test_renderer, HeadlessRenderer};
+
+    #[test]
+    fn only_fxaa_needs_the_post_pass() {
+        assert!(AntiAliasing::Fxaa.uses_post_pass());
+        assert!(!AntiAliasing::None.uses_post_pass());
+        assert!(!AntiAliasing::Msaa(4).uses_post_pass());
+
+        assert_eq!(AntiAliasing::Fxaa.sample_count(), 1);
+        assert_eq!(AntiAliasing::None.sample_count(), 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn fxaa_post_pipeline_builds_and_draws() {
+        let gpu = test_renderer();
+        let (width, height) = gpu.size();
+
+        let (_, error) = with_error_scope(&gpu.device, "FXAA pass", || {
+            let fxaa = FxaaPass::new(&gpu.device, HeadlessRenderer::FORMAT, width, height);
+
+            let mut encoder = gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("FXAA Test Encoder") });
+            fxaa.draw(&mut encoder, &gpu.view);
+            gpu.queue.submit(std::iter::once(encoder.finish()));
+        });
+
+        assert!(error.is_none(), "{:?}", error);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::error::WafError;
+
+/// Something a render graph node reads or writes, e.g. `"depth"` or `"scene_color"`. Only used
+/// to work out the order: the graph never touches the textures themselves.
+pub type ResourceName = &'static str;
+
+/// One pass (or a few) worth of commands. Closures taking the encoder are nodes too.
+pub trait RenderNode {
+    fn run(&mut self, encoder: &mut wgpu::CommandEncoder);
+}
+
+impl<F: FnMut(&mut wgpu::CommandEncoder)> RenderNode for F {
+    fn run(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self(encoder)
+    }
+}
+
+struct Node {
+    name: String,
+    reads: Vec<ResourceName>,
+    writes: Vec<ResourceName>,
+    node: Box<dyn RenderNode>,
+}
+
+/// Orders passes by what they read and write instead of by where they happen to be called. A
+/// node reading a resource runs after whichever node added before it wrote it last (or, if none
+/// did, after the first one added later), and writes happen in the order they were added, each
+/// after the reads of the previous version. Anything left unconstrained keeps the order it was
+/// added in, so slotting in an optional pass doesn't shuffle the others.
+///
+/// wgpu already tracks resource usage and inserts the barriers between passes itself, so the
+/// order is all the graph has to get right.
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<Node>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![],
+        }
+    }
+
+    pub fn add_node<N: RenderNode + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        reads: &[ResourceName],
+        writes: &[ResourceName],
+        node: N,
+    ) {
+        self.nodes.push(Node {
+            name: name.into(),
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            node: Box::new(node),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The node names in the order `execute` would run them.
+    pub fn order(&self) -> Result<Vec<&str>, WafError> {
+        Ok(self.sorted()?.into_iter().map(|index| self.nodes[index].name.as_str()).collect())
+    }
+
+    /// Records every node into `encoder`, dependencies first. Nothing runs if there's a cycle.
+    pub fn execute(&mut self, encoder: &mut wgpu::CommandEncoder) -> Result<(), WafError> {
+        for index in self.sorted()? {
+            self.nodes[index].node.run(encoder);
+        }
+
+        Ok(())
+    }
+
+    /// Kahn's algorithm, always picking the earliest added node that's ready.
+    fn sorted(&self) -> Result<Vec<usize>, WafError> {
+        let mut dependents = vec![vec![]; self.nodes.len()];
+        let mut blockers = vec![0; self.nodes.len()];
+        let mut depend = |before: usize, after: usize| {
+            if before != after && !dependents[before].contains(&after) {
+                dependents[before].push(after);
+                blockers[after] += 1;
+            }
+        };
+
+        // Walking the nodes in the order they were added, per resource: who wrote it last, and
+        // who has read that version since (they need to finish before the next write).
+        let mut last_writer: HashMap<ResourceName, usize> = HashMap::new();
+        let mut readers: HashMap<ResourceName, Vec<usize>> = HashMap::new();
+        // Reads of something nothing has written yet, which must be waiting on a later node.
+        let mut early_readers: HashMap<ResourceName, Vec<usize>> = HashMap::new();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            for &resource in &node.reads {
+                match last_writer.get(resource) {
+                    Some(&writer) => {
+                        depend(writer, index);
+                        readers.entry(resource).or_default().push(index);
+                    },
+                    None => early_readers.entry(resource).or_default().push(index),
+                }
+            }
+
+            for &resource in &node.writes {
+                match last_writer.get(resource) {
+                    Some(&writer) => depend(writer, index),
+                    None => {
+                        for reader in early_readers.remove(resource).unwrap_or_default() {
+                            depend(index, reader);
+                        }
+                    },
+                }
+
+                for reader in readers.remove(resource).unwrap_or_default() {
+                    depend(reader, index);
+                }
+
+                last_writer.insert(resource, index);
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut done = vec![false; self.nodes.len()];
+
+        while let Some(next) = (0..self.nodes.len()).find(|&index| !done[index] && blockers[index] == 0) {
+            done[next] = true;
+            order.push(next);
+
+            for &dependent in &dependents[next] {
+                blockers[dependent] -= 1;
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let stuck = (0..self.nodes.len())
+                .filter(|&index| !done[index])
+                .map(|index| self.nodes[index].name.clone())
+                .collect();
+
+            return Err(WafError::CyclicDependency(stuck));
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::render::headless::test_renderer;
+
+    /// C reads what B writes, and B what A writes, but they're added backwards.
+    fn chain(ran: &Rc<RefCell<Vec<&'static str>>>) -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        for (name, reads, writes) in [("C", &["b"][..], &[][..]), ("B", &["a"], &["b"]), ("A", &[], &["a"])] {
+            let ran = Rc::clone(ran);
+            graph.add_node(name, reads, writes, move |_: &mut wgpu::CommandEncoder| ran.borrow_mut().push(name));
+        }
+
+        graph
+    }
+
+    #[test]
+    fn chain_is_ordered_by_its_dependencies() {
+        let graph = chain(&Rc::default());
+
+        assert_eq!(graph.order().unwrap(), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn chain_executes_in_dependency_order() {
+        let gpu = test_renderer();
+        let ran = Rc::default();
+        let mut graph = chain(&ran);
+
+        let mut encoder = gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Graph Test Encoder") });
+        graph.execute(&mut encoder).unwrap();
+
+        assert_eq!(*ran.borrow(), vec!["A", "B", "C"]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::any::Any;
+
+use crate::vertex::PureVertex;
+use crate::ecs::component::Component;
+use crate::ecs::component::mesh::MeshComponent;
+use crate::ecs::component::instance::{Instance, InstanceComponent, InstanceComponentBuilder};
+
+pub const GRID_LINE_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+/// The lines through the origin, along X and Z, to match `DebugLines`.
+pub const GRID_X_AXIS_COLOR: [f32; 3] = [1.0, 0.0, 0.0];
+pub const GRID_Z_AXIS_COLOR: [f32; 3] = [0.0, 0.0, 1.0];
+
+/// World resource that switches every `GridFloor` on and off. Without one, grids are hidden.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShowGrid(pub bool);
+
+/// Marks the entity holding a `GridFloor::new` mesh and its instances, so `RenderSystem` draws it
+/// (as lines, with the scene pipeline) only while `ShowGrid` is on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridFloor;
+
+impl Component for GridFloor {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        "GridFloor"
+    }
+}
+
+impl GridFloor {
+    /// A `LineList` grid on the XZ plane, centered on the origin, `size` cells across (rounded up
+    /// to even, so a line always runs through the origin) and `spacing` apart. It comes with a
+    /// single instance at the origin, so it can be moved, instanced or hidden like any mesh.
+    // Not `Self`, as the grid is just a mesh; `GridFloor` only marks which entity it's on.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(device: &wgpu::Device, size: u32, spacing: f32) -> (MeshComponent, InstanceComponent) {
+        let (vertices, indices) = grid_geometry(size, spacing);
+        let mesh = MeshComponent::new("GRID_FLOOR".to_owned(), device, vertices, indices, 0, 0)
+            .with_primitive(wgpu::PrimitiveTopology::LineList, None);
+
+        let instances = InstanceComponentBuilder::new()
+            .instance(Instance::default())
+            .build(device);
+
+        (mesh, instances)
+    }
+
+    /// How many lines `new` builds for a grid `size` cells across: one more than that each way.
+    pub fn segment_count(size: u32) -> u32 {
+        (size.div_ceil(2) * 2 + 1) * 2
+    }
+}
+
+fn grid_geometry(size: u32, spacing: f32) -> (Vec<PureVertex>, Vec<u32>) {
+    let half = size.div_ceil(2) as i32;
+    let extent = half as f32 * spacing;
+
+    let mut vertices = Vec::with_capacity(GridFloor::segment_count(size) as usize * 2);
+    for line in -half..=half {
+        let offset = line as f32 * spacing;
+        let (x_color, z_color) = match line {
+            0 => (GRID_X_AXIS_COLOR, GRID_Z_AXIS_COLOR),
+            _ => (GRID_LINE_COLOR, GRID_LINE_COLOR),
+        };
+
+        vertices.push(PureVertex { position: [-extent, 0.0, offset], color: x_color });
+        vertices.push(PureVertex { position: [extent, 0.0, offset], color: x_color });
+        vertices.push(PureVertex { position: [offset, 0.0, -extent], color: z_color });
+        vertices.push(PureVertex { position: [offset, 0.0, extent], color: z_color });
+    }
+
+    let indices = (0..vertices.len() as u32).collect();
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    fn ten_by_ten_grid_has_eleven_lines_each_way() {
+        let (vertices, indices) = grid_geometry(10, 1.0);
+
+        assert_eq!(GridFloor::segment_count(10), 22);
+        assert_eq!((vertices.len(), indices.len()), (22 * 2, 22 * 2));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn grid_floor_is_a_line_list_with_one_instance() {
+        let gpu = test_renderer();
+        let (mesh, instances) = GridFloor::new(&gpu.device, 10, 1.0);
+
+        assert_eq!(mesh.topology, wgpu::PrimitiveTopology::LineList);
+        assert_eq!(mesh.num_indices, 22 * 2);
+        assert_eq!(instances.instances.len(), 1);
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+
+use crate::state::{GRAPHICS_BACKEND, DEVICE_FEATURES, OPTIONAL_DEVICE_FEATURES};
+
+/// A device rendering into an offscreen texture instead of a window surface, for tests and
+/// screenshots. Frames are read back as tightly packed RGBA8 rows.
+pub struct HeadlessRenderer {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl HeadlessRenderer {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        pollster::block_on(Self::new_async(width, height))
+    }
+
+    pub async fn new_async(width: u32, height: u32) -> Result<Self> {
+        let instance = wgpu::Instance::new(GRAPHICS_BACKEND);
+
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            },
+        ).await.ok_or_else(|| anyhow!("No graphics adapter available for headless rendering"))?;
+
+        // Only ask for the features the adapter actually has; CI machines are often software
+        // rasterizers without line polygon modes (or multi draw).
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features: (DEVICE_FEATURES | OPTIONAL_DEVICE_FEATURES) & adapter.features(),
+                limits: wgpu::Limits::default(),
+                label: Some("Headless Device"),
+            },
+            None,
+        ).await?;
+
+        let (width, height) = (width.max(1), height.max(1));
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Render Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(Self {
+            device,
+            queue,
+            texture,
+            view,
+            width,
+            height,
+        })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Fills the whole render texture with `color`.
+    pub fn clear(&self, color: wgpu::Color) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless Clear Encoder"),
+        });
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Headless Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Copies the render texture back to the CPU, blocking until the GPU is done with it.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        // Buffer copies need every row padded out to the copy alignment, which we strip again
+        // once it's mapped.
+        let unpadded_bytes_per_row = self.width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Readback Buffer"),
+            size: (padded_bytes_per_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless Readback Encoder"),
+        });
+
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let pixels = slice.get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+
+        buffer.unmap();
+
+        pixels
+    }
+}
+
+/// A small headless renderer for tests that need a device. Plenty of machines (CI included)
+/// don't have an adapter, so those tests are all `#[ignore = "needs a GPU adapter"]`, and only
+/// run with `cargo test -- --ignored` (or `--include-ignored`). Without an adapter this panics.
+#[cfg(test)]
+pub(crate) fn test_renderer() -> HeadlessRenderer {
+    HeadlessRenderer::new(4, 4).unwrap_or_else(|e| panic!("GPU test ran without an adapter: {}", e))
+}
+
+/// A surface configuration matching `HeadlessRenderer::FORMAT`, for building the things that
+/// size themselves off one (depth textures, render targets, ...) in tests.
+#[cfg(test)]
+pub(crate) fn test_config(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: HeadlessRenderer::FORMAT,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn clear_color_reads_back() {
+        // Wide enough that each row needs padding for the copy.
+        let renderer = HeadlessRenderer::new(70, 3).unwrap();
+
+        renderer.clear(wgpu::Color::RED);
+        let pixels = renderer.read_pixels();
+
+        assert_eq!(pixels.len(), 70 * 3 * 4);
+        assert!(pixels.chunks_exact(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+}
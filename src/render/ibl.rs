@@ -0,0 +1,391 @@
+use wgpu::util::DeviceExt;
+
+/// Cubemaps get baked as half floats, since lighting adds up past 1.0.
+pub const IBL_CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub const BRDF_LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+
+/// How big (and for the specular map, how blurred) `IblMaps::bake_with` makes each map. Diffuse
+/// irradiance has no detail to speak of, so it can be tiny.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IblSettings {
+    pub irradiance_size: u32,
+    pub specular_size: u32,
+    /// Mip 0 is a mirror, and the last one fully rough.
+    pub specular_mip_levels: u32,
+    pub brdf_lut_size: u32,
+}
+
+impl Default for IblSettings {
+    fn default() -> Self {
+        Self {
+            irradiance_size: 32,
+            specular_size: 128,
+            specular_mip_levels: 5,
+            brdf_lut_size: 512,
+        }
+    }
+}
+
+/// What `bake_with` tells the shaders about each face it renders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BakeUniform {
+    face: u32,
+    roughness: f32,
+    _padding: [u32; 2],
+}
+
+/// Image based lighting maps baked from an environment cubemap (a `SkyboxComponent`'s `view`,
+/// say), for the split sum approximation:
+///  * `irradiance`: the diffuse light arriving from each direction
+///  * `specular`: the environment blurred more with each mip, for rougher and rougher surfaces
+///  * `brdf_lut`: the scale and bias to apply to F0, by `n·v` (across) and roughness (down)
+///
+/// `bind_group` (laid out as `bind_group_layout`) hands all three to a shader, along with a
+/// linear sampler, in that order, then the sampler. Baking happens once, on the GPU, and is
+/// tens of thousands of samples per texel, so it's best kept to load time.
+pub struct IblMaps {
+    pub irradiance: wgpu::Texture,
+    pub irradiance_view: wgpu::TextureView,
+    pub specular: wgpu::Texture,
+    pub specular_view: wgpu::TextureView,
+    pub brdf_lut: wgpu::Texture,
+    pub brdf_lut_view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+    settings: IblSettings,
+}
+
+impl IblMaps {
+    pub fn bake(device: &wgpu::Device, queue: &wgpu::Queue, env_cubemap: &wgpu::TextureView) -> Self {
+        Self::bake_with(device, queue, env_cubemap, IblSettings::default())
+    }
+
+    pub fn bake_with(device: &wgpu::Device, queue: &wgpu::Queue, env_cubemap: &wgpu::TextureView, settings: IblSettings) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/ibl_vertex.spv", "ibl_vertex");
+        let irradiance_shader = create_spv_shader!(device, "../../target/ibl_irradiance.spv", "ibl_irradiance");
+        let prefilter_shader = create_spv_shader!(device, "../../target/ibl_prefilter.spv", "ibl_prefilter");
+        let brdf_shader = create_spv_shader!(device, "../../target/ibl_brdf.spv", "ibl_brdf");
+
+        let specular_mip_levels = settings.specular_mip_levels
+            .clamp(1, crate::texture::Texture::mip_level_count((settings.specular_size, settings.specular_size)));
+        let settings = IblSettings { specular_mip_levels, ..settings };
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("IBL Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bake_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("IBL Bake Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let cube_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("IBL Bake Pipeline Layout"),
+            bind_group_layouts: &[&bake_layout],
+            push_constant_ranges: &[],
+        });
+        let lut_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("BRDF LUT Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |label, layout, fragment_shader, format| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: fragment_shader,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let irradiance_pipeline = pipeline("Irradiance Pipeline", &cube_pipeline_layout, &irradiance_shader, IBL_CUBE_FORMAT);
+        let prefilter_pipeline = pipeline("Prefilter Pipeline", &cube_pipeline_layout, &prefilter_shader, IBL_CUBE_FORMAT);
+        let brdf_pipeline = pipeline("BRDF LUT Pipeline", &lut_pipeline_layout, &brdf_shader, BRDF_LUT_FORMAT);
+
+        let irradiance = Self::create_cube(device, "Irradiance Map", settings.irradiance_size, 1);
+        let specular = Self::create_cube(device, "Specular Map", settings.specular_size, specular_mip_levels);
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("BRDF LUT"),
+            size: wgpu::Extent3d {
+                width: settings.brdf_lut_size.max(1),
+                height: settings.brdf_lut_size.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: BRDF_LUT_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("IBL Bake Encoder"),
+        });
+
+        // One draw per face (and per mip, for the specular map), each with its own uniform so
+        // they can all go in the same submission.
+        let mut faces = vec![];
+        for face in 0..6 {
+            faces.push((&irradiance_pipeline, &irradiance, face, 0, 0.0));
+
+            for mip_level in 0..specular_mip_levels {
+                let roughness = mip_level as f32 / (specular_mip_levels - 1).max(1) as f32;
+                faces.push((&prefilter_pipeline, &specular, face, mip_level, roughness));
+            }
+        }
+
+        for (pipeline, texture, face, mip_level, roughness) in faces {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("IBL Bake Buffer"),
+                contents: bytemuck::cast_slice(&[BakeUniform { face, roughness, _padding: [0; 2] }]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("IBL Bake Bind Group"),
+                layout: &bake_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(env_cubemap),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            let target = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("IBL Face View"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: mip_level,
+                mip_level_count: std::num::NonZeroU32::new(1),
+                base_array_layer: face,
+                array_layer_count: std::num::NonZeroU32::new(1),
+                ..Default::default()
+            });
+
+            Self::draw_fullscreen(&mut encoder, &target, pipeline, Some(&bind_group));
+        }
+
+        let brdf_lut_view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+        Self::draw_fullscreen(&mut encoder, &brdf_lut_view, &brdf_pipeline, None);
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let cube_view = |texture: &wgpu::Texture| texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let irradiance_view = cube_view(&irradiance);
+        let specular_view = cube_view(&specular);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("IBL Bind Group"),
+            layout: &Self::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&irradiance_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&specular_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&brdf_lut_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            irradiance,
+            irradiance_view,
+            specular,
+            specular_view,
+            brdf_lut,
+            brdf_lut_view,
+            sampler,
+            bind_group,
+            settings,
+        }
+    }
+
+    /// What the maps were baked with, with `specular_mip_levels` knocked down to what the
+    /// specular map's size allows.
+    pub fn settings(&self) -> IblSettings {
+        self.settings
+    }
+
+    /// How many mips the specular map has. A shader should sample it at
+    /// `roughness * (specular_mip_levels - 1)`.
+    pub fn specular_mip_levels(&self) -> u32 {
+        self.settings.specular_mip_levels
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ibl_bind_group_layout"),
+            entries: &[
+                texture(0, wgpu::TextureViewDimension::Cube),
+                texture(1, wgpu::TextureViewDimension::Cube),
+                texture(2, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    fn create_cube(device: &wgpu::Device, label: &str, size: u32, mip_levels: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size.max(1),
+                height: size.max(1),
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: mip_levels,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: IBL_CUBE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        })
+    }
+
+    fn draw_fullscreen(
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: Option<&wgpu::BindGroup>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("IBL Bake Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(pipeline);
+        if let Some(bind_group) = bind_group {
+            render_pass.set_bind_group(0, bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::error_scope::with_error_scope;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn bake_gives_the_specular_map_its_mip_levels() {
+        let gpu = test_renderer();
+        let environment = IblMaps::create_cube(&gpu.device, "Test Environment", 16, 1);
+        let environment_view = environment.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let settings = IblSettings {
+            irradiance_size: 4,
+            specular_size: 32,
+            specular_mip_levels: 4,
+            brdf_lut_size: 16,
+        };
+
+        let maps = IblMaps::bake_with(&gpu.device, &gpu.queue, &environment_view, settings);
+        assert_eq!(maps.specular_mip_levels(), 4);
+
+        // Viewing the last mip only validates if the texture really has that many.
+        let (_, error) = with_error_scope(&gpu.device, "viewing the last specular mip", || {
+            maps.specular.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                base_mip_level: 3,
+                mip_level_count: std::num::NonZeroU32::new(1),
+                ..Default::default()
+            })
+        });
+        assert!(error.is_none());
+    }
+}
@@ -0,0 +1,19 @@
+/// Mirrors `wgpu::util::DrawIndexedIndirect`, which isn't `Pod`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+/// What a device needs to draw several `DrawIndexedIndirectArgs` with one
+/// `multi_draw_indexed_indirect`, when they index into a shared instance buffer.
+pub const MULTI_DRAW_FEATURES: wgpu::Features = wgpu::Features::MULTI_DRAW_INDIRECT
+    .union(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+
+pub fn supports_multi_draw(features: wgpu::Features) -> bool {
+    features.contains(MULTI_DRAW_FEATURES)
+}
@@ -0,0 +1,28 @@
+pub mod depth;
+pub mod render_system;
+pub mod frustum;
+pub mod pipeline;
+pub mod camera_controller;
+pub mod render_target;
+pub mod headless;
+pub mod clear_color;
+pub mod skybox;
+#[cfg(feature = "hot-reload")]
+pub mod shader;
+pub mod error_scope;
+pub mod primitives;
+pub mod compute_cull;
+pub mod debug_lines;
+pub mod buffer_pool;
+pub mod graph;
+pub mod stats;
+pub mod timing;
+pub mod surface;
+#[cfg(feature = "text")]
+pub mod text;
+pub mod picking;
+pub mod indirect;
+pub mod fxaa;
+pub mod shadow;
+pub mod ibl;
+pub mod grid;
@@ -0,0 +1,280 @@
+use wgpu::util::DeviceExt;
+
+use crate::vertex::{PureVertex, Vertex};
+use crate::ecs::world::{EntityId, World};
+use crate::ecs::component::camera::CameraComponent;
+use crate::ecs::component::mesh::MeshComponent;
+use crate::ecs::component::instance::{InstanceComponent, InstanceRaw};
+use crate::render::depth::DepthTexture;
+
+/// Each pixel of the id target holds `instance + 1` of whatever was drawn there (0 being the
+/// background), where `instance` counts across every entity drawn in the pass.
+pub const PICKING_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// Picks exactly what's under the cursor, however thin, by drawing every instance's id into an
+/// offscreen `PICKING_FORMAT` target and reading back the pixel that was clicked. Unlike the
+/// ray picking in `InstanceComponent::pick`, this goes by the actual triangles.
+///
+/// Integer targets can't be multisampled (there's no resolving them), so the pass is always
+/// drawn at one sample per pixel. Only entities `RenderSystem::draw` would draw directly (a
+/// `MeshComponent` and an `InstanceComponent`) take part.
+pub struct PickingPass {
+    pipeline: wgpu::RenderPipeline,
+    id_texture: wgpu::Texture,
+    id_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    // Which entity each run of instance ids belongs to, as of the last `render`.
+    ranges: Vec<(EntityId, u32, u32)>,
+}
+
+impl PickingPass {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/picking_vertex.spv", "picking_vertex");
+        let fragment_shader = create_spv_shader!(device, "../../target/picking_fragment.spv", "picking_fragment");
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Picking Pipeline Layout"),
+            bind_group_layouts: &[
+                &CameraComponent::bind_group_layout(device),
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Picking Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[PureVertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: PICKING_FORMAT,
+                    // Ids can't be blended.
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthTexture::depth_stencil_state()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (id_texture, id_view, depth_view) = Self::create_targets(device, width, height);
+
+        Self {
+            pipeline,
+            id_texture,
+            id_view,
+            depth_view,
+            width: width.max(1),
+            height: height.max(1),
+            ranges: vec![],
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.id_texture, self.id_view, self.depth_view) = Self::create_targets(device, width, height);
+        self.width = width.max(1);
+        self.height = height.max(1);
+        self.ranges.clear();
+    }
+
+    fn create_targets(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView, wgpu::TextureView) {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let target = |label, format, usage| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+        });
+
+        let id_texture = target("Picking Id Texture", PICKING_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC);
+        let depth_texture = target("Picking Depth Texture", DepthTexture::FORMAT, wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+        let id_view = id_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (id_texture, id_view, depth_view)
+    }
+
+    /// Draws the world's ids. Every entity's instances get copied into one buffer first, so
+    /// the ids carry on from one entity to the next instead of starting over at 0.
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &World) {
+        let camera = world.resource::<CameraComponent>()
+            .or_else(|| world.query::<CameraComponent>().next().map(|(_, camera)| camera));
+
+        let mut drawables = world.query::<InstanceComponent>()
+            .filter_map(|(entity, instances)| Some((entity, world.get::<MeshComponent>(entity)?, instances)))
+            .collect::<Vec<_>>();
+        // Keeps the ids stable between renders, whatever order the query visits.
+        drawables.sort_by_key(|(entity, ..)| (entity.index, entity.generation));
+
+        self.ranges.clear();
+        let mut raw = vec![];
+        for (entity, _, instances) in &drawables {
+            self.ranges.push((*entity, raw.len() as u32, instances.instances.len() as u32));
+            raw.extend(instances.raw_instances());
+        }
+
+        let instance_buffer = (!raw.is_empty()).then(|| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Instance Buffer"),
+            contents: bytemuck::cast_slice(&raw),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Picking Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Picking Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            if let (Some(camera), Some(instance_buffer)) = (camera, &instance_buffer) {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &camera.bind_group, &[]);
+                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+                // Starting each draw at the entity's first instance makes the shader's instance
+                // index count across entities, and read from the right place in the buffer.
+                for ((_, mesh, _), (_, start, len)) in drawables.iter().zip(&self.ranges) {
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..mesh.num_indices, 0, *start..start + len);
+                }
+            }
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// The entity, and index into its `InstanceComponent::instances`, drawn at pixel `(x, y)`
+    /// (from the top left) by the last `render`. Blocks until the GPU is done with it.
+    pub fn read_pixel(&self, device: &wgpu::Device, queue: &wgpu::Queue, x: u32, y: u32) -> Option<(EntityId, u32)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Readback Buffer"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Picking Readback Encoder"),
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.id_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..4);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+
+        let id = u32::from_le_bytes(slice.get_mapped_range()[..4].try_into().unwrap_or_default());
+        buffer.unmap();
+
+        self.resolve_id(id)
+    }
+
+    /// Turns an id read back from the target into the entity and instance it stands for.
+    pub fn resolve_id(&self, id: u32) -> Option<(EntityId, u32)> {
+        let instance = id.checked_sub(1)?;
+
+        self.ranges.iter()
+            .find(|(_, start, len)| (*start..start + len).contains(&instance))
+            .map(|(entity, start, _)| (*entity, instance - start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Point3;
+    use crate::ecs::component::instance::{Instance, InstanceComponentBuilder};
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn pixel_covered_by_one_instance_picks_index_0() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        // Square on to the default mesh's first (upper left) triangle, off its diagonal.
+        let mut world = World::new();
+        world.insert_resource(CameraComponent::new(device, Point3::new(0.5, 0.75, 3.0), Point3::new(0.5, 0.75, 0.0), 1.0, cgmath::Deg(45.0)));
+        let entity = world.spawn_bundle((
+            MeshComponent::default(device, 0, 0),
+            InstanceComponentBuilder::new().instance(Instance::default()).build(device),
+        ));
+
+        let mut picking = PickingPass::new(device, 64, 64);
+        picking.render(device, &gpu.queue, &world);
+
+        assert_eq!(picking.read_pixel(device, &gpu.queue, 32, 32), Some((entity, 0)));
+        assert_eq!(picking.read_pixel(device, &gpu.queue, 0, 0), None);
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use log::warn;
+
+use crate::vertex::{PureVertex, Vertex};
+use crate::ecs::component::instance::InstanceRaw;
+use crate::ecs::component::material::MaterialComponent;
+use crate::render::depth::DepthTexture;
+use crate::error::WafError;
+
+/// The main scene pipeline, along with everything needed to rebuild it. Settings like the polygon
+/// mode can then be swapped at runtime without spelling out the whole descriptor again.
+pub struct ScenePipeline {
+    pub pipeline: wgpu::RenderPipeline,
+    layout: wgpu::PipelineLayout,
+    vertex_shader: wgpu::ShaderModule,
+    fragment_shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    polygon_mode: wgpu::PolygonMode,
+    sample_count: u32,
+    blend: wgpu::BlendState,
+    depth_prepass: bool,
+    // Only there while `depth_prepass' is on.
+    prepass: Option<wgpu::RenderPipeline>,
+    // For transparent materials, whatever `blend' is.
+    transparent: wgpu::RenderPipeline,
+    // Everything but the default triangle list, built as meshes ask for them.
+    variants: HashMap<wgpu::PrimitiveState, Variant>,
+}
+
+/// A color pipeline, its prepass twin if the prepass is on, and its transparent twin.
+type Variant = (wgpu::RenderPipeline, Option<wgpu::RenderPipeline>, wgpu::RenderPipeline);
+
+/// World resource that turns on `ScenePipeline`'s depth prepass. Without one it stays off.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepthPrepass(pub bool);
+
+impl ScenePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: wgpu::PipelineLayout,
+        vertex_shader: wgpu::ShaderModule,
+        fragment_shader: wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        polygon_mode: wgpu::PolygonMode,
+        sample_count: u32,
+    ) -> Self {
+        let polygon_mode = supported_polygon_mode(device.features(), polygon_mode);
+        let blend = wgpu::BlendState::REPLACE;
+        let primitive = Self::primitive_state(wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back), polygon_mode);
+        let pipeline = Self::create_pipeline(device, &layout, (&vertex_shader, &fragment_shader),
+            Self::color_target(format, blend), DepthTexture::depth_stencil_state(), primitive, sample_count);
+        let transparent = Self::create_pipeline(device, &layout, (&vertex_shader, &fragment_shader),
+            Self::color_target(format, wgpu::BlendState::ALPHA_BLENDING), DepthTexture::read_only_depth_stencil_state(),
+            primitive, sample_count);
+
+        Self {
+            pipeline,
+            layout,
+            vertex_shader,
+            fragment_shader,
+            format,
+            polygon_mode,
+            sample_count,
+            blend,
+            depth_prepass: false,
+            prepass: None,
+            transparent,
+            variants: HashMap::new(),
+        }
+    }
+
+    pub fn polygon_mode(&self) -> wgpu::PolygonMode {
+        self.polygon_mode
+    }
+
+    pub fn set_polygon_mode(&mut self, device: &wgpu::Device, polygon_mode: wgpu::PolygonMode) {
+        self.polygon_mode = supported_polygon_mode(device.features(), polygon_mode);
+        self.rebuild(device);
+    }
+
+    pub fn toggle_wireframe(&mut self, device: &wgpu::Device) {
+        let polygon_mode = match self.polygon_mode {
+            wgpu::PolygonMode::Fill => wgpu::PolygonMode::Line,
+            _ => wgpu::PolygonMode::Fill,
+        };
+
+        self.set_polygon_mode(device, polygon_mode);
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Has to match the sample count of the targets the pipeline draws into.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            self.rebuild(device);
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend != wgpu::BlendState::REPLACE
+    }
+
+    /// Transparent pipelines alpha blend, and test against depth without writing it, so things
+    /// behind a transparent surface still get drawn. Their instances want to be drawn back to
+    /// front (see `InstanceComponent::sort_by_depth').
+    pub fn set_transparent(&mut self, device: &wgpu::Device, transparent: bool) {
+        self.blend = if transparent {
+            wgpu::BlendState::ALPHA_BLENDING
+        } else {
+            wgpu::BlendState::REPLACE
+        };
+
+        self.rebuild(device);
+    }
+
+    /// The depth-only pipeline to draw the opaque geometry with first, if the prepass is on.
+    pub fn prepass_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.prepass.as_ref()
+    }
+
+    /// With the prepass on, the geometry gets drawn twice: once with `prepass_pipeline` to lay
+    /// down depth, then again with `pipeline`, which only passes where the depth is `Equal`.
+    /// Every pixel is then shaded once, however much overdraw there is. Both draws need the same
+    /// vertex and instance buffers, or the depths won't line up.
+    pub fn set_depth_prepass(&mut self, device: &wgpu::Device, enabled: bool) {
+        if enabled != self.depth_prepass {
+            self.depth_prepass = enabled;
+            self.rebuild(device);
+        }
+    }
+
+    pub fn depth_prepass(&self) -> bool {
+        self.depth_prepass
+    }
+
+    /// How many non-default variants have been built (since the last settings change, which
+    /// throws them all away).
+    pub fn variant_count(&self) -> usize {
+        self.variants.len()
+    }
+
+    /// Builds the variant for meshes drawn as `topology` with `cull_mode`, unless it's already
+    /// there. This needs to happen before the pass `variant` is used in.
+    pub fn prepare_variant(&mut self, device: &wgpu::Device, topology: wgpu::PrimitiveTopology, cull_mode: Option<wgpu::Face>) {
+        let primitive = Self::primitive_state(topology, cull_mode, self.polygon_mode);
+
+        if !self.is_default(primitive) && !self.variants.contains_key(&primitive) {
+            let variant = self.build_variant(device, primitive);
+            self.variants.insert(primitive, variant);
+        }
+    }
+
+    /// The pipeline (or with `prepass`, the prepass pipeline) for meshes drawn as `topology` with
+    /// `cull_mode`. `None` if `prepare_variant` hasn't been called for it, or if asking for a
+    /// prepass that's off.
+    pub fn variant(&self, topology: wgpu::PrimitiveTopology, cull_mode: Option<wgpu::Face>, prepass: bool) -> Option<&wgpu::RenderPipeline> {
+        let primitive = Self::primitive_state(topology, cull_mode, self.polygon_mode);
+        let (pipeline, prepass_pipeline) = if self.is_default(primitive) {
+            (&self.pipeline, self.prepass.as_ref())
+        } else {
+            let (pipeline, prepass_pipeline, _) = self.variants.get(&primitive)?;
+            (pipeline, prepass_pipeline.as_ref())
+        };
+
+        if prepass {
+            prepass_pipeline
+        } else {
+            Some(pipeline)
+        }
+    }
+
+    /// Like `variant`, but alpha blending and without depth writes, for transparent materials.
+    /// These get drawn after all the opaque geometry, back to front (see
+    /// `RenderSystem::draw_transparent`), and never in the prepass.
+    pub fn transparent_variant(&self, topology: wgpu::PrimitiveTopology, cull_mode: Option<wgpu::Face>) -> Option<&wgpu::RenderPipeline> {
+        let primitive = Self::primitive_state(topology, cull_mode, self.polygon_mode);
+
+        if self.is_default(primitive) {
+            Some(&self.transparent)
+        } else {
+            self.variants.get(&primitive).map(|(_, _, transparent)| transparent)
+        }
+    }
+
+    /// `transparent_variant` for transparent materials, `variant` for everything else (including
+    /// no material at all).
+    pub fn material_variant(
+        &self,
+        material: Option<&MaterialComponent>,
+        topology: wgpu::PrimitiveTopology,
+        cull_mode: Option<wgpu::Face>,
+        prepass: bool,
+    ) -> Option<&wgpu::RenderPipeline> {
+        match material {
+            Some(material) if material.transparent => if prepass {
+                None
+            } else {
+                self.transparent_variant(topology, cull_mode)
+            },
+            _ => self.variant(topology, cull_mode, prepass),
+        }
+    }
+
+    fn is_default(&self, primitive: wgpu::PrimitiveState) -> bool {
+        primitive == Self::primitive_state(wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back), self.polygon_mode)
+    }
+
+    fn primitive_state(topology: wgpu::PrimitiveTopology, cull_mode: Option<wgpu::Face>, polygon_mode: wgpu::PolygonMode) -> wgpu::PrimitiveState {
+        let strip_index_format = match topology {
+            wgpu::PrimitiveTopology::LineStrip | wgpu::PrimitiveTopology::TriangleStrip => Some(wgpu::IndexFormat::Uint32),
+            _ => None,
+        };
+
+        wgpu::PrimitiveState {
+            topology,
+            strip_index_format,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            polygon_mode,
+            unclipped_depth: false,
+            conservative: false,
+        }
+    }
+
+    fn rebuild(&mut self, device: &wgpu::Device) {
+        let primitive = Self::primitive_state(wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back), self.polygon_mode);
+        (self.pipeline, self.prepass, self.transparent) = self.build_variant(device, primitive);
+        self.variants.clear();
+    }
+
+    fn build_variant(&self, device: &wgpu::Device, primitive: wgpu::PrimitiveState) -> Variant {
+        let shaders = (&self.vertex_shader, &self.fragment_shader);
+        let depth_stencil = if self.is_transparent() {
+            DepthTexture::read_only_depth_stencil_state()
+        } else if self.depth_prepass {
+            wgpu::DepthStencilState {
+                depth_compare: wgpu::CompareFunction::Equal,
+                ..DepthTexture::read_only_depth_stencil_state()
+            }
+        } else {
+            DepthTexture::depth_stencil_state()
+        };
+
+        let pipeline = Self::create_pipeline(device, &self.layout, shaders, Self::color_target(self.format, self.blend),
+            depth_stencil, primitive, self.sample_count);
+        // Transparent geometry can't go in the prepass: it'd hide whatever is behind it.
+        let prepass = (self.depth_prepass && !self.is_transparent()).then(|| Self::create_pipeline(device, &self.layout,
+            shaders, Self::prepass_color_target(self.format), Self::prepass_depth_stencil_state(), primitive,
+            self.sample_count));
+        let transparent = Self::create_pipeline(device, &self.layout, shaders,
+            Self::color_target(self.format, wgpu::BlendState::ALPHA_BLENDING), DepthTexture::read_only_depth_stencil_state(),
+            primitive, self.sample_count);
+
+        (pipeline, prepass, transparent)
+    }
+
+    fn color_target(format: wgpu::TextureFormat, blend: wgpu::BlendState) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format,
+            blend: Some(blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    /// The prepass keeps a (masked off) color target so it can share the color pass's render pass.
+    pub fn prepass_color_target(format: wgpu::TextureFormat) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::empty(),
+        }
+    }
+
+    fn prepass_depth_stencil_state() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            ..DepthTexture::depth_stencil_state()
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        (vertex_shader, fragment_shader): (&wgpu::ShaderModule, &wgpu::ShaderModule),
+        target: wgpu::ColorTargetState,
+        depth_stencil: wgpu::DepthStencilState,
+        primitive: wgpu::PrimitiveState,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: vertex_shader,
+                entry_point: "main",
+                buffers: &[
+                    PureVertex::desc(), InstanceRaw::desc(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: fragment_shader,
+                entry_point: "main",
+                targets: &[Some(target)],
+            }),
+            primitive,
+            depth_stencil: Some(depth_stencil),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+}
+
+/// Line (and point) polygon modes need their own device features. Without them we fall back to
+/// `Fill` rather than letting pipeline creation blow up.
+pub fn supported_polygon_mode(features: wgpu::Features, polygon_mode: wgpu::PolygonMode) -> wgpu::PolygonMode {
+    require_polygon_mode(features, polygon_mode).unwrap_or_else(|e| {
+        warn!("Polygon mode {:?}: {}. Falling back to Fill.", polygon_mode, e);
+
+        wgpu::PolygonMode::Fill
+    })
+}
+
+/// Like `supported_polygon_mode`, but fails with the missing features instead of falling back.
+pub fn require_polygon_mode(features: wgpu::Features, polygon_mode: wgpu::PolygonMode) -> Result<wgpu::PolygonMode, WafError> {
+    let required = match polygon_mode {
+        wgpu::PolygonMode::Fill => return Ok(polygon_mode),
+        wgpu::PolygonMode::Line => wgpu::Features::POLYGON_MODE_LINE,
+        wgpu::PolygonMode::Point => wgpu::Features::POLYGON_MODE_POINT,
+    };
+
+    if features.contains(required) {
+        Ok(polygon_mode)
+    } else {
+        Err(WafError::UnsupportedFeature(required - features))
+    }
+}
+
+/// A `ScenePipeline` for tests, laid out like `State`'s: camera, shadow map, material.
+#[cfg(test)]
+pub(crate) fn test_scene_pipeline(device: &wgpu::Device) -> ScenePipeline {
+    use crate::ecs::component::camera::CameraComponent;
+    use crate::render::headless::HeadlessRenderer;
+    use crate::render::shadow::{ShadowPass, ShadowSettings};
+
+    let shadow_pass = ShadowPass::new(device, ShadowSettings::default());
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Test Pipeline Layout"),
+        bind_group_layouts: &[
+            &CameraComponent::bind_group_layout(device),
+            shadow_pass.bind_group_layout(),
+            &MaterialComponent::bind_group_layout(device),
+        ],
+        push_constant_ranges: &[],
+    });
+    let vertex_shader = create_spv_shader!(device, "../../target/vertex.spv", "vertex");
+    let fragment_shader = create_spv_shader!(device, "../../target/fragment.spv", "fragment");
+
+    ScenePipeline::new(device, layout, vertex_shader, fragment_shader, HeadlessRenderer::FORMAT,
+        wgpu::PolygonMode::Fill, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    fn line_without_the_feature_falls_back_to_fill() {
+        assert_eq!(supported_polygon_mode(wgpu::Features::empty(), wgpu::PolygonMode::Line), wgpu::PolygonMode::Fill);
+        assert!(matches!(require_polygon_mode(wgpu::Features::empty(), wgpu::PolygonMode::Line),
+            Err(WafError::UnsupportedFeature(missing)) if missing == wgpu::Features::POLYGON_MODE_LINE));
+    }
+
+    #[test]
+    fn line_with_the_feature_is_kept() {
+        assert_eq!(supported_polygon_mode(wgpu::Features::POLYGON_MODE_LINE, wgpu::PolygonMode::Line), wgpu::PolygonMode::Line);
+    }
+
+    #[test]
+    fn prepass_writes_no_color_and_tests_less_equal() {
+        let target = ScenePipeline::prepass_color_target(wgpu::TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(target.write_mask, wgpu::ColorWrites::empty());
+        assert_eq!(target.format, wgpu::TextureFormat::Bgra8UnormSrgb);
+
+        let depth = ScenePipeline::prepass_depth_stencil_state();
+        assert_eq!(depth.depth_compare, wgpu::CompareFunction::LessEqual);
+        assert!(depth.depth_write_enabled);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn point_and_triangle_lists_get_distinct_variants() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+        let mut pipeline = test_scene_pipeline(device);
+
+        pipeline.prepare_variant(device, wgpu::PrimitiveTopology::PointList, None);
+        pipeline.prepare_variant(device, wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back));
+        assert_eq!(pipeline.variant_count(), 1);
+
+        let points = pipeline.variant(wgpu::PrimitiveTopology::PointList, None, false).unwrap();
+        let triangles = pipeline.variant(wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back), false).unwrap();
+        assert!(!std::ptr::eq(points, triangles));
+        assert!(std::ptr::eq(triangles, &pipeline.pipeline));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn transparent_material_selects_the_blending_pipeline() {
+        let gpu = test_renderer();
+        let pipeline = test_scene_pipeline(&gpu.device);
+        let opaque = MaterialComponent::from_bytes(&gpu.device, &gpu.queue, &[255; 4], (1, 1), "Opaque Material");
+        let transparent = MaterialComponent::from_bytes(&gpu.device, &gpu.queue, &[255, 255, 255, 128], (1, 1), "Transparent Material")
+            .with_transparent(true);
+
+        let select = |material| pipeline.material_variant(material, wgpu::PrimitiveTopology::TriangleList, Some(wgpu::Face::Back), false).unwrap();
+        assert!(std::ptr::eq(select(Some(&transparent)), &pipeline.transparent));
+        assert!(std::ptr::eq(select(Some(&opaque)), &pipeline.pipeline));
+        assert!(std::ptr::eq(select(None), &pipeline.pipeline));
+    }
+}
@@ -0,0 +1,176 @@
+use std::f32::consts::PI;
+
+use crate::vertex::PureVertex;
+use crate::ecs::component::mesh::MeshComponent;
+
+// Every primitive is centered on the origin and a unit across, with counter-clockwise front
+// faces to match the scene pipeline's culling. They're `PureVertex` meshes, like everything else
+// the scene pipeline draws, colored by their normals (see `normal_color`) so their shape reads
+// without any lighting. Instance colors and materials tint them from there.
+
+/// Maps each axis of `normal` from -1..1 to 0..1, so +X faces are reddest, +Y greenest and so on.
+pub fn normal_color(normal: [f32; 3]) -> [f32; 3] {
+    normal.map(|n| n * 0.5 + 0.5)
+}
+
+/// 24 vertices (4 per face, so each face gets its own color) and 36 indices.
+pub fn cube(device: &wgpu::Device) -> MeshComponent {
+    let (vertices, indices) = cube_geometry();
+
+    MeshComponent::new("CUBE".to_owned(), device, vertices, indices, 0, 0)
+}
+
+fn cube_geometry() -> (Vec<PureVertex>, Vec<u32>) {
+    // Each face's normal, then the axes its corners are laid out along (right and up, seen from
+    // outside).
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+    let corners = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, right, up) in faces {
+        let first = vertices.len() as u32;
+
+        for (u, v) in corners {
+            let position = [0, 1, 2].map(|axis| normal[axis] * 0.5 + right[axis] * u + up[axis] * v);
+            vertices.push(PureVertex { position, color: normal_color(normal) });
+        }
+
+        indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    (vertices, indices)
+}
+
+/// `(rings + 1) * (sectors + 1)` vertices and `rings * sectors * 6` indices. The seam and poles
+/// get duplicated vertices, so the grid of rings and sectors stays regular. At least 2 rings and
+/// 3 sectors are used.
+pub fn uv_sphere(device: &wgpu::Device, rings: u32, sectors: u32) -> MeshComponent {
+    let (vertices, indices) = uv_sphere_geometry(rings, sectors);
+
+    MeshComponent::new("UV_SPHERE".to_owned(), device, vertices, indices, 0, 0)
+}
+
+fn uv_sphere_geometry(rings: u32, sectors: u32) -> (Vec<PureVertex>, Vec<u32>) {
+    let (rings, sectors) = (rings.max(2), sectors.max(3));
+
+    let mut vertices = Vec::with_capacity(((rings + 1) * (sectors + 1)) as usize);
+    for ring in 0..=rings {
+        let phi = PI * ring as f32 / rings as f32;
+
+        for sector in 0..=sectors {
+            let theta = 2.0 * PI * sector as f32 / sectors as f32;
+            let normal = [phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin()];
+
+            vertices.push(PureVertex { position: normal.map(|n| n * 0.5), color: normal_color(normal) });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((rings * sectors * 6) as usize);
+    for ring in 0..rings {
+        for sector in 0..sectors {
+            let current = ring * (sectors + 1) + sector;
+            let below = current + sectors + 1;
+
+            indices.extend_from_slice(&[current, current + 1, below, current + 1, below + 1, below]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// A flat square on the XZ plane facing +Y, split into `subdivisions` squares along each side
+/// (at least 1): `(subdivisions + 1)^2` vertices and `subdivisions^2 * 6` indices.
+pub fn plane(device: &wgpu::Device, subdivisions: u32) -> MeshComponent {
+    let (vertices, indices) = plane_geometry(subdivisions);
+
+    MeshComponent::new("PLANE".to_owned(), device, vertices, indices, 0, 0)
+}
+
+fn plane_geometry(subdivisions: u32) -> (Vec<PureVertex>, Vec<u32>) {
+    let subdivisions = subdivisions.max(1);
+    let side = subdivisions + 1;
+
+    let mut vertices = Vec::with_capacity((side * side) as usize);
+    for z in 0..side {
+        for x in 0..side {
+            let (u, v) = (x as f32 / subdivisions as f32, z as f32 / subdivisions as f32);
+
+            vertices.push(PureVertex { position: [u - 0.5, 0.0, v - 0.5], color: normal_color([0.0, 1.0, 0.0]) });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((subdivisions * subdivisions * 6) as usize);
+    for z in 0..subdivisions {
+        for x in 0..subdivisions {
+            let current = z * side + x;
+            let next_row = current + side;
+
+            indices.extend_from_slice(&[current, next_row, current + 1, current + 1, next_row, next_row + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::system::System;
+    use crate::ecs::world::World;
+    use crate::ecs::component::instance::{Instance, InstanceComponentBuilder};
+    use crate::ecs::component::material::MaterialComponent;
+    use crate::render::headless::test_renderer;
+    use crate::render::pipeline::test_scene_pipeline;
+    use crate::render::render_system::{RecordingPass, RenderSystem};
+    use crate::render::stats::RenderStats;
+
+    #[test]
+    fn cube_has_24_vertices_and_36_indices() {
+        let (vertices, indices) = cube_geometry();
+
+        assert_eq!(vertices.len(), 24);
+        assert_eq!(indices.len(), 36);
+    }
+
+    #[test]
+    fn sphere_vertex_count_follows_rings_and_sectors() {
+        for (rings, sectors) in [(2, 3), (8, 16), (5, 7)] {
+            let (vertices, indices) = uv_sphere_geometry(rings, sectors);
+
+            assert_eq!(vertices.len() as u32, (rings + 1) * (sectors + 1));
+            assert_eq!(indices.len() as u32, rings * sectors * 6);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn spawned_cube_is_drawn_by_the_render_system() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        let mut world = World::new();
+        world.spawn_bundle((cube(device), InstanceComponentBuilder::new().instance(Instance::default()).build(device)));
+
+        let pipeline = test_scene_pipeline(device);
+        let material = MaterialComponent::from_bytes(device, &gpu.queue, &[255; 4], (1, 1), "Test Material");
+
+        let mut system = RenderSystem::new();
+        system.run(&mut world, 0.0);
+
+        let mut pass = RecordingPass::default();
+        let mut stats = RenderStats::default();
+        system.draw(&world, &pipeline, &material.bind_group, false, &mut pass, &mut stats);
+
+        assert_eq!(pass.draws, 1);
+        assert_eq!(stats.triangles, 12);
+    }
+}
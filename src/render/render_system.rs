@@ -0,0 +1,758 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+use cgmath::prelude::*;
+use log::warn;
+use wgpu::util::DeviceExt;
+
+use crate::ecs::{
+    world::{EntityId, World},
+    system::System,
+    hierarchy::TransformPropagationSystem,
+    component::camera::CameraComponent,
+    component::mesh::MeshComponent,
+    component::material::MaterialComponent,
+    component::instance::{Instance, InstanceRaw, InstanceComponent},
+    component::renderable::RenderableComponent,
+    component::lod::LodComponent,
+};
+use crate::render::stats::RenderStats;
+use crate::render::indirect::{DrawIndexedIndirectArgs, supports_multi_draw};
+use crate::render::pipeline::ScenePipeline;
+use crate::render::grid::{GridFloor, ShowGrid};
+use crate::vertex::PureVertex;
+
+/// The `(mesh, material)` entities a batch is drawn with.
+type BatchKey = (EntityId, Option<EntityId>);
+
+/// One draw call's worth of `RenderableComponent`s. The buffer is kept across frames and only
+/// reallocated once the batch outgrows it.
+struct Batch {
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    len: usize,
+    // Only set for batches with a transparent material, which `draw' skips.
+    transparent: bool,
+    // From the camera to the batch's farthest instance, for ordering the transparent batches.
+    distance: f32,
+}
+
+/// Draws every entity that has both a `MeshComponent` and an `InstanceComponent`.
+///
+/// `run` (as a regular system) works out what needs drawing this frame, and `draw` then records
+/// it into a render pass, picking each mesh's variant of the `ScenePipeline` it's handed. That
+/// pipeline is expected to use:
+///  * bind group 0: the camera uniform (`CameraComponent::bind_group_layout`). The world's camera
+///    is bound there if it has one; otherwise whatever the caller bound is drawn with.
+///  * bind group 1: left alone for the caller (`State` binds the shadow map there)
+///  * bind group 2: the entity's or batch's material (`MaterialComponent::bind_group_layout`),
+///    or the `default_material` passed to `draw` for anything without one
+///  * vertex buffer slot 0: the mesh's vertices
+///  * vertex buffer slot 1: the raw instances
+///
+/// `GridFloor` entities are skipped while `ShowGrid` is off.
+///
+/// Entities with a `RenderableComponent` instead get grouped by mesh and material, with one
+/// draw per group. Entities with an `InstanceComponent` and a `LodComponent` get their instances
+/// split up by level of detail, with one draw per level in use. Both kinds of batch are only
+/// uploaded by `prepare_batches`, which needs to run (at some point after `run`) before `draw`.
+///
+/// Batches with a transparent material are left to `draw_transparent`, which needs to go after
+/// `draw`. It draws them back to front with `ScenePipeline::transparent_variant` (alpha blending,
+/// no depth writes), both the batches and the instances within each.
+///
+/// `draw` adds every call it records to a `RenderStats`, which the caller resets each frame (and
+/// usually stores back into the world once the pass is done).
+pub struct RenderSystem {
+    camera: Option<EntityId>,
+    drawables: Vec<EntityId>,
+    batches: HashMap<BatchKey, Batch>,
+    // Keyed by the entity and its level of detail.
+    lod_batches: HashMap<(EntityId, usize), Batch>,
+    // Only there with `set_indirect` on.
+    indirect: Option<IndirectBatches>,
+}
+
+/// Every opaque batch packed into shared buffers: the meshes one after another in a single
+/// vertex and index buffer, the instances likewise, and one `DrawIndexedIndirectArgs` per batch
+/// pointing into them. Batches sharing a material and a pipeline variant then go down in a single
+/// multi draw.
+///
+/// The meshes are only repacked when the set of them changes, so a mesh edited in place (with
+/// `MeshComponent::update_buffers`) keeps drawing its old geometry until then.
+#[derive(Default)]
+struct IndirectBatches {
+    // What's in `vertex_buffer' and `index_buffer', in order. Only rebuilt when this changes.
+    meshes: Vec<EntityId>,
+    // Where each mesh landed: first index, base vertex and index count.
+    mesh_ranges: HashMap<EntityId, (u32, i32, u32)>,
+    vertex_buffer: Option<GrowableBuffer>,
+    index_buffer: Option<GrowableBuffer>,
+    instance_buffer: Option<GrowableBuffer>,
+    args_buffer: Option<GrowableBuffer>,
+    args: Vec<DrawIndexedIndirectArgs>,
+    // Runs of `args' sharing a material and the topology and cull mode their meshes are drawn
+    // with, so a whole run can go down with one pipeline.
+    runs: Vec<(Option<EntityId>, Primitive, Range<usize>)>,
+    // Each entry's topology, for the stats.
+    topologies: Vec<wgpu::PrimitiveTopology>,
+    multi_draw: bool,
+}
+
+/// Which of the scene pipeline's variants a mesh is drawn with.
+type Primitive = (wgpu::PrimitiveTopology, Option<wgpu::Face>);
+
+/// What every draw in a pass picks its pipeline and fallback material from.
+#[derive(Copy, Clone)]
+struct PassState<'a> {
+    pipeline: &'a ScenePipeline,
+    default_material: &'a wgpu::BindGroup,
+    prepass: bool,
+}
+
+/// A buffer that's written to in place while its contents fit, and reallocated when they don't.
+struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+}
+
+/// The part of `wgpu::RenderPass` that `RenderSystem` draws with, so tests can hand it a pass
+/// that only writes down what it was asked to do.
+pub trait DrawPass<'a> {
+    fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline);
+    fn set_bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup);
+    fn set_vertex_buffer(&mut self, slot: u32, buffer: wgpu::BufferSlice<'a>);
+    fn set_index_buffer(&mut self, buffer: wgpu::BufferSlice<'a>, format: wgpu::IndexFormat);
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>);
+    fn multi_draw_indexed_indirect(&mut self, buffer: &'a wgpu::Buffer, offset: wgpu::BufferAddress, count: u32);
+}
+
+impl<'a> DrawPass<'a> for wgpu::RenderPass<'a> {
+    fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+        wgpu::RenderPass::set_pipeline(self, pipeline);
+    }
+
+    fn set_bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup) {
+        wgpu::RenderPass::set_bind_group(self, index, bind_group, &[]);
+    }
+
+    fn set_vertex_buffer(&mut self, slot: u32, buffer: wgpu::BufferSlice<'a>) {
+        wgpu::RenderPass::set_vertex_buffer(self, slot, buffer);
+    }
+
+    fn set_index_buffer(&mut self, buffer: wgpu::BufferSlice<'a>, format: wgpu::IndexFormat) {
+        wgpu::RenderPass::set_index_buffer(self, buffer, format);
+    }
+
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        wgpu::RenderPass::draw_indexed(self, indices, base_vertex, instances);
+    }
+
+    fn multi_draw_indexed_indirect(&mut self, buffer: &'a wgpu::Buffer, offset: wgpu::BufferAddress, count: u32) {
+        wgpu::RenderPass::multi_draw_indexed_indirect(self, buffer, offset, count);
+    }
+}
+
+impl Default for RenderSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for RenderSystem {
+    fn run(&mut self, world: &mut World, _dt: f32) {
+        let show_grid = world.resource::<ShowGrid>().is_some_and(|show| show.0);
+
+        self.camera = world.query::<CameraComponent>().map(|(entity, _)| entity).next();
+        self.drawables = world.query::<InstanceComponent>()
+            .map(|(entity, _)| entity)
+            .filter(|entity| world.get::<MeshComponent>(*entity).is_some() && world.get::<LodComponent>(*entity).is_none())
+            .filter(|entity| show_grid || world.get::<GridFloor>(*entity).is_none())
+            .collect();
+    }
+}
+
+impl RenderSystem {
+    pub fn new() -> Self {
+        Self {
+            camera: None,
+            drawables: vec![],
+            batches: HashMap::new(),
+            lod_batches: HashMap::new(),
+            indirect: None,
+        }
+    }
+
+    /// Packs the opaque `RenderableComponent` batches into shared buffers (see `prepare_batches`)
+    /// and draws them with one `multi_draw_indexed_indirect` per material and pipeline variant
+    /// (a mesh's topology and cull mode), on devices with `MULTI_DRAW_FEATURES`. Elsewhere it
+    /// draws them one by one from the shared buffers, which still saves rebinding them. Worth it
+    /// for scenes with lots of small batches.
+    pub fn set_indirect(&mut self, enabled: bool) {
+        if enabled != self.indirect.is_some() {
+            self.indirect = enabled.then(IndirectBatches::default);
+        }
+    }
+
+    /// One entry per opaque batch, as of the last `prepare_batches`. Empty with `set_indirect` off.
+    pub fn indirect_args(&self) -> &[DrawIndexedIndirectArgs] {
+        self.indirect.as_ref().map(|indirect| indirect.args.as_slice()).unwrap_or_default()
+    }
+
+    /// How many draw calls the `RenderableComponent`s currently collapse into.
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Regroups every `RenderableComponent` and uploads each group's transforms. Batches whose
+    /// instance count still fits their buffer just get written to, so a stable scene doesn't
+    /// allocate from frame to frame.
+    pub fn prepare_batches(&mut self, world: &World, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut groups: HashMap<BatchKey, Vec<EntityId>> = HashMap::new();
+        for (entity, renderable) in world.query::<RenderableComponent>() {
+            groups.entry((renderable.mesh, renderable.material)).or_default().push(entity);
+        }
+
+        self.batches.retain(|key, _| groups.contains_key(key));
+        let mut opaque = vec![];
+
+        let transforms = TransformPropagationSystem::resolve(world);
+        let camera_position = self.camera_position(world);
+        for (key, mut entities) in groups {
+            // Keeps the instance order stable between frames, whatever order the query visits.
+            entities.sort_by_key(|entity| (entity.index, entity.generation));
+
+            let transparent = key.1
+                .and_then(|material| world.get::<MaterialComponent>(material))
+                .is_some_and(|material| material.transparent);
+            let transform = |entity: &EntityId| transforms.get(entity).copied().unwrap_or_else(cgmath::Matrix4::identity);
+            let distance = |entity: &EntityId| transform(entity).w.truncate().distance(camera_position);
+
+            // Farthest first, so nearer surfaces blend over the ones behind them.
+            if transparent {
+                entities.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+            }
+
+            let instances = entities.iter()
+                .map(|entity| Instance::default().to_raw_in(transform(entity)))
+                .collect::<Vec<InstanceRaw>>();
+
+            upload_batch(&mut self.batches, key, &instances, device, queue);
+
+            if !transparent && self.indirect.is_some() {
+                opaque.push((key, instances));
+            }
+
+            if let Some(batch) = self.batches.get_mut(&key) {
+                batch.transparent = transparent;
+                batch.distance = entities.iter().map(distance).fold(0.0, f32::max);
+            }
+        }
+
+        if let Some(indirect) = &mut self.indirect {
+            indirect.prepare(world, device, queue, opaque);
+        }
+
+        self.prepare_lod_batches(world, device, queue);
+    }
+
+    /// A camera stored as a resource wins over one attached to an entity.
+    fn camera<'a>(&self, world: &'a World) -> Option<&'a CameraComponent> {
+        world.resource::<CameraComponent>()
+            .or_else(|| self.camera.and_then(|entity| world.get::<CameraComponent>(entity)))
+    }
+
+    fn camera_position(&self, world: &World) -> cgmath::Vector3<f32> {
+        self.camera(world)
+            .map(|camera| camera.eye.to_vec())
+            .unwrap_or_else(cgmath::Vector3::zero)
+    }
+
+    fn prepare_lod_batches(&mut self, world: &World, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let camera_position = self.camera_position(world);
+
+        let mut levels: HashMap<(EntityId, usize), Vec<InstanceRaw>> = HashMap::new();
+        for (entity, lod) in world.query::<LodComponent>() {
+            let instances = match world.get::<InstanceComponent>(entity) {
+                Some(instances) => instances,
+                None => continue,
+            };
+
+            for instance in &instances.instances {
+                let distance = instances.world_position(instance).distance(camera_position);
+
+                if let Some(level) = lod.select(distance) {
+                    levels.entry((entity, level)).or_default().push(instance.to_raw_in(instances.transform));
+                }
+            }
+        }
+
+        self.lod_batches.retain(|key, _| levels.contains_key(key));
+
+        for (key, instances) in levels {
+            upload_batch(&mut self.lod_batches, key, &instances, device, queue);
+        }
+    }
+
+    /// Records everything opaque. With `prepass`, only the depth (using the pipeline's prepass
+    /// variants), for a second call without it to shade.
+    pub fn draw<'a, P: DrawPass<'a>>(
+        &'a self,
+        world: &'a World,
+        pipeline: &'a ScenePipeline,
+        default_material: &'a wgpu::BindGroup,
+        prepass: bool,
+        render_pass: &mut P,
+        stats: &mut RenderStats,
+    ) {
+        let pass = PassState { pipeline, default_material, prepass };
+
+        if let Some(camera) = self.camera(world) {
+            render_pass.set_bind_group(0, &camera.bind_group);
+        }
+
+        for entity in &self.drawables {
+            let (mesh, instances) = match (world.get::<MeshComponent>(*entity), world.get::<InstanceComponent>(*entity)) {
+                (Some(mesh), Some(instances)) => (mesh, instances),
+                _ => continue,
+            };
+
+            if !pass.bind(world.get::<MaterialComponent>(*entity), mesh.topology, mesh.cull_mode, render_pass) {
+                continue;
+            }
+
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, instances.instance_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..instances.instances.len() as _);
+            stats.record_draw(mesh.topology, mesh.num_indices, instances.instances.len() as _);
+        }
+
+        match &self.indirect {
+            Some(indirect) => indirect.draw(world, pass, render_pass, stats),
+            None => for (key, batch) in self.batches.iter().filter(|(_, batch)| !batch.transparent) {
+                draw_batch(world, pass, render_pass, *key, batch, stats);
+            },
+        }
+
+        for ((entity, level), batch) in &self.lod_batches {
+            let mesh = world.get::<LodComponent>(*entity)
+                .and_then(|lod| lod.levels.get(*level))
+                .and_then(|(_, mesh)| world.get::<MeshComponent>(*mesh));
+
+            let mesh = match mesh {
+                Some(mesh) => mesh,
+                None => {
+                    warn!("Level {} of entity {:?} doesn't point at a mesh. Not rendering!", level, entity);
+                    continue;
+                }
+            };
+
+            if !pass.bind(world.get::<MaterialComponent>(*entity), mesh.topology, mesh.cull_mode, render_pass) {
+                continue;
+            }
+
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, batch.instance_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..batch.len as _);
+            stats.record_draw(mesh.topology, mesh.num_indices, batch.len as _);
+        }
+    }
+
+    /// Draws the batches `draw` skipped, farthest first, with the pipeline's transparent variants.
+    /// The pass should already have the opaque geometry (and its depth) in it.
+    pub fn draw_transparent<'a, P: DrawPass<'a>>(
+        &'a self,
+        world: &'a World,
+        pipeline: &'a ScenePipeline,
+        default_material: &'a wgpu::BindGroup,
+        render_pass: &mut P,
+        stats: &mut RenderStats,
+    ) {
+        let pass = PassState { pipeline, default_material, prepass: false };
+
+        let mut batches = self.batches.iter().filter(|(_, batch)| batch.transparent).collect::<Vec<_>>();
+        batches.sort_by(|(_, a), (_, b)| b.distance.total_cmp(&a.distance));
+
+        if let Some(camera) = self.camera(world) {
+            render_pass.set_bind_group(0, &camera.bind_group);
+        }
+
+        for (key, batch) in batches {
+            draw_batch(world, pass, render_pass, *key, batch, stats);
+        }
+    }
+}
+
+impl<'a> PassState<'a> {
+    /// Sets the pipeline variant for `material` (see `ScenePipeline::material_variant`) and binds
+    /// the material, or the default one. `false` if the variant hasn't been prepared, in which
+    /// case nothing should be drawn.
+    fn bind<P: DrawPass<'a>>(
+        self,
+        material: Option<&'a MaterialComponent>,
+        topology: wgpu::PrimitiveTopology,
+        cull_mode: Option<wgpu::Face>,
+        render_pass: &mut P,
+    ) -> bool {
+        match self.pipeline.material_variant(material, topology, cull_mode, self.prepass) {
+            Some(pipeline) => render_pass.set_pipeline(pipeline),
+            None => return false,
+        }
+
+        render_pass.set_bind_group(2, material.map_or(self.default_material, |material| &material.bind_group));
+        true
+    }
+}
+
+impl IndirectBatches {
+    fn prepare(&mut self, world: &World, device: &wgpu::Device, queue: &wgpu::Queue, mut batches: Vec<(BatchKey, Vec<InstanceRaw>)>) {
+        self.multi_draw = supports_multi_draw(device.features());
+
+        // Grouped by material and then pipeline variant, so each combination's batches form one run.
+        let order = |entity: Option<EntityId>| entity.map(|entity| (entity.index, entity.generation));
+        let primitive = |mesh: EntityId| world.get::<MeshComponent>(mesh)
+            .map(|mesh| (mesh.topology as u32, mesh.cull_mode.map(|face| face as u32)));
+        batches.sort_by_key(|((mesh, material), _)| (order(*material), primitive(*mesh), order(Some(*mesh))));
+
+        let mut meshes = batches.iter()
+            .map(|((mesh, _), _)| *mesh)
+            .filter(|mesh| world.get::<MeshComponent>(*mesh).is_some())
+            .collect::<Vec<_>>();
+        meshes.sort_by_key(|mesh| (mesh.index, mesh.generation));
+        meshes.dedup();
+
+        if meshes != self.meshes {
+            self.pack_meshes(world, device, queue, meshes);
+        }
+
+        self.args.clear();
+        self.runs.clear();
+        self.topologies.clear();
+        let mut instances = vec![];
+
+        for ((mesh, material), batch_instances) in batches {
+            let (&(first_index, base_vertex, index_count), mesh) = match (self.mesh_ranges.get(&mesh), world.get::<MeshComponent>(mesh)) {
+                (Some(range), Some(mesh)) => (range, mesh),
+                _ => {
+                    warn!("Batch points at entity {:?}, which has no mesh. Not rendering!", mesh);
+                    continue;
+                }
+            };
+
+            let primitive = (mesh.topology, mesh.cull_mode);
+            match self.runs.last_mut() {
+                Some((run_material, run_primitive, range)) if *run_material == material && *run_primitive == primitive =>
+                    range.end += 1,
+                _ => self.runs.push((material, primitive, self.args.len()..self.args.len() + 1)),
+            }
+
+            self.args.push(DrawIndexedIndirectArgs {
+                index_count,
+                instance_count: batch_instances.len() as u32,
+                first_index,
+                base_vertex,
+                first_instance: instances.len() as u32,
+            });
+            self.topologies.push(mesh.topology);
+            instances.extend(batch_instances);
+        }
+
+        upload(&mut self.instance_buffer, device, queue, bytemuck::cast_slice(&instances),
+            wgpu::BufferUsages::VERTEX, "Indirect Instance Buffer");
+        upload(&mut self.args_buffer, device, queue, bytemuck::cast_slice(&self.args),
+            wgpu::BufferUsages::INDIRECT, "Indirect Args Buffer");
+    }
+
+    fn pack_meshes(&mut self, world: &World, device: &wgpu::Device, queue: &wgpu::Queue, meshes: Vec<EntityId>) {
+        let mut vertices: Vec<PureVertex> = vec![];
+        let mut indices: Vec<u32> = vec![];
+        self.mesh_ranges.clear();
+
+        for entity in &meshes {
+            if let Some(mesh) = world.get::<MeshComponent>(*entity) {
+                self.mesh_ranges.insert(*entity, (indices.len() as u32, vertices.len() as i32, mesh.indices.len() as u32));
+                vertices.extend_from_slice(&mesh.vertices);
+                indices.extend_from_slice(&mesh.indices);
+            }
+        }
+
+        upload(&mut self.vertex_buffer, device, queue, bytemuck::cast_slice(&vertices),
+            wgpu::BufferUsages::VERTEX, "Indirect Vertex Buffer");
+        upload(&mut self.index_buffer, device, queue, bytemuck::cast_slice(&indices),
+            wgpu::BufferUsages::INDEX, "Indirect Index Buffer");
+        self.meshes = meshes;
+    }
+
+    fn draw<'a, P: DrawPass<'a>>(&'a self, world: &'a World, pass: PassState<'a>, render_pass: &mut P, stats: &mut RenderStats) {
+        let buffers = (&self.vertex_buffer, &self.index_buffer, &self.instance_buffer, &self.args_buffer);
+        let (vertex_buffer, index_buffer, instance_buffer, args_buffer) = match buffers {
+            (Some(vertex_buffer), Some(index_buffer), Some(instance_buffer), Some(args_buffer)) if !self.args.is_empty() =>
+                (vertex_buffer, index_buffer, instance_buffer, args_buffer),
+            _ => return,
+        };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        for (material, (topology, cull_mode), range) in &self.runs {
+            let material = material.and_then(|material| world.get::<MaterialComponent>(material));
+
+            if !pass.bind(material, *topology, *cull_mode, render_pass) {
+                continue;
+            }
+
+            if self.multi_draw {
+                let offset = (range.start * std::mem::size_of::<DrawIndexedIndirectArgs>()) as wgpu::BufferAddress;
+                render_pass.multi_draw_indexed_indirect(&args_buffer.buffer, offset, range.len() as u32);
+                stats.draw_calls += 1;
+            }
+
+            for (args, topology) in self.args[range.clone()].iter().zip(&self.topologies[range.clone()]) {
+                if self.multi_draw {
+                    stats.record_instances(*topology, args.index_count, args.instance_count);
+                } else {
+                    render_pass.draw_indexed(args.first_index..args.first_index + args.index_count, args.base_vertex,
+                        args.first_instance..args.first_instance + args.instance_count);
+                    stats.record_draw(*topology, args.index_count, args.instance_count);
+                }
+            }
+        }
+    }
+}
+
+/// Writes `contents` into `slot`, only allocating a new buffer if there isn't one yet or it's too
+/// small. Nothing happens for empty contents, since zero sized buffers can't be bound.
+fn upload(
+    slot: &mut Option<GrowableBuffer>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    contents: &[u8],
+    usage: wgpu::BufferUsages,
+    label: &str,
+) {
+    if contents.is_empty() {
+        return;
+    }
+
+    match slot {
+        Some(growable) if growable.size >= contents.len() as wgpu::BufferAddress => {
+            queue.write_buffer(&growable.buffer, 0, contents);
+        },
+        _ => {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+            });
+
+            *slot = Some(GrowableBuffer {
+                buffer,
+                size: contents.len() as wgpu::BufferAddress,
+            });
+        },
+    }
+}
+
+/// Binds the batch's mesh, material and pipeline variant, and draws it.
+fn draw_batch<'a, P: DrawPass<'a>>(
+    world: &'a World,
+    pass: PassState<'a>,
+    render_pass: &mut P,
+    (mesh, material): BatchKey,
+    batch: &'a Batch,
+    stats: &mut RenderStats,
+) {
+    let mesh = match world.get::<MeshComponent>(mesh) {
+        Some(mesh) => mesh,
+        None => {
+            warn!("Batch points at entity {:?}, which has no mesh. Not rendering!", mesh);
+            return;
+        }
+    };
+
+    let material = material.and_then(|material| world.get::<MaterialComponent>(material));
+    if !pass.bind(material, mesh.topology, mesh.cull_mode, render_pass) {
+        return;
+    }
+
+    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+    render_pass.set_vertex_buffer(1, batch.instance_buffer.slice(..));
+    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    render_pass.draw_indexed(0..mesh.num_indices, 0, 0..batch.len as _);
+    stats.record_draw(mesh.topology, mesh.num_indices, batch.len as _);
+}
+
+/// Writes `instances` into the batch at `key`, only allocating a new buffer if there isn't one
+/// yet or it's too small.
+fn upload_batch<K: Hash + Eq>(
+    batches: &mut HashMap<K, Batch>,
+    key: K,
+    instances: &[InstanceRaw],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) {
+    match batches.get_mut(&key) {
+        Some(batch) if batch.capacity >= instances.len() => {
+            queue.write_buffer(&batch.instance_buffer, 0, bytemuck::cast_slice(instances));
+            batch.len = instances.len();
+        },
+        _ => {
+            let instance_buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Batch Instance Buffer"),
+                    contents: bytemuck::cast_slice(instances),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                }
+            );
+
+            batches.insert(key, Batch {
+                instance_buffer,
+                capacity: instances.len(),
+                len: instances.len(),
+                transparent: false,
+                distance: 0.0,
+            });
+        },
+    }
+}
+
+/// A `DrawPass` for tests that only counts what it's asked to draw.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingPass {
+    pub draws: usize,
+    pub multi_draws: usize,
+}
+
+#[cfg(test)]
+impl<'a> DrawPass<'a> for RecordingPass {
+    fn set_pipeline(&mut self, _pipeline: &'a wgpu::RenderPipeline) {}
+    fn set_bind_group(&mut self, _index: u32, _bind_group: &'a wgpu::BindGroup) {}
+    fn set_vertex_buffer(&mut self, _slot: u32, _buffer: wgpu::BufferSlice<'a>) {}
+    fn set_index_buffer(&mut self, _buffer: wgpu::BufferSlice<'a>, _format: wgpu::IndexFormat) {}
+
+    fn draw_indexed(&mut self, _indices: Range<u32>, _base_vertex: i32, _instances: Range<u32>) {
+        self.draws += 1;
+    }
+
+    fn multi_draw_indexed_indirect(&mut self, _buffer: &'a wgpu::Buffer, _offset: wgpu::BufferAddress, _count: u32) {
+        self.multi_draws += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::instance::InstanceComponentBuilder;
+    use crate::render::headless::test_renderer;
+    use crate::render::pipeline::test_scene_pipeline;
+    use crate::ecs::component::transform::TransformComponent;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn one_entity_world_draws_once() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        let mut world = World::new();
+        world.spawn_bundle((
+            MeshComponent::default(device, 0, 0),
+            InstanceComponentBuilder::new().instance(Instance::default()).build(device),
+        ));
+
+        let pipeline = test_scene_pipeline(device);
+        let material = MaterialComponent::from_bytes(device, &gpu.queue, &[255; 4], (1, 1), "Test Material");
+
+        let mut system = RenderSystem::new();
+        system.run(&mut world, 0.0);
+
+        let mut pass = RecordingPass::default();
+        let mut stats = RenderStats::default();
+        system.draw(&world, &pipeline, &material.bind_group, false, &mut pass, &mut stats);
+
+        assert_eq!(pass.draws, 1);
+        assert_eq!(pass.multi_draws, 0);
+        assert_eq!(stats.draw_calls, 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn four_entities_sharing_a_mesh_draw_once() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        let mut world = World::new();
+        let mesh = world.spawn();
+        world.insert(mesh, MeshComponent::default(device, 0, 0));
+
+        for x in 0..4 {
+            let entity = world.spawn();
+            let position = cgmath::Vector3::new(x as f32, 0.0, 0.0);
+            world.insert(entity, TransformComponent::new(position, cgmath::Quaternion::one(), cgmath::Vector3::new(1.0, 1.0, 1.0)));
+            world.insert(entity, RenderableComponent { mesh, material: None });
+        }
+
+        let pipeline = test_scene_pipeline(device);
+        let material = MaterialComponent::from_bytes(device, &gpu.queue, &[255; 4], (1, 1), "Test Material");
+
+        let mut system = RenderSystem::new();
+        system.run(&mut world, 0.0);
+        system.prepare_batches(&world, device, &gpu.queue);
+        assert_eq!(system.batch_count(), 1);
+
+        let mut pass = RecordingPass::default();
+        let mut stats = RenderStats::default();
+        system.draw(&world, &pipeline, &material.bind_group, false, &mut pass, &mut stats);
+
+        assert_eq!(pass.draws, 1);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn indirect_args_have_one_entry_per_batch() {
+        let gpu = test_renderer();
+        let device = &gpu.device;
+
+        let mut world = World::new();
+        let square = world.spawn();
+        world.insert(square, MeshComponent::default(device, 0, 0));
+        let triangle = world.spawn();
+        let vertices = vec![
+            PureVertex { position: [0.0, 0.0, 0.0], color: [1.0; 3] },
+            PureVertex { position: [1.0, 0.0, 0.0], color: [1.0; 3] },
+            PureVertex { position: [0.0, 1.0, 0.0], color: [1.0; 3] },
+        ];
+        world.insert(triangle, MeshComponent::new("TRIANGLE".to_owned(), device, vertices, vec![0, 1, 2], 0, 0));
+
+        for mesh in [square, square, triangle, square, triangle] {
+            world.spawn_bundle((TransformComponent::identity(), RenderableComponent { mesh, material: None }));
+        }
+
+        let mut system = RenderSystem::new();
+        system.set_indirect(true);
+        system.run(&mut world, 0.0);
+        system.prepare_batches(&world, device, &gpu.queue);
+
+        // The square's 24 vertices and indices go first, then the triangle's.
+        assert_eq!(system.indirect_args(), &[
+            DrawIndexedIndirectArgs { index_count: 24, instance_count: 3, first_index: 0, base_vertex: 0, first_instance: 0 },
+            DrawIndexedIndirectArgs { index_count: 3, instance_count: 2, first_index: 24, base_vertex: 24, first_instance: 3 },
+        ]);
+
+        let pipeline = test_scene_pipeline(device);
+        let material = MaterialComponent::from_bytes(device, &gpu.queue, &[255; 4], (1, 1), "Test Material");
+        let mut pass = RecordingPass::default();
+        let mut stats = RenderStats::default();
+        system.draw(&world, &pipeline, &material.bind_group, false, &mut pass, &mut stats);
+
+        if supports_multi_draw(device.features()) {
+            assert_eq!((pass.multi_draws, pass.draws), (1, 0));
+        } else {
+            assert_eq!((pass.multi_draws, pass.draws), (0, 2));
+        }
+        assert_eq!(stats.instances_drawn, 5);
+    }
+}
@@ -0,0 +1,104 @@
+use log::warn;
+
+/// The color target the scene gets drawn into. With multisampling on, that's an intermediate
+/// MSAA texture which is resolved onto the swapchain view at the end of the pass; otherwise it's
+/// just the swapchain view itself.
+pub struct RenderTarget {
+    sample_count: u32,
+    msaa_texture: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl RenderTarget {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            msaa_texture: Self::create_msaa_texture(device, config, sample_count),
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn has_msaa_texture(&self) -> bool {
+        self.msaa_texture.is_some()
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.msaa_texture = Self::create_msaa_texture(device, config, self.sample_count);
+    }
+
+    /// Swaps the MSAA texture for one with `sample_count` samples (or none at all, for 1).
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.resize(device, config);
+    }
+
+    /// The `(view, resolve_target)` pair for the scene's color attachment.
+    pub fn color_views<'a>(&'a self, surface_view: &'a wgpu::TextureView) -> (&'a wgpu::TextureView, Option<&'a wgpu::TextureView>) {
+        match &self.msaa_texture {
+            Some((_, msaa_view)) => (msaa_view, Some(surface_view)),
+            None => (surface_view, None),
+        }
+    }
+
+    /// WebGPU only guarantees 1x and 4x multisampling, and only for formats that allow it, so
+    /// anything else is knocked back down (with a warning) instead of failing validation later.
+    pub fn supported_sample_count(adapter: &wgpu::Adapter, formats: &[wgpu::TextureFormat], sample_count: u32) -> u32 {
+        if sample_count <= 1 {
+            return 1;
+        }
+
+        if sample_count != 4 {
+            warn!("A sample count of {} isn't supported, only 1 or 4. Using 4.", sample_count);
+        }
+
+        for format in formats {
+            if !adapter.get_texture_format_features(*format).flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE) {
+                warn!("{:?} can't be multisampled on this adapter. Disabling MSAA.", format);
+                return 1;
+            }
+        }
+
+        4
+    }
+
+    fn create_msaa_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<(wgpu::Texture, wgpu::TextureView)> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("msaa_texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Some((texture, view))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::{test_config, test_renderer};
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn msaa_texture_only_with_more_than_one_sample() {
+        let gpu = test_renderer();
+        let config = test_config(8, 8);
+
+        assert!(!RenderTarget::new(&gpu.device, &config, 1).has_msaa_texture());
+        assert!(RenderTarget::new(&gpu.device, &config, 4).has_msaa_texture());
+    }
+}
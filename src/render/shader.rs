@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+type PipelineBuilder = Box<dyn Fn(&wgpu::Device, &wgpu::ShaderModule) -> wgpu::RenderPipeline>;
+
+/// Rebuilds a pipeline whenever its WGSL source changes on disk. Nothing happens behind the main
+/// loop's back: changes only get picked up (and the new pipeline handed out) by `poll`.
+pub struct ShaderWatcher {
+    path: PathBuf,
+    build_pipeline: PipelineBuilder,
+    events: Receiver<notify::Result<notify::Event>>,
+    // Never read, but dropping it stops the watch.
+    _watcher: RecommendedWatcher,
+}
+
+impl ShaderWatcher {
+    /// `build_pipeline` gets the freshly compiled module and should build the same pipeline as
+    /// the one currently in use around it.
+    pub fn new<F>(path: impl AsRef<Path>, build_pipeline: F) -> Result<Self>
+    where
+        F: Fn(&wgpu::Device, &wgpu::ShaderModule) -> wgpu::RenderPipeline + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let (sender, events) = channel();
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiving end only goes away along with the watcher, so this can't really fail.
+            let _ = sender.send(event);
+        }).context("Unable to create a shader file watcher")?;
+
+        watcher.watch(&path, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Unable to watch shader {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            build_pipeline: Box::new(build_pipeline),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// A rebuilt pipeline if the shader changed since the last poll. If the new source doesn't
+    /// compile the error is logged and `None` returned, so whatever pipeline is in use keeps
+    /// working.
+    pub fn poll(&self, device: &wgpu::Device) -> Option<wgpu::RenderPipeline> {
+        if !self.drain_changes() {
+            return None;
+        }
+
+        let source = match std::fs::read_to_string(&self.path) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Unable to read shader {}: {}. Keeping the old pipeline.", self.path.display(), e);
+                return None;
+            },
+        };
+
+        // Without an error scope a bad shader goes to the device's error handler, which panics.
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: self.path.to_str(),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = (self.build_pipeline)(device, &module);
+
+        if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+            warn!("Shader {} failed to compile: {}. Keeping the old pipeline.", self.path.display(), e);
+            return None;
+        }
+
+        info!("Reloaded shader {}.", self.path.display());
+
+        Some(pipeline)
+    }
+
+    /// Whether the file changed since the last call, going by the events queued up since.
+    fn drain_changes(&self) -> bool {
+        let mut changed = false;
+
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if event.kind.is_modify() || event.kind.is_create() => changed = true,
+                Ok(_) => {},
+                Err(e) => warn!("Error watching shader {}: {}", self.path.display(), e),
+            }
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+    use crate::render::headless::{test_renderer, HeadlessRenderer};
+
+    const SHADER: &str = "
+    @vertex
+    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    @fragment
+    fn fs_main() -> @location(0) vec4<f32> {
+        return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+    }
+    ";
+
+    /// A fresh file in the temp directory, so parallel tests don't step on each other.
+    fn temp_shader(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sit-{}-{}.wgsl", name, std::process::id()));
+        std::fs::write(&path, SHADER).unwrap();
+        path
+    }
+
+    // The watcher's events arrive on another thread, so give them a moment.
+    fn wait_for(mut f: impl FnMut() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if f() {
+                return true;
+            }
+
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        false
+    }
+
+    fn build_pipeline(device: &wgpu::Device, module: &wgpu::ShaderModule) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Hot Reload Test Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: "fs_main",
+                targets: &[Some(HeadlessRenderer::FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    #[test]
+    fn editing_the_file_is_noticed() {
+        let path = temp_shader("noticed");
+        let watcher = ShaderWatcher::new(&path, build_pipeline).unwrap();
+        assert!(!watcher.drain_changes());
+
+        std::fs::write(&path, format!("{}\n// Edited\n", SHADER)).unwrap();
+
+        assert!(wait_for(|| watcher.drain_changes()));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn editing_the_file_rebuilds_the_pipeline() {
+        let gpu = test_renderer();
+        let path = temp_shader("rebuilds");
+        let builds = Rc::new(Cell::new(0));
+
+        let counter = builds.clone();
+        let watcher = ShaderWatcher::new(&path, move |device, module| {
+            counter.set(counter.get() + 1);
+            build_pipeline(device, module)
+        }).unwrap();
+
+        std::fs::write(&path, format!("{}\n// Edited\n", SHADER)).unwrap();
+
+        assert!(wait_for(|| watcher.poll(&gpu.device).is_some()));
+        assert_eq!(builds.get(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+}
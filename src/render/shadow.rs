@@ -0,0 +1,317 @@
+use cgmath::SquareMatrix;
+use wgpu::util::DeviceExt;
+
+use crate::vertex::{PureVertex, Vertex};
+use crate::ecs::world::World;
+use crate::ecs::component::light::DirectionalLight;
+use crate::ecs::component::material::MaterialComponent;
+use crate::ecs::component::mesh::MeshComponent;
+use crate::ecs::component::instance::{InstanceComponent, InstanceRaw};
+use crate::math::convert::mat4_to_array;
+
+pub const SHADOW_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+pub const DEFAULT_SHADOW_RESOLUTION: u32 = 2048;
+pub const DEFAULT_SHADOW_BIAS: f32 = 0.002;
+
+/// World resource tuning `ShadowPass`. Without one, the defaults are used.
+///
+/// `resolution` is the width (and height) of the shadow map. `bias` is how far, in the light's
+/// 0..1 depth, a surface has to be behind whatever's nearest the light before it counts as
+/// shadowed. Too little and surfaces pick up stripes of their own shadow ("acne"); too much and
+/// shadows come away from whatever casts them ("peter-panning"). Keep it as small as the acne
+/// allows, and shrink the light's bounds before growing it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub resolution: u32,
+    pub bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: DEFAULT_SHADOW_RESOLUTION,
+            bias: DEFAULT_SHADOW_BIAS,
+        }
+    }
+}
+
+/// Shared by the shadow pass (which only needs the matrix) and the shaders reading the map. The
+/// bias goes in `params.x`; the rest is padding for std140.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    light_view_proj: [[f32; 4]; 4],
+    params: [f32; 4],
+}
+
+impl ShadowUniform {
+    pub fn new(light_view_proj: cgmath::Matrix4<f32>, bias: f32) -> Self {
+        Self {
+            light_view_proj: mat4_to_array(light_view_proj),
+            params: [bias, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Renders the depth of every shadow caster, as seen from a `DirectionalLight`, into a shadow
+/// map the scene shaders then compare against to darken whatever's out of the light.
+///
+/// `bind_group` (laid out as `bind_group_layout`) holds the light's matrix, the map and a
+/// comparison sampler, and is what the scene pipeline expects at bind group 1. With no light the
+/// map is just cleared, leaving everything lit.
+pub struct ShadowPass {
+    pipeline: wgpu::RenderPipeline,
+    settings: ShadowSettings,
+    uniform_buffer: wgpu::Buffer,
+    // Just the uniform, for the pass itself (which can't have the map it's drawing into bound).
+    pass_bind_group: wgpu::BindGroup,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    map_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ShadowPass {
+    pub fn new(device: &wgpu::Device, settings: ShadowSettings) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/shadow_vertex.spv", "shadow_vertex");
+
+        let uniform_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let pass_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Pass Bind Group Layout"),
+            entries: &[uniform_entry(wgpu::ShaderStages::VERTEX)],
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                uniform_entry(wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&pass_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Depth only, so there's no fragment stage at all.
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[PureVertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // Both sides, so thin (or open) meshes still block the light.
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_MAP_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Anything but a zero matrix will do until there's a light, since the map starts out
+        // cleared (and so lit) anyway.
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[ShadowUniform::new(cgmath::Matrix4::identity(), settings.bias)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Pass Bind Group"),
+            layout: &pass_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Linear filtering on a comparison sampler blends the results of the four nearest
+        // comparisons, which softens the shadows' edges a little for free.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let map_view = Self::create_map(device, settings.resolution);
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &uniform_buffer, &map_view, &sampler);
+
+        Self {
+            pipeline,
+            settings,
+            uniform_buffer,
+            pass_bind_group,
+            bind_group_layout,
+            sampler,
+            map_view,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> ShadowSettings {
+        self.settings
+    }
+
+    /// Rebuilds the shadow map if the resolution changed. The bias takes effect on the next
+    /// `update`.
+    pub fn set_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        if settings.resolution != self.settings.resolution {
+            self.map_view = Self::create_map(device, settings.resolution);
+            self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.uniform_buffer,
+                &self.map_view, &self.sampler);
+        }
+
+        self.settings = settings;
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Points the pass (and the scene shaders) at `light`'s view-projection.
+    pub fn update(&self, queue: &wgpu::Queue, light: &DirectionalLight) {
+        let uniform = ShadowUniform::new(light.view_projection(), self.settings.bias);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Clears the map and draws every one of `casters` into it. Needs to go before the scene
+    /// pass that reads it (which is the order they're recorded in, if it's the same encoder).
+    pub fn render<'a>(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        casters: impl IntoIterator<Item = (&'a MeshComponent, &'a InstanceComponent)>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.map_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.pass_bind_group, &[]);
+
+        for (mesh, instances) in casters {
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, instances.instance_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..instances.instances.len() as u32);
+        }
+    }
+
+    /// Every entity in `world` that casts a shadow: those with a triangle list `MeshComponent`
+    /// and an `InstanceComponent`, unless their material is transparent.
+    pub fn shadow_casters(world: &World) -> Vec<(&MeshComponent, &InstanceComponent)> {
+        world.query::<InstanceComponent>()
+            .filter(|(entity, _)| !world.get::<MaterialComponent>(*entity).is_some_and(|material| material.transparent))
+            .filter_map(|(entity, instances)| Some((world.get::<MeshComponent>(entity)?, instances)))
+            .filter(|(mesh, _)| mesh.topology == wgpu::PrimitiveTopology::TriangleList)
+            .collect()
+    }
+
+    fn create_map(device: &wgpu::Device, resolution: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: wgpu::Extent3d {
+                width: resolution.max(1),
+                height: resolution.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_MAP_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        map_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(map_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::ecs::component::skybox::SkyboxComponent;
+use crate::render::depth::DepthTexture;
+
+/// Draws a `SkyboxComponent` as a single screen-covering triangle on the far plane. It needs to
+/// go before the main geometry in the pass: it tests with `LessEqual` against the cleared depth
+/// and never writes it, so everything drawn after it ends up in front.
+pub struct SkyboxPipeline {
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl SkyboxPipeline {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let vertex_shader = create_spv_shader!(device, "../../target/skybox_vertex.spv", "skybox_vertex");
+        let fragment_shader = create_spv_shader!(device, "../../target/skybox_fragment.spv", "skybox_fragment");
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[
+                &SkyboxComponent::bind_group_layout(device),
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader,
+                entry_point: "main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                ..DepthTexture::read_only_depth_stencil_state()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+        }
+    }
+
+    pub fn draw<'a>(&'a self, skybox: &'a SkyboxComponent, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &skybox.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
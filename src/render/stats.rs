@@ -0,0 +1,65 @@
+/// World resource counting what the last frame drew, for working out how expensive a scene is.
+/// Reset at the start of every frame, then added to as each batch is drawn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub draw_calls: usize,
+    /// Summed over draw calls, so a mesh drawn twice (e.g. with the depth prepass on) counts twice.
+    pub instances_drawn: usize,
+    /// Only triangle topologies count; points and lines add nothing.
+    pub triangles: usize,
+    /// Instances skipped by CPU culling (`InstanceComponent::cull`), counted by whoever culls.
+    pub culled_instances: usize,
+    /// Milliseconds per timed pass (see `GpuTimer`), which lag a frame or so behind the rest.
+    /// `None` if the device can't do timestamp queries.
+    pub pass_times: Option<Vec<(&'static str, f32)>>,
+}
+
+impl RenderStats {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// One indexed draw of `index_count` indices, `instance_count` times.
+    pub fn record_draw(&mut self, topology: wgpu::PrimitiveTopology, index_count: u32, instance_count: u32) {
+        self.draw_calls += 1;
+        self.record_instances(topology, index_count, instance_count);
+    }
+
+    /// Like `record_draw`, without counting a draw call. For the draws packed into a multi draw,
+    /// which only count once between them.
+    pub fn record_instances(&mut self, topology: wgpu::PrimitiveTopology, index_count: u32, instance_count: u32) {
+        let per_instance = match topology {
+            wgpu::PrimitiveTopology::TriangleList => index_count / 3,
+            wgpu::PrimitiveTopology::TriangleStrip => index_count.saturating_sub(2),
+            _ => 0,
+        };
+
+        self.instances_drawn += instance_count as usize;
+        self.triangles += per_instance as usize * instance_count as usize;
+    }
+
+    pub fn record_culled(&mut self, culled: usize) {
+        self.culled_instances += culled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_instances_of_36_indices_are_24_triangles() {
+        let mut stats = RenderStats::default();
+        stats.record_draw(wgpu::PrimitiveTopology::TriangleList, 36, 2);
+
+        assert_eq!(stats.draw_calls, 1);
+        assert_eq!(stats.instances_drawn, 2);
+        assert_eq!(stats.triangles, 24);
+
+        stats.record_draw(wgpu::PrimitiveTopology::LineList, 36, 2);
+        assert_eq!(stats.triangles, 24);
+
+        stats.reset();
+        assert_eq!(stats, RenderStats::default());
+    }
+}
@@ -0,0 +1,68 @@
+use log::warn;
+
+/// The first sRGB format in `formats` (what `Surface::get_supported_formats` hands back, best
+/// first), or failing that just the first one. `None` if there aren't any.
+///
+/// On an sRGB swapchain the hardware encodes the linear colors we write, which is what we want.
+/// Anything else shows them as is, which looks too dark, so the scene's fragment shader has to
+/// encode them itself (see `needs_manual_gamma`).
+pub fn preferred_surface_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    formats.iter().copied().find(|format| is_srgb(*format)).or_else(|| formats.first().copied())
+}
+
+pub fn is_srgb(format: wgpu::TextureFormat) -> bool {
+    format.describe().srgb
+}
+
+pub fn needs_manual_gamma(format: wgpu::TextureFormat) -> bool {
+    !is_srgb(format)
+}
+
+/// How frames are handed to the display.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Vsync: frames queue up and go out one per refresh. Always supported.
+    #[default]
+    Fifo,
+    /// Vsync without the queueing: a newer frame replaces a waiting one, so there's no tearing
+    /// and less latency, but frames can be thrown away.
+    Mailbox,
+    /// Frames go out as soon as they're done, uncapped, tearing and all.
+    Immediate,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+/// `requested` if it's among `supported` (what `Surface::get_supported_modes` hands back),
+/// otherwise `Fifo` (with a warning), which every surface has to support.
+pub fn supported_present_mode(requested: PresentMode, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    let mode = requested.into();
+
+    if supported.contains(&mode) {
+        mode
+    } else {
+        warn!("{:?} presenting isn't supported by this surface. Using Fifo.", requested);
+        wgpu::PresentMode::Fifo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_present_mode_falls_back_to_fifo() {
+        let supported = [wgpu::PresentMode::Fifo, wgpu::PresentMode::Immediate];
+
+        assert_eq!(supported_present_mode(PresentMode::Mailbox, &supported), wgpu::PresentMode::Fifo);
+        assert_eq!(supported_present_mode(PresentMode::Immediate, &supported), wgpu::PresentMode::Immediate);
+    }
+}
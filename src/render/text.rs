@@ -0,0 +1,133 @@
+use log::warn;
+use wgpu_glyph::{ab_glyph::FontArc, GlyphBrush, GlyphBrushBuilder, Section, Text};
+
+use crate::error::WafError;
+
+// Plenty for a screenful of HUD text; the belt grabs more chunks if it ever needs them.
+const STAGING_CHUNK_SIZE: wgpu::BufferAddress = 1024;
+
+/// A string waiting for the next `TextOverlay::draw`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueuedText {
+    /// In pixels, from the top left corner of the window.
+    pub position: (f32, f32),
+    /// Height in pixels.
+    pub size: f32,
+    pub color: [f32; 4],
+    pub text: String,
+}
+
+/// Screen space text (HUDs, `RenderStats`, ...) drawn over whatever has already been rendered,
+/// with `wgpu_glyph`. Text is queued each frame with `queue_text` and drawn (then forgotten) by
+/// `draw`, which should come after the scene, and `after_submit` once the frame is submitted.
+///
+/// There's no font built in, so one has to be handed to `new` (TTF or OTF bytes).
+pub struct TextOverlay {
+    brush: GlyphBrush<()>,
+    staging_belt: wgpu::util::StagingBelt,
+    queued: Vec<QueuedText>,
+    width: u32,
+    height: u32,
+}
+
+impl TextOverlay {
+    /// `format` is the format of the view it'll draw into, and `width` and `height` its size.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, font: Vec<u8>, width: u32, height: u32) -> Result<Self, WafError> {
+        let font = FontArc::try_from_vec(font)
+            .map_err(|e| WafError::DecodeFailed(format!("Unable to load overlay font: {}", e)))?;
+
+        Ok(Self {
+            brush: GlyphBrushBuilder::using_font(font).build(device, format),
+            staging_belt: wgpu::util::StagingBelt::new(STAGING_CHUNK_SIZE),
+            queued: vec![],
+            width,
+            height,
+        })
+    }
+
+    pub fn queue_text(&mut self, position: (f32, f32), size: f32, color: [f32; 4], text: &str) {
+        self.queued.push(QueuedText {
+            position,
+            size,
+            color,
+            text: text.to_owned(),
+        });
+    }
+
+    /// One section per `queue_text` since the last `draw`.
+    pub fn queued(&self) -> &[QueuedText] {
+        &self.queued
+    }
+
+    /// Keeps text the same size in pixels (instead of stretching with the window) by rebuilding
+    /// the projection for the new size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Draws everything queued into `view` in a pass of its own, keeping what's already there.
+    pub fn draw(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        for queued in &self.queued {
+            self.brush.queue(Section {
+                screen_position: queued.position,
+                text: vec![Text::new(&queued.text).with_scale(queued.size).with_color(queued.color)],
+                ..Section::default()
+            });
+        }
+
+        self.queued.clear();
+
+        if let Err(e) = self.brush.draw_queued(device, &mut self.staging_belt, encoder, view, self.width, self.height) {
+            warn!("Unable to draw the text overlay: {}", e);
+        }
+
+        self.staging_belt.finish();
+    }
+
+    /// Frees up the staging buffers `draw` wrote through, once the frame they were used in has
+    /// been submitted.
+    pub fn after_submit(&mut self) {
+        self.staging_belt.recall();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::{test_renderer, HeadlessRenderer};
+
+    /// There's no font in the repo, so borrow the system's (`SIT_TEST_FONT` wins if it's set).
+    fn test_font() -> Option<Vec<u8>> {
+        let candidates = [
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+            "/System/Library/Fonts/Supplemental/Arial.ttf",
+            "C:\\Windows\\Fonts\\arial.ttf",
+        ];
+
+        std::env::var("SIT_TEST_FONT").ok().into_iter()
+            .chain(candidates.iter().map(|path| path.to_string()))
+            .find_map(|path| std::fs::read(path).ok())
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn two_strings_queue_two_sections_until_drawn() {
+        let gpu = test_renderer();
+        let Some(font) = test_font() else { return };
+        let (width, height) = gpu.size();
+        let mut overlay = TextOverlay::new(&gpu.device, HeadlessRenderer::FORMAT, font, width, height).unwrap();
+
+        overlay.queue_text((0.0, 0.0), 16.0, [1.0; 4], "Draw calls: 1");
+        overlay.queue_text((0.0, 20.0), 16.0, [1.0; 4], "Triangles: 12");
+        assert_eq!(overlay.queued().len(), 2);
+        assert_eq!(overlay.queued()[1].text, "Triangles: 12");
+
+        let mut encoder = gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Text Test Encoder") });
+        overlay.draw(&gpu.device, &mut encoder, &gpu.view);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+        overlay.after_submit();
+
+        assert!(overlay.queued().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use std::sync::{Arc, atomic::{AtomicU8, Ordering}};
+use log::warn;
+
+pub const DEFAULT_MAX_TIMED_PASSES: u32 = 16;
+
+// Where a readback buffer's `map_async` has got to.
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// Times passes on the GPU with timestamp queries: `begin_pass` and `end_pass` around each pass,
+/// `resolve` before finishing the encoder and `after_submit` once it's submitted.
+///
+/// Reading the timestamps back waits for the GPU to get through the frame, so instead of stalling
+/// on it, each frame's are mapped in the background and picked up a frame later. `pass_times`
+/// therefore always lags a frame or so behind. Without `Features::TIMESTAMP_QUERY` every call is
+/// a no-op and `pass_times` is `None`.
+pub struct GpuTimer {
+    timestamps: Option<Timestamps>,
+    // This frame's passes, in the order they began.
+    names: Vec<&'static str>,
+    open: bool,
+    pass_times: Vec<(&'static str, f32)>,
+}
+
+struct Timestamps {
+    query_set: wgpu::QuerySet,
+    // One being written this frame, one (maybe) still being mapped from the last.
+    readbacks: [Readback; 2],
+    current: usize,
+    // Nanoseconds per tick.
+    period: f32,
+    max_passes: u32,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    names: Vec<&'static str>,
+    // `None` while the buffer is free to copy into.
+    state: Option<Arc<AtomicU8>>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::with_max_passes(device, queue, DEFAULT_MAX_TIMED_PASSES)
+    }
+
+    /// Passes past the first `max_passes` in a frame just don't get timed.
+    pub fn with_max_passes(device: &wgpu::Device, queue: &wgpu::Queue, max_passes: u32) -> Self {
+        let timestamps = device.features().contains(wgpu::Features::TIMESTAMP_QUERY).then(|| {
+            let size = (max_passes * 2) as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+            let readback = || Readback {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                names: vec![],
+                state: None,
+            };
+
+            Timestamps {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("Timestamp Query Set"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: max_passes * 2,
+                }),
+                readbacks: [readback(), readback()],
+                current: 0,
+                period: queue.get_timestamp_period(),
+                max_passes,
+            }
+        });
+
+        Self {
+            timestamps,
+            names: vec![],
+            open: false,
+            pass_times: vec![],
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    /// Needs to go outside the pass (before `begin_render_pass`), as does `end_pass`.
+    pub fn begin_pass(&mut self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        let timestamps = match &self.timestamps {
+            Some(timestamps) => timestamps,
+            None => return,
+        };
+
+        if self.open {
+            warn!("GPU timer pass {} began before the last one ended. Not timing it.", name);
+            return;
+        }
+
+        if self.names.len() as u32 >= timestamps.max_passes {
+            return;
+        }
+
+        encoder.write_timestamp(&timestamps.query_set, self.names.len() as u32 * 2);
+        self.names.push(name);
+        self.open = true;
+    }
+
+    pub fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let (Some(timestamps), true) = (&self.timestamps, self.open) {
+            encoder.write_timestamp(&timestamps.query_set, self.names.len() as u32 * 2 - 1);
+            self.open = false;
+        }
+    }
+
+    /// Resolves this frame's timestamps into a buffer for reading back. If the last frame that
+    /// used that buffer hasn't been picked up yet, this frame's are dropped rather than waited on.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let names = std::mem::take(&mut self.names);
+        self.open = false;
+
+        let timestamps = match &mut self.timestamps {
+            Some(timestamps) if !names.is_empty() => timestamps,
+            _ => return,
+        };
+
+        let readback = &mut timestamps.readbacks[timestamps.current];
+        if readback.state.is_some() || !readback.names.is_empty() {
+            return;
+        }
+
+        let count = names.len() as u32 * 2;
+        // Query sets resolve into anything that can be copied to, mappable buffers included.
+        encoder.resolve_query_set(&timestamps.query_set, 0..count, &readback.buffer, 0);
+        readback.names = names;
+    }
+
+    /// Starts mapping this frame's timestamps, and picks up the last frame's if they're mapped.
+    pub fn after_submit(&mut self, device: &wgpu::Device) {
+        let timestamps = match &mut self.timestamps {
+            Some(timestamps) => timestamps,
+            None => return,
+        };
+
+        let readback = &mut timestamps.readbacks[timestamps.current];
+        if !readback.names.is_empty() && readback.state.is_none() {
+            let state = Arc::new(AtomicU8::new(MAP_PENDING));
+            let callback_state = Arc::clone(&state);
+
+            readback.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                callback_state.store(if result.is_ok() { MAP_DONE } else { MAP_FAILED }, Ordering::Release);
+            });
+
+            readback.state = Some(state);
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+
+        let period = timestamps.period;
+        timestamps.current = 1 - timestamps.current;
+        let previous = &mut timestamps.readbacks[timestamps.current];
+
+        let state = match &previous.state {
+            Some(state) => state.load(Ordering::Acquire),
+            None => return,
+        };
+
+        match state {
+            MAP_PENDING => return,
+            MAP_DONE => {
+                let ticks = previous.buffer.slice(..).get_mapped_range().chunks_exact(wgpu::QUERY_SIZE as usize)
+                    .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+                    .collect::<Vec<_>>();
+
+                self.pass_times = previous.names.iter().zip(ticks.chunks_exact(2))
+                    .map(|(&name, span)| (name, span[1].saturating_sub(span[0]) as f32 * period / 1_000_000.0))
+                    .collect();
+
+                previous.buffer.unmap();
+            },
+            _ => warn!("Unable to map the GPU timestamps. Skipping a frame of pass times."),
+        }
+
+        previous.names.clear();
+        previous.state = None;
+    }
+
+    /// How long each pass took, in milliseconds, as of the latest frame that's been read back.
+    /// `None` if the device can't do timestamp queries.
+    pub fn pass_times(&self) -> Option<&[(&'static str, f32)]> {
+        self.timestamps.as_ref().map(|_| self.pass_times.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::headless::test_renderer;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn named_pass_records_a_duration() {
+        let gpu = test_renderer();
+        let (device, queue) = (&gpu.device, &gpu.queue);
+
+        let mut timer = GpuTimer::new(device, queue);
+        if !timer.is_supported() {
+            assert!(timer.pass_times().is_none());
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Timer Test Encoder") });
+        timer.begin_pass(&mut encoder, "Test Pass");
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Timer Test Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &gpu.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        timer.end_pass(&mut encoder);
+        timer.resolve(&mut encoder);
+        queue.submit(std::iter::once(encoder.finish()));
+        timer.after_submit(device);
+
+        // The times are picked up a frame late, once the mapping's done.
+        device.poll(wgpu::Maintain::Wait);
+        queue.submit(std::iter::empty());
+        timer.after_submit(device);
+
+        let times = timer.pass_times().unwrap();
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].0, "Test Pass");
+        assert!(times[0].1 >= 0.0);
+    }
+}
@@ -5,14 +5,34 @@ use winit::{
     event::{KeyboardInput, WindowEvent, MouseButton},
 };
 
-use crate::vertex::{PureVertex, Vertex};
 use crate::shader::create_spv_shader;
 use crate::camera::{Camera, CameraUniform, CameraController, Projection};
-use crate::texture::Texture;
+use crate::render::depth::DepthTexture;
+use crate::render::pipeline::{ScenePipeline, DepthPrepass};
+use crate::render::render_target::RenderTarget;
+use crate::render::clear_color::ClearColor;
+use crate::render::skybox::SkyboxPipeline;
+use crate::render::error_scope::pop_error_scope;
+use crate::render::debug_lines::{DebugLines, DebugLinePipeline};
+use crate::render::stats::RenderStats;
+use crate::render::timing::GpuTimer;
+use crate::render::surface::{preferred_surface_format, needs_manual_gamma, supported_present_mode, PresentMode};
+use crate::render::fxaa::{AntiAliasing, FxaaPass};
+use crate::render::shadow::{ShadowPass, ShadowSettings};
+use crate::render::render_system::RenderSystem;
+use crate::render::indirect::MULTI_DRAW_FEATURES;
 use crate::ecs::{
     scene::Scene,
+    world::World,
+    system::{System, Scheduler},
+    motion::MotionSystem,
+    time::Time,
+    component::camera::CameraComponent,
     component::mesh::MeshComponent,
-    component::instance::{InstanceRaw, InstanceComponent},
+    component::instance::InstanceComponent,
+    component::material::MaterialComponent,
+    component::skybox::SkyboxComponent,
+    component::light::DirectionalLight,
 };
 
 #[cfg(target_os = "macos")]
@@ -20,11 +40,19 @@ pub const GRAPHICS_BACKEND: wgpu::Backends = wgpu::Backends::METAL;
 #[cfg(not(target_os = "macos"))]
 pub const GRAPHICS_BACKEND: wgpu::Backends = wgpu::Backends::VULKAN;
 pub const DEVICE_FEATURES: wgpu::Features = wgpu::Features::POLYGON_MODE_LINE;
+/// Asked for only if the adapter has them. Without `MULTI_DRAW_FEATURES`, indirect batches fall
+/// back to one draw each (see `RenderSystem::set_indirect`).
+pub const OPTIONAL_DEVICE_FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY
+    .union(MULTI_DRAW_FEATURES);
 pub const DRAW_POLYGON_MODE: wgpu::PolygonMode = wgpu::PolygonMode::Fill;
+pub const MSAA_SAMPLE_COUNT: u32 = 4;
+/// What the swapchain starts out presenting with (see `State::set_present_mode`).
+pub const PRESENT_MODE: PresentMode = PresentMode::Fifo;
 
 pub struct State {
     // Rendering
     pub surface: wgpu::Surface,
+    pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
@@ -36,12 +64,31 @@ pub struct State {
     pub noise_storage_buffer: wgpu::Buffer,
     pub projection: Projection,
     pub camera_controller: CameraController,
-    pub depth_texture: Texture,
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub depth_texture: DepthTexture,
+    pub render_target: RenderTarget,
+    pub render_pipeline: ScenePipeline,
+    pub skybox_pipeline: SkyboxPipeline,
+    pub debug_lines: DebugLines,
+    pub gpu_timer: GpuTimer,
+    pub debug_line_pipeline: DebugLinePipeline,
+    /// What the targets and pipelines are currently built for.
+    pub anti_aliasing: AntiAliasing,
+    /// Only there with `AntiAliasing::Fxaa`.
+    pub fxaa: Option<FxaaPass>,
+    pub shadow_pass: ShadowPass,
+    /// The world's systems, run by `update` before anything gets drawn. `MotionSystem` is
+    /// registered as a fixed system, so it steps at the world's `FixedTimestep`; add your own
+    /// systems here too.
+    pub scheduler: Scheduler,
+    /// Draws the world's entities, after the active scene's meshes.
+    pub render_system: RenderSystem,
+    /// Bound for whatever `render_system` draws without a material of its own.
+    pub default_material: MaterialComponent,
 
     // Scenes
     pub scenes: Vec<Scene>,
     pub active_scene_index: usize,
+    pub world: World,
 }
 
 impl State {
@@ -69,7 +116,7 @@ impl State {
 
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
-                features: DEVICE_FEATURES,
+                features: DEVICE_FEATURES | (adapter.features() & OPTIONAL_DEVICE_FEATURES),
                 limits: wgpu::Limits::default(),
                 label: None,
             },
@@ -78,16 +125,21 @@ impl State {
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format: preferred_surface_format(&surface.get_supported_formats(&adapter))
+                .expect("The surface isn't compatible with the adapter!"),
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: supported_present_mode(PRESENT_MODE, &surface.get_supported_modes(&adapter)),
         };
 
         surface.configure(&device, &config);
 
         let vertex_shader = create_spv_shader!(device, "../target/vertex.spv", "vertex");
-        let fragment_shader = create_spv_shader!(device, "../target/fragment.spv", "fragment");
+        let fragment_shader = if needs_manual_gamma(config.format) {
+            create_spv_shader!(device, "../target/fragment_gamma.spv", "fragment_gamma")
+        } else {
+            create_spv_shader!(device, "../target/fragment.spv", "fragment")
+        };
 
         let camera = Camera::new((0.0, 3.0, 6.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
         let projection = Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 5000.0);
@@ -140,59 +192,37 @@ impl State {
             mapped_at_creation: false,
         });
 
-        let depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
+        let sample_count = RenderTarget::supported_sample_count(&adapter, &[config.format, DepthTexture::FORMAT],
+            MSAA_SAMPLE_COUNT);
+        let depth_texture = DepthTexture::create_multisampled(&device, &config, sample_count);
+        let render_target = RenderTarget::new(&device, &config, sample_count);
+
+        let shadow_pass = ShadowPass::new(&device, ShadowSettings::default());
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
                     &camera_bind_group_layout,
+                    shadow_pass.bind_group_layout(),
+                    &MaterialComponent::bind_group_layout(&device),
                 ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &vertex_shader,
-                entry_point: "main",
-                buffers: &[
-                    PureVertex::desc(), InstanceRaw::desc(),
-                ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &fragment_shader,
-                entry_point: "main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: DRAW_POLYGON_MODE,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let render_pipeline = ScenePipeline::new(&device, render_pipeline_layout, vertex_shader, fragment_shader,
+            config.format, DRAW_POLYGON_MODE, sample_count);
+        let skybox_pipeline = SkyboxPipeline::new(&device, config.format, sample_count);
+        let debug_line_pipeline = DebugLinePipeline::new(&device, config.format, sample_count);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        let default_material = MaterialComponent::from_bytes(&device, &queue, &[255; 4], (1, 1), "Default Material");
+
+        let mut world = World::new();
+        world.insert_resource(Time::new());
+        world.insert_resource(RenderStats::default());
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_fixed_system(MotionSystem);
 
         let scene = Scene::new();
         let scenes = vec![scene];
@@ -200,6 +230,7 @@ impl State {
 
         Self {
             surface,
+            adapter,
             device,
             queue,
             config,
@@ -212,18 +243,57 @@ impl State {
             projection,
             camera_controller,
             depth_texture,
+            render_target,
             render_pipeline,
+            skybox_pipeline,
+            debug_lines: DebugLines::new(),
+            gpu_timer,
+            debug_line_pipeline,
+            anti_aliasing: AntiAliasing::default(),
+            fxaa: None,
+            shadow_pass,
+            scheduler,
+            render_system: RenderSystem::new(),
+            default_material,
             active_scene_index,
             scenes,
+            world,
         }
     }
 
+    /// Preferably sRGB (see `preferred_surface_format`).
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Reconfigures the surface to present with `mode`, or with `Fifo` if the surface can't. Returns
+    /// the mode that ended up being used.
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> wgpu::PresentMode {
+        self.config.present_mode = supported_present_mode(mode, &self.surface.get_supported_modes(&self.adapter));
+        self.surface.configure(&self.device, &self.config);
+
+        self.config.present_mode
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.depth_texture = Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+            self.depth_texture.resize(&self.device, &self.config);
+            self.render_target.resize(&self.device, &self.config);
+
+            if let Some(fxaa) = &mut self.fxaa {
+                fxaa.resize(&self.device, new_size.width, new_size.height);
+            }
+
+            if let Some(camera) = self.world.resource_mut::<CameraComponent>() {
+                camera.resize(new_size.width, new_size.height);
+            }
+
+            for (_, camera) in self.world.query_mut::<CameraComponent>() {
+                camera.resize(new_size.width, new_size.height);
+            }
             self.projection.resize(new_size.width, new_size.height);
             self.surface.configure(&self.device, &self.config);
         }
@@ -257,7 +327,21 @@ impl State {
         }
     }
 
-    pub fn update(&mut self, dt: std::time::Duration) {
+    /// Ticks the `Time` resource first, so everything after it sees this frame's delta, then runs
+    /// `scheduler` (fixed steps included), and only then uploads cameras and works out what to
+    /// draw.
+    pub fn update(&mut self) {
+        let dt = match self.world.resource_mut::<Time>() {
+            Some(time) => {
+                time.tick();
+                time.delta()
+            },
+            None => std::time::Duration::ZERO,
+        };
+
+        // Before the cameras go up, in case a system moved one.
+        self.scheduler.update(&mut self.world, dt.as_secs_f32());
+
         self.camera_controller.update_camera(&mut self.camera, dt);
         self.camera_uniform
             .update_view_proj(&self.camera, &self.projection);
@@ -266,14 +350,110 @@ impl State {
             0,
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
+
+        if let Some(camera) = self.world.resource_mut::<CameraComponent>().filter(|camera| camera.dirty) {
+            camera.update_buffer(&self.queue);
+        }
+
+        for (_, camera) in self.world.query_mut::<CameraComponent>().filter(|(_, camera)| camera.dirty) {
+            camera.update_buffer(&self.queue);
+        }
+
+        if let Some(skybox) = self.skybox() {
+            skybox.update_buffer(&self.queue, self.camera.calc_matrix(), self.projection.calc_matrix());
+        }
+
+        self.debug_lines.run(&mut self.world, dt.as_secs_f32());
+        self.debug_lines.prepare(&self.device, &self.queue);
+
+        self.render_system.run(&mut self.world, dt.as_secs_f32());
+        self.render_system.prepare_batches(&self.world, &self.device, &self.queue);
+    }
+
+    /// Like the skybox, a light stored as a resource wins over one on an entity.
+    fn directional_light(&self) -> Option<&DirectionalLight> {
+        self.world.resource::<DirectionalLight>()
+            .or_else(|| self.world.query::<DirectionalLight>().next().map(|(_, light)| light))
+    }
+
+    /// A skybox stored as a resource wins over one on an entity.
+    fn skybox(&self) -> Option<&SkyboxComponent> {
+        self.world.resource::<SkyboxComponent>()
+            .or_else(|| self.world.query::<SkyboxComponent>().next().map(|(_, skybox)| skybox))
+    }
+
+    /// Rebuilds whatever `anti_aliasing` needs changed: the targets and pipelines for a new
+    /// sample count, and the FXAA pass.
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        let sample_count = RenderTarget::supported_sample_count(&self.adapter, &[self.config.format, DepthTexture::FORMAT],
+            anti_aliasing.sample_count());
+
+        if sample_count != self.render_target.sample_count() {
+            self.render_target.set_sample_count(&self.device, &self.config, sample_count);
+            self.depth_texture = DepthTexture::create_multisampled(&self.device, &self.config, sample_count);
+            self.render_pipeline.set_sample_count(&self.device, sample_count);
+            self.skybox_pipeline = SkyboxPipeline::new(&self.device, self.config.format, sample_count);
+            self.debug_line_pipeline = DebugLinePipeline::new(&self.device, self.config.format, sample_count);
+        }
+
+        self.fxaa = match self.fxaa.take() {
+            Some(fxaa) if anti_aliasing.uses_post_pass() => Some(fxaa),
+            _ if anti_aliasing.uses_post_pass() => Some(FxaaPass::new(&self.device, self.config.format,
+                self.config.width, self.config.height)),
+            _ => None,
+        };
+
+        self.anti_aliasing = anti_aliasing;
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let anti_aliasing = self.world.resource::<AntiAliasing>().copied().unwrap_or_default();
+        if anti_aliasing != self.anti_aliasing {
+            self.set_anti_aliasing(anti_aliasing);
+        }
+
+        let depth_prepass = self.world.resource::<DepthPrepass>().is_some_and(|prepass| prepass.0);
+        self.render_pipeline.set_depth_prepass(&self.device, depth_prepass);
+
+        for component in &self.scenes[self.active_scene_index].components {
+            if let Some(mesh) = component.as_any().downcast_ref::<MeshComponent>() {
+                self.render_pipeline.prepare_variant(&self.device, mesh.topology, mesh.cull_mode);
+            }
+        }
+
+        for (_, mesh) in self.world.query::<MeshComponent>() {
+            self.render_pipeline.prepare_variant(&self.device, mesh.topology, mesh.cull_mode);
+        }
+
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
+        // With FXAA on, the scene goes offscreen first and only reaches `view` through the FXAA pass.
+        let scene_view = self.fxaa.as_ref().map_or(&view, FxaaPass::input_view);
+        let (color_view, resolve_target) = self.render_target.color_views(scene_view);
+        let clear_color = self.world.resource::<ClearColor>().copied().unwrap_or_default().for_format(self.config.format);
+        let mut stats = RenderStats::default();
+
+        // Pass errors only get reported once the pass ends, so the scope has to cover all of it.
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let shadow_settings = self.world.resource::<ShadowSettings>().copied().unwrap_or_default();
+        self.shadow_pass.set_settings(&self.device, shadow_settings);
+
+        // Without a light the map is still cleared, so nothing ends up shadowed.
+        let light = self.directional_light().copied();
+        if let Some(light) = &light {
+            self.shadow_pass.update(&self.queue, light);
+        }
+
+        self.gpu_timer.begin_pass(&mut encoder, "Shadows");
+        let casters = light.map(|_| self.shadow_casters()).unwrap_or_default();
+        self.shadow_pass.render(&mut encoder, casters);
+        self.gpu_timer.end_pass(&mut encoder);
+
+        self.gpu_timer.begin_pass(&mut encoder, "Scene");
 
         // `begin_render_pass()' borrows encoder mutably (aka `&mut self'). We can't call
         // `encoder.finish()' until we release that mutable borrow, hence the block here.
@@ -283,17 +463,10 @@ impl State {
                 color_attachments: &[
                     // This is what @location(0) in the fragment shader targets
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
+                        view: color_view,
+                        resolve_target,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(
-                                wgpu::Color {
-                                    r: 0.0,
-                                    g: 0.0,
-                                    b: 0.0,
-                                    a: 1.0,
-                                }
-                            ),
+                            load: wgpu::LoadOp::Clear(clear_color),
                             store: true,
                         }
                     })
@@ -308,73 +481,132 @@ impl State {
                 }),
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            if let Some(skybox) = self.skybox() {
+                self.skybox_pipeline.draw(skybox, &mut render_pass);
+            }
 
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            // With the prepass on, the scene goes down twice: depth first, then color.
+            if self.render_pipeline.depth_prepass() {
+                self.draw_scene(&mut render_pass, true, &mut stats);
+            }
 
-            for component in &self.get_active_scene().components {
-                let mesh_component = match component.as_any().downcast_ref::<MeshComponent>() {
-                    Some(c) => c,
-                    None => continue,
-                };
+            self.draw_scene(&mut render_pass, false, &mut stats);
+            self.render_system.draw_transparent(&self.world, &self.render_pipeline, &self.default_material.bind_group,
+                &mut render_pass, &mut stats);
 
-                render_pass.set_vertex_buffer(0, mesh_component.vertex_buffer.slice(..));
+            self.debug_line_pipeline.draw(&self.debug_lines, &self.camera_bind_group, &mut render_pass);
+        }
 
-                let parent_object_components = &self.get_active_scene().objects.get(mesh_component.parent_index)
-                    .expect("Invalid component parent index!").components;
+        self.gpu_timer.end_pass(&mut encoder);
 
-                /* for sub_component_index in 0..parent_object_components.len() {
-                        let sub_component = self.get_active_scene().components.get(sub_component_index)
-                            .expect("Invalid sub component index!");
-                        match sub_component.as_any().downcast_ref::<InstanceComponent>() {
-                            Some(instance_component) => {
-                                ;
-                            },
-                            None => {
-                                error!("A mesh component also requires an instance component. Not rendering!");
-                                continue;
-                            },
-                        }
-                } */
+        if let Some(fxaa) = &self.fxaa {
+            self.gpu_timer.begin_pass(&mut encoder, "FXAA");
+            fxaa.draw(&mut encoder, &view);
+            self.gpu_timer.end_pass(&mut encoder);
+        }
 
-                match &self.get_active_scene().components.get(mesh_component.instance_component_index)
-                    .expect("Invalid instance component index (pointed to by mesh)!").as_any().downcast_ref::<InstanceComponent>() {
-                        Some(instance_component) => render_pass.set_vertex_buffer(1, instance_component.instance_buffer.slice(..)),
-                        None => {
-                            warn!("Instance component pointed to by mesh component (via index {}) isn't actually an instance. Not rendering!",
-                                mesh_component.instance_component_index);
+        self.gpu_timer.resolve(&mut encoder);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        pop_error_scope(&self.device, "Scene rendering");
+        self.gpu_timer.after_submit(&self.device);
+
+        stats.pass_times = self.gpu_timer.pass_times().map(<[_]>::to_vec);
+        self.world.insert_resource(stats);
+        output.present();
 
+        Ok(())
+    }
+
+    /// Records every mesh of the active scene, each with the pipeline variant matching its
+    /// primitive state (or its prepass twin, with `prepass`), then the world's opaque entities
+    /// through `render_system`, adding each draw to `stats`.
+    fn draw_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, prepass: bool, stats: &mut RenderStats) {
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, self.shadow_pass.bind_group(), &[]);
+        render_pass.set_bind_group(2, &self.default_material.bind_group, &[]);
+
+        for component in &self.get_active_scene().components {
+            let mesh_component = match component.as_any().downcast_ref::<MeshComponent>() {
+                Some(c) => c,
+                None => continue,
+            };
+
+            match self.render_pipeline.variant(mesh_component.topology, mesh_component.cull_mode, prepass) {
+                Some(pipeline) => render_pass.set_pipeline(pipeline),
+                None => continue,
+            }
+
+            render_pass.set_vertex_buffer(0, mesh_component.vertex_buffer.slice(..));
+
+            let parent_object_components = &self.get_active_scene().objects.get(mesh_component.parent_index)
+                .expect("Invalid component parent index!").components;
+
+            /* for sub_component_index in 0..parent_object_components.len() {
+                    let sub_component = self.get_active_scene().components.get(sub_component_index)
+                        .expect("Invalid sub component index!");
+                    match sub_component.as_any().downcast_ref::<InstanceComponent>() {
+                        Some(instance_component) => {
+                            ;
+                        },
+                        None => {
+                            error!("A mesh component also requires an instance component. Not rendering!");
                             continue;
-                        }
+                        },
                     }
+            } */
 
-                render_pass.set_index_buffer(mesh_component.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-
-                for sub_component_index in 0..parent_object_components.len() {
-                        let sub_component = self.get_active_scene().components.get(sub_component_index)
-                            .expect("Invalid sub component index!");
-                        match sub_component.as_any().downcast_ref::<InstanceComponent>() {
-                            Some(instance_component) => {
-                                render_pass.draw_indexed(0..mesh_component.num_indices, 0, 0..instance_component.instances.len() as _);
-                            },
-                            None => {
-                                // Regular drawing
-                                render_pass.draw_indexed(0..mesh_component.num_indices, 0, 0..1);
-                            },
-                        }
+            match &self.get_active_scene().components.get(mesh_component.instance_component_index)
+                .expect("Invalid instance component index (pointed to by mesh)!").as_any().downcast_ref::<InstanceComponent>() {
+                    Some(instance_component) => render_pass.set_vertex_buffer(1, instance_component.instance_buffer.slice(..)),
+                    None => {
+                        warn!("Instance component pointed to by mesh component (via index {}) isn't actually an instance. Not rendering!",
+                            mesh_component.instance_component_index);
+
+                        continue;
+                    }
                 }
+
+            render_pass.set_index_buffer(mesh_component.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+            for sub_component_index in 0..parent_object_components.len() {
+                    let sub_component = self.get_active_scene().components.get(sub_component_index)
+                        .expect("Invalid sub component index!");
+                    match sub_component.as_any().downcast_ref::<InstanceComponent>() {
+                        Some(instance_component) => {
+                            render_pass.draw_indexed(0..mesh_component.num_indices, 0, 0..instance_component.instances.len() as _);
+                            stats.record_draw(mesh_component.topology, mesh_component.num_indices, instance_component.instances.len() as _);
+                        },
+                        None => {
+                            // Regular drawing
+                            render_pass.draw_indexed(0..mesh_component.num_indices, 0, 0..1);
+                            stats.record_draw(mesh_component.topology, mesh_component.num_indices, 1);
+                        },
+                    }
             }
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        self.render_system.draw(&self.world, &self.render_pipeline, &self.default_material.bind_group, prepass,
+            render_pass, stats);
+    }
 
-        Ok(())
+    /// The active scene's meshes (with the instances they point at), and the world's casters.
+    fn shadow_casters(&self) -> Vec<(&MeshComponent, &InstanceComponent)> {
+        let components = &self.get_active_scene().components;
+        let mut casters = components.iter()
+            .filter_map(|component| component.as_any().downcast_ref::<MeshComponent>())
+            .filter(|mesh| mesh.topology == wgpu::PrimitiveTopology::TriangleList)
+            .filter_map(|mesh| Some((mesh, components.get(mesh.instance_component_index)?
+                .as_any().downcast_ref::<InstanceComponent>()?)))
+            .collect::<Vec<_>>();
+
+        casters.extend(ShadowPass::shadow_casters(&self.world));
+        casters
     }
 
     fn get_active_scene(&self) -> &Scene {
         self.scenes.get(self.active_scene_index)
-            .expect(format!("Invalid active scene index ({})!", self.active_scene_index).as_str())
+            .unwrap_or_else(|| panic!("Invalid active scene index ({})!", self.active_scene_index))
     }
 }
 
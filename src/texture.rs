@@ -1,4 +1,6 @@
+#[cfg(feature = "image-loading")]
 use anyhow::Result;
+#[cfg(feature = "image-loading")]
 use image::GenericImageView;
 
 pub struct Texture {
@@ -10,6 +12,7 @@ pub struct Texture {
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+    #[cfg(feature = "image-loading")]
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -21,6 +24,7 @@ impl Texture {
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    #[cfg(feature = "image-loading")]
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -30,16 +34,39 @@ impl Texture {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
+        Ok(Self::from_rgba(device, queue, &rgba, dimensions, label))
+    }
+
+    pub fn from_rgba(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: &[u8],
+        dimensions: (u32, u32),
+        label: Option<&str>
+    ) -> Self {
+        Self::from_rgba_layers(device, queue, &[rgba], dimensions, wgpu::TextureViewDimension::D2, label)
+    }
+
+    /// Like `from_rgba`, but with every one of `layers` (all `dimensions` big) in its own layer
+    /// of one texture, viewed as `view_dimension` (`D2Array`, usually).
+    pub fn from_rgba_layers(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[&[u8]],
+        dimensions: (u32, u32),
+        view_dimension: wgpu::TextureViewDimension,
+        label: Option<&str>
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: dimensions.0,
             height: dimensions.1,
-            depth_or_array_layers: 1,
+            depth_or_array_layers: layers.len().max(1) as u32,
         };
         let texture = device.create_texture(
             &wgpu::TextureDescriptor {
                 label,
                 size,
-                mip_level_count: 1,
+                mip_level_count: Self::mip_level_count(dimensions),
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
                 format: wgpu::TextureFormat::Rgba8UnormSrgb,
@@ -47,39 +74,73 @@ impl Texture {
             }
         );
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-                rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-            },
-            size,
-        );
+        // Each level gets box filtered on the CPU from the one above it. Odd sizes just drop the
+        // last row/column, so non-power-of-two textures get a full chain too.
+        for (layer, rgba) in layers.iter().enumerate() {
+            let mut level = rgba.to_vec();
+            let mut level_dimensions = dimensions;
 
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            for mip_level in 0..Self::mip_level_count(dimensions) {
+                if mip_level > 0 {
+                    (level, level_dimensions) = downsample(&level, level_dimensions);
+                }
+
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        aspect: wgpu::TextureAspect::All,
+                        texture: &texture,
+                        mip_level,
+                        origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                    },
+                    &level,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: std::num::NonZeroU32::new(4 * level_dimensions.0),
+                        rows_per_image: std::num::NonZeroU32::new(level_dimensions.1),
+                    },
+                    wgpu::Extent3d {
+                        width: level_dimensions.0,
+                        height: level_dimensions.1,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
         let sampler = device.create_sampler(
             &wgpu::SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
                 address_mode_v: wgpu::AddressMode::ClampToEdge,
                 address_mode_w: wgpu::AddressMode::ClampToEdge,
                 mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
                 ..Default::default()
             }
         );
 
-        Ok(Self { texture, view, sampler })
+        Self { texture, view, sampler }
+    }
+
+    /// The full mip chain down to 1x1: `floor(log2(max(width, height))) + 1` levels.
+    pub fn mip_level_count(dimensions: (u32, u32)) -> u32 {
+        32 - dimensions.0.max(dimensions.1).max(1).leading_zeros()
     }
 
     pub fn create_depth_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str) -> Self {
+        Self::create_multisampled_depth_texture(device, config, 1, label)
+    }
+
+    pub fn create_multisampled_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        label: &str
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: config.width,
             height: config.height,
@@ -89,7 +150,7 @@ impl Texture {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT
@@ -117,3 +178,40 @@ impl Texture {
     }
 }
 
+
+/// Halves an RGBA8 image (rounding down, but never below 1x1) by averaging each 2x2 block.
+fn downsample(rgba: &[u8], (width, height): (u32, u32)) -> (Vec<u8>, (u32, u32)) {
+    let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+    let mut next = Vec::with_capacity((next_width * next_height * 4) as usize);
+
+    for y in 0..next_height {
+        for x in 0..next_width {
+            let texel = |dx: u32, dy: u32| {
+                let (sx, sy) = ((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+                ((sy * width + sx) * 4) as usize
+            };
+            let samples = [texel(0, 0), texel(1, 0), texel(0, 1), texel(1, 1)];
+
+            for channel in 0..4 {
+                let sum: u32 = samples.iter().map(|&offset| rgba[offset + channel] as u32).sum();
+                next.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+
+    (next, (next_width, next_height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_256_has_nine_mips() {
+        assert_eq!(Texture::mip_level_count((256, 256)), 9);
+        assert_eq!(Texture::mip_level_count((256, 1)), 9);
+        assert_eq!(Texture::mip_level_count((255, 100)), 8);
+        assert_eq!(Texture::mip_level_count((1, 1)), 1);
+        assert_eq!(Texture::mip_level_count((0, 0)), 1);
+    }
+}